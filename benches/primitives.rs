@@ -0,0 +1,76 @@
+// Micro-benchmarks for the hot inner loop: vector math, intersections and scene traversal.
+//
+//     cargo bench --bench primitives
+#[macro_use]
+extern crate criterion;
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use criterion::{black_box, Criterion};
+use rust_tracer::ray::{intersect_plane, intersect_sphere, trace, Ray};
+use rust_tracer::material::{Lambertian, Material};
+use rust_tracer::scene::{create_scene, Plane, Sphere};
+use rust_tracer::vec3::Vec3;
+
+fn material() -> Arc<dyn Material> {
+    Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) })
+}
+
+fn vec3_ops(c: &mut Criterion) {
+    let a = Vec3::new(0.3, -1.2, 2.5);
+    let b = Vec3::new(-0.7, 0.4, 1.1);
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let incident = Vec3::new(0.3, 0.0, -1.0).unit();
+
+    let mut group = c.benchmark_group("vec3");
+    group.bench_function("add", |bench| bench.iter(|| black_box(&a) + black_box(&b)));
+    group.bench_function("mul_scalar", |bench| bench.iter(|| black_box(&a) * black_box(2.5)));
+    group.bench_function("dot", |bench| bench.iter(|| black_box(&a).dot(black_box(&b))));
+    group.bench_function("cross", |bench| bench.iter(|| black_box(&a).cross(black_box(&b))));
+    group.bench_function("unit", |bench| bench.iter(|| black_box(&a).unit()));
+    group.bench_function("reflect", |bench| bench.iter(|| black_box(&incident).reflect(black_box(&normal))));
+    group.bench_function("refract", |bench| bench.iter(|| black_box(&incident).refract(black_box(&normal), black_box(1.0 / 1.52))));
+    group.finish();
+}
+
+fn intersections(c: &mut Criterion) {
+    let sphere = Sphere {
+        position: Vec3::new(0.0, 0.0, -3.0),
+        radius: 1.0,
+        material: material(),
+    };
+    let plane = Plane {
+        position: Vec3::new(0.0, 1.0, 0.0),
+        normal: Vec3::new(0.0, -1.0, 0.0),
+        material: material(),
+    };
+    let hit_ray = Ray {
+        origin: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.1, 0.2, -1.0),
+    };
+    let miss_ray = Ray {
+        origin: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.0, -1.0, 0.2),
+    };
+
+    let mut group = c.benchmark_group("intersect");
+    group.bench_function("sphere_hit", |bench| bench.iter(|| intersect_sphere(black_box(&hit_ray), black_box(&sphere)).is_ok()));
+    group.bench_function("sphere_miss", |bench| bench.iter(|| intersect_sphere(black_box(&miss_ray), black_box(&sphere)).is_ok()));
+    group.bench_function("plane_hit", |bench| bench.iter(|| intersect_plane(black_box(&hit_ray), black_box(&plane)).is_ok()));
+    group.bench_function("plane_miss", |bench| bench.iter(|| intersect_plane(black_box(&miss_ray), black_box(&plane)).is_ok()));
+    group.finish();
+}
+
+fn scene_trace(c: &mut Criterion) {
+    let scene = create_scene();
+    let ray = Ray {
+        origin: Vec3::new(0.0, -0.6, 2.0),
+        direction: Vec3::new(0.05, 0.3, -1.0),
+    };
+
+    c.bench_function("trace/demo_scene", |bench| bench.iter(|| trace(black_box(&ray), black_box(&scene)).t));
+}
+
+criterion_group!(benches, vec3_ops, intersections, scene_trace);
+criterion_main!(benches);
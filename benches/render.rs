@@ -0,0 +1,73 @@
+// Macro-benchmarks rendering the demo scene end to end at a few resolutions.
+//
+//     cargo bench --bench render
+#[macro_use]
+extern crate criterion;
+extern crate rust_tracer;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use rust_tracer::camera::{create_camera, create_ray_from_camera, Camera};
+use rust_tracer::film::Film;
+use rust_tracer::integrator::Whitted;
+use rust_tracer::render::{render, CancelToken, RenderSettings, Screen};
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::create_scene;
+use rust_tracer::tracer::trace_ray;
+use rust_tracer::vec2::Vec2;
+use rust_tracer::vec3::Vec3;
+
+const RESOLUTIONS: [(u32, u32); 3] = [(80, 60), (160, 120), (320, 240)];
+
+fn demo_camera(screen: &Screen) -> Camera {
+    let aspect_ratio = screen.width as f64 / screen.height as f64;
+    create_camera(
+        Vec3::new(0.0, -0.6, 2.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        45.0,
+        aspect_ratio,
+        Vec3::new(0.0, 1.0, 0.0),
+    )
+}
+
+fn single_ray(c: &mut Criterion) {
+    let scene = create_scene();
+    let camera = demo_camera(&Screen { width: 800, height: 600 });
+
+    // Through the reflective sphere, so it exercises the bounce loop
+    c.bench_function("trace_ray/demo_reflective_sphere", |bench| {
+        let mut sampler = Sampler::new(0, 0, 0);
+        bench.iter(|| trace_ray(create_ray_from_camera(&camera, &Vec2::new(0.35, 0.45)), &scene, None, &mut sampler))
+    });
+}
+
+fn full_frame(c: &mut Criterion) {
+    let scene = create_scene();
+    let mut settings = RenderSettings {
+        integrator: Box::new(Whitted::default()),
+        samples_per_pixel: 1,
+        seed: 0,
+        checkpoint: None,
+    };
+
+    let mut group = c.benchmark_group("render/demo_scene");
+    group.sample_size(10);
+
+    for &(width, height) in RESOLUTIONS.iter() {
+        let screen = Screen { width, height };
+        let camera = demo_camera(&screen);
+
+        group.throughput(Throughput::Elements((width * height) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}x{}", width, height)), &screen, |bench, screen| {
+            bench.iter(|| {
+                let mut film = Film::new(screen.width, screen.height);
+                render(&scene, &camera, screen, &mut settings, &mut film, &CancelToken::new(), |_| {}).unwrap();
+                film
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, single_ray, full_frame);
+criterion_main!(benches);
@@ -0,0 +1,507 @@
+use emitter::{emitters, Emitter};
+use image::Color;
+use lighting::{environment_lighting, offset_ray_origin, shadow_transmittance, visible, Mis};
+use material::Material;
+use optics::beer_lambert;
+use path_tracer::russian_roulette;
+use ray::{trace, Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use tracer::display_color;
+use vec3::Vec3;
+use warp::cosine_hemisphere_pdf;
+
+// Vertices per subpath, not counting where it starts
+const MAX_VERTICES: u32 = 16;
+
+// Bidirectional path tracing: a path from the camera and one from a light, every
+// vertex of one connected to every vertex of the other. Each connection is a
+// different way of sampling the same kind of path, weighted against all the others
+// with the power heuristic (Veach's thesis, chapter 10), so light squeezing through
+// a gap is found from the light's side and glossy reflections from the camera's.
+//
+// Light paths start on point, spot and area lights and on emissive spheres, quads
+// and meshes. Directional lights and the background have no position to start
+// from, they are sampled from the camera path like the path tracer does. Light paths
+// aren't joined to the camera itself, which would need splatting onto other pixels,
+// so caustics seen directly are only found through their light.
+pub fn trace_bidirectional(initial_ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+    display_color(bidirectional_radiance(initial_ray, scene, sampler))
+}
+
+pub fn bidirectional_radiance(initial_ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let emitters = emitters(scene);
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    let mut camera_path = Vec::new();
+    let escape = random_walk(scene, initial_ray, Vec3::new(1.0, 1.0, 1.0), 1.0, None, sampler, &mut camera_path);
+
+    if let Some(escape) = escape.escape {
+        color = color + escape_radiance(&escape, scene);
+    }
+    if camera_path.is_empty() {
+        return color;
+    }
+
+    let mut light_path = Vec::new();
+    let light_origin = start_light_path(&emitters, scene, sampler, &mut light_path);
+
+    // Strategies are named by how many vertices come from the light (s) and from the
+    // camera (t), the camera itself counting as one
+    for index in 0..camera_path.len() {
+        let vertex = &camera_path[index];
+        let camera = PathEnd {
+            vertex,
+            previous: if index > 0 { Some((&camera_path[index - 1].point, Some(&camera_path[index - 1].normal))) } else { None },
+            pdfs: camera_pdfs(&camera_path[..=index]),
+        };
+
+        color = color + &vertex.throughput * &infinite_lighting(vertex, scene, sampler);
+        color = color + hit_emitter(&camera, emitters.len());
+        color = color + connect_to_light(&camera, &emitters, scene, sampler);
+
+        if let Some(ref origin) = light_origin {
+            for s in 2..light_path.len() + 2 {
+                let previous = match s {
+                    2 => (&origin.point, origin.normal.as_ref()),
+                    _ => (&light_path[s - 3].point, Some(&light_path[s - 3].normal)),
+                };
+                let light = PathEnd {
+                    vertex: &light_path[s - 2],
+                    previous: Some(previous),
+                    pdfs: light_pdfs(origin, &light_path[..s - 1]),
+                };
+                color = color + connect(&light, &camera, origin.emitter.hittable(), scene);
+            }
+        }
+    }
+
+    color
+}
+
+// Where a light path starts
+struct LightOrigin<'a, 'b: 'a> {
+    emitter: &'a Emitter<'b>,
+    point: Vec3,
+    normal: Option<Vec3>,
+    // Of picking this emitter and this point on it, per unit of area
+    pdf: f64,
+    // Of sampling it from the light path's first vertex, per unit of area
+    pdf_reverse: f64,
+}
+
+fn sample_light_origin<'a, 'b>(emitters: &'a [Emitter<'b>], sampler: &mut Sampler) -> Option<LightOrigin<'a, 'b>> {
+    if emitters.is_empty() {
+        return None;
+    }
+
+    let index = ((sampler.next_f64() * emitters.len() as f64) as usize).min(emitters.len() - 1);
+    let emitter = &emitters[index];
+    let (point, normal, pdf) = emitter.sample_point(sampler.next_f64(), sampler.next_f64());
+
+    Some(LightOrigin {
+        emitter,
+        point,
+        normal,
+        pdf: pdf / emitters.len() as f64,
+        pdf_reverse: 0.0,
+    })
+}
+
+fn start_light_path<'a, 'b>(
+    emitters: &'a [Emitter<'b>],
+    scene: &'b Scene,
+    sampler: &mut Sampler,
+    light_path: &mut Vec<Vertex<'b>>,
+) -> Option<LightOrigin<'a, 'b>> {
+    let mut origin = sample_light_origin(emitters, sampler)?;
+    let (direction, direction_pdf) = origin.emitter.sample_direction(origin.normal.as_ref(), sampler.next_f64(), sampler.next_f64());
+    let emitted = origin.emitter.emitted(origin.normal.as_ref(), &direction);
+    if direction_pdf <= 0.0 || emitted.max_component() <= 0.0 {
+        return Some(origin);
+    }
+
+    let cos_light = origin.normal.as_ref().map_or(1.0, |normal| normal.dot(&direction).abs());
+    let throughput = emitted * (cos_light / (origin.pdf * direction_pdf));
+    let ray = Ray {
+        origin: match origin.normal {
+            Some(ref normal) => offset_ray_origin(&origin.point, normal, &direction),
+            None => origin.point.clone(),
+        },
+        direction,
+    };
+
+    let walk = random_walk(scene, ray, throughput, direction_pdf, origin.normal.as_ref(), sampler, light_path);
+    origin.pdf_reverse = walk.origin_pdf_reverse;
+    Some(origin)
+}
+
+// A scattering point on either path
+struct Vertex<'a> {
+    point: Vec3,
+    normal: Vec3,
+    material: &'a dyn Material,
+    // Unit vector towards the previous vertex of its path
+    wo: Vec3,
+    // Light (or the camera's importance) carried here from the start of its path,
+    // over the density of the path so far
+    throughput: Vec3,
+    // Per unit of area, of sampling this vertex from the previous one and the
+    // previous one from this one. Zero around specular bounces.
+    pdf_forward: f64,
+    pdf_reverse: f64,
+    // Left by a specular lobe, connections can't go through it
+    specular: bool,
+    // Of the whole object hit
+    area: f64,
+}
+
+// How a path left the scene
+struct Escape {
+    direction: Vec3,
+    throughput: Vec3,
+    // Of the BSDF sample that left, per solid angle
+    pdf: f64,
+    specular: bool,
+}
+
+struct Walk {
+    // Of sampling the path's start from its first vertex, per unit of area
+    origin_pdf_reverse: f64,
+    escape: Option<Escape>,
+}
+
+// Follows BSDF samples from `ray`, picked with `pdf` per solid angle, adding a vertex per hit
+fn random_walk<'a>(
+    scene: &'a Scene,
+    mut ray: Ray,
+    mut throughput: Vec3,
+    mut pdf: f64,
+    origin_normal: Option<&Vec3>,
+    sampler: &mut Sampler,
+    path: &mut Vec<Vertex<'a>>,
+) -> Walk {
+    let mut origin_pdf_reverse = 0.0;
+    // The camera sees the background directly, like a specular bounce would
+    let mut specular = true;
+
+    for bounces in 0..MAX_VERTICES {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            let escape = Escape { direction: ray.direction, throughput, pdf, specular };
+            return Walk { origin_pdf_reverse, escape: Some(escape) };
+        }
+
+        let direction = ray.direction.unit();
+        let distance = intersection.t * ray.direction.mag();
+        let point = &ray.origin + &(&ray.direction * intersection.t);
+        let material = intersection.material;
+        let normal = intersection.normal;
+        let wo = &direction * -1.0;
+
+        // Hit from the inside, the path went through the object
+        if direction.dot(&normal) > 0.0 {
+            throughput = throughput * beer_lambert(&material.absorption(), distance);
+        }
+
+        path.push(Vertex {
+            pdf_forward: pdf * normal.dot(&direction).abs() / (distance * distance),
+            pdf_reverse: 0.0,
+            point: point.clone(),
+            normal: normal.clone(),
+            material,
+            wo: wo.clone(),
+            throughput: throughput.clone(),
+            specular: false,
+            area: intersection.area,
+        });
+
+        let sample = match material.sample(&normal, &wo, sampler) {
+            Some(sample) => sample,
+            None => break,
+        };
+        specular = sample.specular;
+        pdf = if specular { 0.0 } else { sample.pdf };
+
+        // Back the way the path came
+        let reverse = if specular { 0.0 } else { material.pdf(&normal, &sample.direction, &wo) };
+        let index = path.len() - 1;
+        path[index].specular = specular;
+        if index == 0 {
+            let cos_origin = origin_normal.map_or(1.0, |origin_normal| origin_normal.dot(&direction).abs());
+            origin_pdf_reverse = reverse * cos_origin / (distance * distance);
+        } else {
+            path[index - 1].pdf_reverse = reverse * path[index - 1].normal.dot(&direction).abs() / (distance * distance);
+        }
+
+        throughput = throughput * &sample.weight;
+        match russian_roulette(&throughput, bounces, sampler) {
+            Some(survival) => throughput = throughput * (1.0 / survival),
+            None => break,
+        }
+
+        ray = Ray {
+            origin: offset_ray_origin(&point, &normal, &sample.direction),
+            direction: sample.direction,
+        };
+    }
+
+    Walk { origin_pdf_reverse, escape: None }
+}
+
+// The last vertex of a path being connected, with the point and normal of the one
+// before it unless that is the camera, and the densities of the whole path
+struct PathEnd<'p, 'a: 'p> {
+    vertex: &'p Vertex<'a>,
+    previous: Option<(&'p Vec3, Option<&'p Vec3>)>,
+    pdfs: Vec<PathPdf>,
+}
+
+impl<'p, 'a> PathEnd<'p, 'a> {
+    // Of the vertex before this one, when sampled from this one towards `wi`
+    fn previous_pdf(&self, wi: &Vec3) -> Option<f64> {
+        let (point, normal) = self.previous?;
+        let vertex = self.vertex;
+        let pdf = vertex.material.pdf(&vertex.normal, wi, &vertex.wo);
+        Some(area_pdf(pdf, &vertex.point, point, normal))
+    }
+}
+
+// The densities of one path vertex, copied so connections can change them
+#[derive(Debug, Clone)]
+struct PathPdf {
+    forward: f64,
+    reverse: f64,
+    specular: bool,
+}
+
+// The camera first, its densities never get used
+fn camera_pdfs(path: &[Vertex]) -> Vec<PathPdf> {
+    let mut pdfs = vec![PathPdf { forward: 1.0, reverse: 1.0, specular: false }];
+    pdfs.extend(path.iter().map(vertex_pdf));
+    pdfs
+}
+
+fn light_pdfs(origin: &LightOrigin, path: &[Vertex]) -> Vec<PathPdf> {
+    let mut pdfs = vec![PathPdf { forward: origin.pdf, reverse: origin.pdf_reverse, specular: false }];
+    pdfs.extend(path.iter().map(vertex_pdf));
+    pdfs
+}
+
+fn vertex_pdf(vertex: &Vertex) -> PathPdf {
+    PathPdf {
+        forward: vertex.pdf_forward,
+        reverse: vertex.pdf_reverse,
+        specular: vertex.specular,
+    }
+}
+
+// Power heuristic weight of the strategy that made this path, against every other
+// split of the same vertices between the light and the camera path. Walking away from
+// the connection, each vertex's reverse over forward density turns one strategy's
+// path density into the next one's. Specular vertices have zero densities that cancel
+// out, strategies connecting to them can't work and are skipped. Only camera paths
+// can end on `hittable` lights.
+fn mis_weight(light: &[PathPdf], camera: &[PathPdf], hittable: bool) -> f64 {
+    let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+    let mut others = 0.0;
+
+    // Strategies with one camera vertex aren't used, so stop before the first hit
+    let mut ratio = 1.0;
+    for i in (2..camera.len()).rev() {
+        ratio *= remap(camera[i].reverse) / remap(camera[i].forward);
+        if !camera[i].specular && !camera[i - 1].specular {
+            others += ratio * ratio;
+        }
+    }
+
+    let mut ratio = 1.0;
+    for i in (0..light.len()).rev() {
+        ratio *= remap(light[i].reverse) / remap(light[i].forward);
+        let possible = if i > 0 {
+            !light[i].specular && !light[i - 1].specular
+        } else {
+            hittable
+        };
+        if possible {
+            others += ratio * ratio;
+        }
+    }
+
+    1.0 / (1.0 + others)
+}
+
+// Of sampling `to` from `from` with `pdf` per solid angle, per unit of area. Points
+// without a normal are point lights.
+fn area_pdf(pdf: f64, from: &Vec3, to: &Vec3, to_normal: Option<&Vec3>) -> f64 {
+    let offset = to - from;
+    let distance_sq = offset.dot(&offset);
+    let cos = to_normal.map_or(1.0, |normal| normal.dot(&offset).abs() / distance_sq.sqrt());
+    pdf * cos / distance_sq
+}
+
+// s = 0: the camera path ran into an emissive surface by itself
+fn hit_emitter(camera: &PathEnd, emitter_count: usize) -> Vec3 {
+    let vertex = camera.vertex;
+    let emitted = vertex.material.emission(&vertex.normal, &vertex.wo);
+    if emitted.max_component() <= 0.0 {
+        return emitted;
+    }
+    // Infinite planes and glowing materials that aren't lights can't be sampled any other way
+    if !vertex.material.is_emissive() || vertex.area >= INFINITY {
+        return &vertex.throughput * &emitted;
+    }
+
+    // As if a light path had started here towards the previous vertex
+    let mut pdfs = camera.pdfs.clone();
+    let last = pdfs.len() - 1;
+    pdfs[last].reverse = 1.0 / (emitter_count as f64 * vertex.area);
+    if let Some((point, normal)) = camera.previous {
+        let direction_pdf = cosine_hemisphere_pdf(vertex.normal.dot(&vertex.wo));
+        pdfs[last - 1].reverse = area_pdf(direction_pdf, &vertex.point, point, normal);
+    }
+
+    &vertex.throughput * &emitted * mis_weight(&[], &pdfs, true)
+}
+
+// s = 1: a new point picked on a light, joined to the camera path
+fn connect_to_light(camera: &PathEnd, emitters: &[Emitter], scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let black = Vec3::new(0.0, 0.0, 0.0);
+    let vertex = camera.vertex;
+    let origin = match sample_light_origin(emitters, sampler) {
+        Some(origin) => origin,
+        None => return black,
+    };
+    if vertex.specular {
+        return black;
+    }
+
+    let to_light = &origin.point - &vertex.point;
+    let distance_sq = to_light.dot(&to_light);
+    let wi = &to_light * (1.0 / distance_sq.sqrt());
+    let from_light = &wi * -1.0;
+
+    let emitted = origin.emitter.emitted(origin.normal.as_ref(), &from_light);
+    let bsdf = vertex.material.evaluate(&vertex.normal, &vertex.wo, &wi);
+    if emitted.max_component() <= 0.0 || bsdf.max_component() <= 0.0 {
+        return black;
+    }
+    if !visible(&vertex.point, &vertex.normal, &origin.point, scene) {
+        return black;
+    }
+
+    let cos_camera = vertex.normal.dot(&wi).abs();
+    let cos_light = origin.normal.as_ref().map_or(1.0, |normal| normal.dot(&wi).abs());
+    let contribution = &vertex.throughput * &bsdf * &emitted * (cos_camera * cos_light / (distance_sq * origin.pdf));
+
+    let light = [PathPdf {
+        forward: origin.pdf,
+        reverse: vertex.material.pdf(&vertex.normal, &vertex.wo, &wi) * cos_light / distance_sq,
+        specular: false,
+    }];
+    let mut pdfs = camera.pdfs.clone();
+    let last = pdfs.len() - 1;
+    pdfs[last].reverse = origin.emitter.direction_pdf(origin.normal.as_ref(), &from_light) * cos_camera / distance_sq;
+    if let Some(pdf) = camera.previous_pdf(&wi) {
+        pdfs[last - 1].reverse = pdf;
+    }
+
+    contribution * mis_weight(&light, &pdfs, origin.emitter.hittable())
+}
+
+// s >= 2: the light path's last vertex joined to the camera path's last vertex
+fn connect(light: &PathEnd, camera: &PathEnd, hittable: bool, scene: &Scene) -> Vec3 {
+    let black = Vec3::new(0.0, 0.0, 0.0);
+    let (light_vertex, vertex) = (light.vertex, camera.vertex);
+    if light_vertex.specular || vertex.specular {
+        return black;
+    }
+
+    let to_camera = &vertex.point - &light_vertex.point;
+    let distance_sq = to_camera.dot(&to_camera);
+    let to_camera = &to_camera * (1.0 / distance_sq.sqrt());
+    let to_light = &to_camera * -1.0;
+
+    let light_bsdf = light_vertex.material.evaluate(&light_vertex.normal, &light_vertex.wo, &to_camera);
+    let camera_bsdf = vertex.material.evaluate(&vertex.normal, &vertex.wo, &to_light);
+    if light_bsdf.max_component() <= 0.0 || camera_bsdf.max_component() <= 0.0 {
+        return black;
+    }
+    if !visible(&vertex.point, &vertex.normal, &light_vertex.point, scene) {
+        return black;
+    }
+
+    let cos_light = light_vertex.normal.dot(&to_camera).abs();
+    let cos_camera = vertex.normal.dot(&to_camera).abs();
+    let geometry = cos_light * cos_camera / distance_sq;
+    let contribution = &light_vertex.throughput * &light_bsdf * &camera_bsdf * &vertex.throughput * geometry;
+
+    let mut light_pdfs = light.pdfs.clone();
+    let mut camera_pdfs = camera.pdfs.clone();
+    let last_light = light_pdfs.len() - 1;
+    let last_camera = camera_pdfs.len() - 1;
+
+    let pdf = light_vertex.material.pdf(&light_vertex.normal, &light_vertex.wo, &to_camera);
+    camera_pdfs[last_camera].reverse = pdf * cos_camera / distance_sq;
+    if let Some(pdf) = camera.previous_pdf(&to_light) {
+        camera_pdfs[last_camera - 1].reverse = pdf;
+    }
+    let pdf = vertex.material.pdf(&vertex.normal, &vertex.wo, &to_light);
+    light_pdfs[last_light].reverse = pdf * cos_light / distance_sq;
+    if let Some(pdf) = light.previous_pdf(&to_camera) {
+        light_pdfs[last_light - 1].reverse = pdf;
+    }
+
+    contribution * mis_weight(&light_pdfs, &camera_pdfs, hittable)
+}
+
+// Directional lights and the environment, which light paths can't start from
+fn infinite_lighting(vertex: &Vertex, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    if vertex.specular {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    let mut color = environment_lighting(
+        &vertex.wo,
+        &vertex.point,
+        &vertex.normal,
+        vertex.material,
+        scene,
+        sampler,
+        Mis::PowerHeuristic,
+    );
+
+    for light in scene.lights.iter().filter(|light| light.position().is_none()) {
+        let sample = match light.sample(&vertex.point, sampler.next_f64(), sampler.next_f64()) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let light_ray = Ray {
+            origin: offset_ray_origin(&vertex.point, &vertex.normal, &sample.direction),
+            direction: sample.direction.clone(),
+        };
+        // Through fog and smoke like the other integrators' shadow rays
+        let transmittance = shadow_transmittance(&light_ray, sample.distance, scene, sampler);
+        if transmittance.max_component() <= 0.0 {
+            continue;
+        }
+        let light_on_surface = vertex.normal.dot(&sample.direction).abs();
+        let irradiance = sample.irradiance * &transmittance * light_on_surface;
+        color = color + vertex.material.evaluate(&vertex.normal, &vertex.wo, &sample.direction) * &irradiance;
+    }
+
+    color
+}
+
+// The background, where the camera path left the scene
+fn escape_radiance(escape: &Escape, scene: &Scene) -> Vec3 {
+    let background = &scene.background;
+    let samples = background.samples();
+    let weight = if escape.specular || samples == 0 {
+        1.0
+    } else {
+        Mis::PowerHeuristic.weight(escape.pdf, samples as f64 * background.pdf(&escape.direction))
+    };
+
+    &escape.throughput * &background.radiance(&escape.direction) * weight
+}
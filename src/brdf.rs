@@ -0,0 +1,68 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+
+// Below this GGX becomes a spike that a point light can never hit
+const MIN_ALPHA: f64 = 0.001;
+// Under this roughness surfaces are treated as perfect mirrors
+const SMOOTH_ROUGHNESS: f64 = 0.02;
+
+pub fn roughness_to_alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+pub fn is_smooth(roughness: f64) -> bool {
+    roughness < SMOOTH_ROUGHNESS
+}
+
+pub fn ggx_distribution(n_dot_h: f64, alpha: f64) -> f64 {
+    let alpha_sq = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha_sq - 1.0) + 1.0;
+    alpha_sq / (PI * denominator * denominator)
+}
+
+// Smith masking for one direction, the full shadowing term is G1(v) * G1(l)
+pub fn smith_g1(n_dot_x: f64, alpha: f64) -> f64 {
+    let alpha_sq = alpha * alpha;
+    2.0 * n_dot_x / (n_dot_x + (alpha_sq + (1.0 - alpha_sq) * n_dot_x * n_dot_x).sqrt())
+}
+
+// D * G / (4 cos_o cos_i), the Fresnel factor is left to the caller.
+// Both directions must be on the normal's side.
+pub fn ggx_reflection(normal: &Vec3, wo: &Vec3, wi: &Vec3, alpha: f64) -> f64 {
+    let cos_o = normal.dot(wo);
+    let cos_i = normal.dot(wi);
+
+    if cos_o <= 0.0 || cos_i <= 0.0 {
+        return 0.0;
+    }
+
+    let half = (wo + wi).unit();
+    let distribution = ggx_distribution(normal.dot(&half).max(0.0), alpha);
+    let shadowing = smith_g1(cos_o, alpha) * smith_g1(cos_i, alpha);
+
+    distribution * shadowing / (4.0 * cos_o * cos_i)
+}
+
+// Microfacet normal distributed as D(h) * cos(theta_h), in local space (z up)
+pub fn sample_ggx_half_vector(alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let tan_theta_sq = alpha * alpha * u1 / (1.0 - u1).max(1e-12);
+    let cos_theta = 1.0 / (1.0 + tan_theta_sq).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+// Density of wi when it is the reflection of wo over a half vector from `sample_ggx_half_vector`
+pub fn ggx_reflection_pdf(normal: &Vec3, wo: &Vec3, wi: &Vec3, alpha: f64) -> f64 {
+    if normal.dot(wo) <= 0.0 || normal.dot(wi) <= 0.0 {
+        return 0.0;
+    }
+
+    let half = (wo + wi).unit();
+    let n_dot_h = normal.dot(&half).max(0.0);
+    let w_dot_h = wo.dot(&half).abs().max(1e-12);
+
+    ggx_distribution(n_dot_h, alpha) * n_dot_h / (4.0 * w_dot_h)
+}
@@ -0,0 +1,48 @@
+use ray::Ray;
+use vec2::Vec2;
+use vec3::Vec3;
+
+#[derive(Debug)]
+pub struct CameraSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug)]
+pub struct Camera {
+    pub origin: Vec3,
+    pub target: Vec3,
+    pub forward: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    pub size: CameraSize,
+}
+
+pub fn create_camera(origin: Vec3, target: Vec3, fov: f64, aspect_ratio: f64, up_guide: Vec3) -> Camera {
+    let height = (fov.to_radians() * 0.5).tan();
+    let width = height * aspect_ratio;
+    let forward = (&target - &origin).unit();
+    let right = forward.cross(&up_guide).unit();
+    let up = right.cross(&forward).unit();
+    let size = CameraSize { width, height };
+
+    Camera {
+        origin,
+        target,
+        forward,
+        right,
+        up,
+        size,
+    }
+}
+
+pub fn create_ray_from_camera(camera :&Camera, px: &Vec2) -> Ray {
+    let x_offset = camera.right.clone() * px.x * camera.size.width;
+    let y_offset = camera.up.clone() * px.y * camera.size.height;
+    let ray_direction = camera.forward.clone() + x_offset + y_offset;
+
+    Ray {
+        origin: camera.origin.clone(),
+        direction: ray_direction,
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use camera::Camera;
+use film::Film;
+use integrator::Integrator;
+use render::Screen;
+use scene::Scene;
+use vec3::Vec3;
+
+const MAGIC: &[u8; 8] = b"RTCKPT01";
+// Magic, scene hash, seed, width and height
+const HEADER_BYTES: u64 = 8 + 8 + 8 + 4 + 4;
+// Sum of the samples and how many there are
+const PIXEL_BYTES: u64 = 3 * 8 + 4;
+
+#[derive(Debug)]
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub seed: u64,
+    pub film: Film,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    InvalidFormat,
+    SceneChanged,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckpointError::Io(ref err) => write!(f, "{}", err),
+            CheckpointError::InvalidFormat => write!(f, "not a checkpoint file"),
+            CheckpointError::SceneChanged => write!(f, "scene, camera, resolution or integrator changed since the checkpoint was saved"),
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> CheckpointError {
+        CheckpointError::Io(err)
+    }
+}
+
+// FNV-1a over the debug representation. Rust prints floats with enough digits to
+// round-trip, so any change to geometry, materials, lights or camera changes the hash.
+// Samples from different integrators don't average to anything either.
+pub fn scene_hash(scene: &Scene, camera: &Camera, screen: &Screen, integrator: &dyn Integrator) -> u64 {
+    let description = format!("{:?}{:?}{:?}{:?}", scene, camera, screen, integrator);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for byte in description.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    hash
+}
+
+impl Checkpoint {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_checkpoint(path, self.scene_hash, self.seed, &self.film)
+    }
+
+    pub fn load(path: &Path) -> Result<Checkpoint, CheckpointError> {
+        Checkpoint::read(path, None)
+    }
+
+    // Refuses to continue accumulating samples of a different scene into this film
+    pub fn resume(path: &Path, scene: &Scene, camera: &Camera, screen: &Screen, integrator: &dyn Integrator) -> Result<Checkpoint, CheckpointError> {
+        Checkpoint::read(path, Some((scene_hash(scene, camera, screen, integrator), screen)))
+    }
+
+    // With an expected scene hash and screen, checked before the film gets allocated
+    fn read(path: &Path, expected: Option<(u64, &Screen)>) -> Result<Checkpoint, CheckpointError> {
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::InvalidFormat);
+        }
+
+        let scene_hash = read_u64(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+
+        if let Some((expected_hash, screen)) = expected {
+            if scene_hash != expected_hash || width != screen.width || height != screen.height {
+                return Err(CheckpointError::SceneChanged);
+            }
+        }
+        // A corrupt header mustn't get a huge film allocated, the pixels have to be in the file
+        let pixel_bytes = (width as u64).checked_mul(height as u64).and_then(|pixels| pixels.checked_mul(PIXEL_BYTES));
+        if pixel_bytes != Some(file_length - HEADER_BYTES) {
+            return Err(CheckpointError::InvalidFormat);
+        }
+
+        let mut film = Film::new(width, height);
+        for index in 0..film.samples.len() {
+            let x = read_f64(&mut reader)?;
+            let y = read_f64(&mut reader)?;
+            let z = read_f64(&mut reader)?;
+            film.sums[index] = Vec3::new(x, y, z);
+            film.samples[index] = read_u32(&mut reader)?;
+        }
+
+        Ok(Checkpoint { scene_hash, seed, film })
+    }
+}
+
+// Written to a temporary file first so a crash mid-write never clobbers the last good checkpoint
+pub fn write_checkpoint(path: &Path, scene_hash: u64, seed: u64, film: &Film) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&scene_hash.to_le_bytes())?;
+        writer.write_all(&seed.to_le_bytes())?;
+        writer.write_all(&film.width.to_le_bytes())?;
+        writer.write_all(&film.height.to_le_bytes())?;
+
+        for (sum, samples) in film.sums.iter().zip(&film.samples) {
+            writer.write_all(&sum.x.to_le_bytes())?;
+            writer.write_all(&sum.y.to_le_bytes())?;
+            writer.write_all(&sum.z.to_le_bytes())?;
+            writer.write_all(&samples.to_le_bytes())?;
+        }
+        writer.flush()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(reader: &mut R) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(reader)?))
+}
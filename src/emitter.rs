@@ -0,0 +1,130 @@
+use std::f64::consts::PI;
+
+use light::{AreaLight, Light, PointLight, SpotLight};
+use material::Material;
+use scene::{Mesh, Quad, Scene, Sphere, SurfaceSample};
+use vec3::Vec3;
+use warp::{cosine_hemisphere, cosine_hemisphere_pdf, uniform_cone, uniform_sphere, Frame};
+
+// Something light paths and photons can start from. Directional lights and the
+// background have no position to start from.
+#[derive(Debug)]
+pub enum Emitter<'a> {
+    Point(&'a PointLight),
+    Spot(&'a SpotLight),
+    Area(&'a AreaLight),
+    Sphere(&'a Sphere),
+    Quad(&'a Quad),
+    Mesh(&'a Mesh),
+}
+
+pub fn emitters<'a>(scene: &'a Scene) -> Vec<Emitter<'a>> {
+    let mut emitters = Vec::new();
+
+    for light in &scene.lights {
+        match *light {
+            Light::Point(ref light) => emitters.push(Emitter::Point(light)),
+            Light::Spot(ref light) => emitters.push(Emitter::Spot(light)),
+            Light::Area(ref light) => emitters.push(Emitter::Area(light)),
+            Light::Directional(_) => {}
+        }
+    }
+    emitters.extend(surface_emitters(scene));
+
+    emitters
+}
+
+// Emissive spheres, quads and meshes, the emitters that are part of the scene's geometry
+pub fn surface_emitters<'a>(scene: &'a Scene) -> Vec<Emitter<'a>> {
+    let mut emitters = Vec::new();
+
+    for sphere in scene.spheres.iter().filter(|sphere| sphere.material.is_emissive()) {
+        emitters.push(Emitter::Sphere(sphere));
+    }
+    for quad in scene.quads.iter().filter(|quad| quad.material.is_emissive()) {
+        emitters.push(Emitter::Quad(quad));
+    }
+    for mesh in scene.meshes.iter().filter(|mesh| mesh.material.is_emissive()) {
+        emitters.push(Emitter::Mesh(mesh));
+    }
+
+    emitters
+}
+
+impl<'a> Emitter<'a> {
+    // Point and spot lights are a single point without a normal
+    pub fn sample_point(&self, u1: f64, u2: f64) -> (Vec3, Option<Vec3>, f64) {
+        let surface = |sample: SurfaceSample| (sample.point, Some(sample.normal), sample.pdf);
+        match *self {
+            Emitter::Point(light) => (light.position.clone(), None, 1.0),
+            Emitter::Spot(light) => (light.position.clone(), None, 1.0),
+            Emitter::Area(light) => surface(light.sample_surface(u1, u2)),
+            Emitter::Sphere(sphere) => surface(sphere.sample_surface(u1, u2)),
+            Emitter::Quad(quad) => surface(quad.sample_surface(u1, u2)),
+            Emitter::Mesh(mesh) => surface(mesh.sample_surface(u1, u2)),
+        }
+    }
+
+    // A point on an emissive object, with what it is made of. None for lights.
+    pub fn sample_surface(&self, u1: f64, u2: f64) -> Option<(&'a dyn Material, SurfaceSample)> {
+        match *self {
+            Emitter::Sphere(sphere) => Some((&*sphere.material, sphere.sample_surface(u1, u2))),
+            Emitter::Quad(quad) => Some((&*quad.material, quad.sample_surface(u1, u2))),
+            Emitter::Mesh(mesh) => Some((&*mesh.material, mesh.sample_surface(u1, u2))),
+            Emitter::Point(_) | Emitter::Spot(_) | Emitter::Area(_) => None,
+        }
+    }
+
+    // Intensity of point and spot lights, radiance of the others
+    pub fn emitted(&self, normal: Option<&Vec3>, direction: &Vec3) -> Vec3 {
+        match (self, normal) {
+            (&Emitter::Point(light), _) => light.intensity(direction),
+            (&Emitter::Spot(light), _) => light.intensity(direction),
+            (&Emitter::Area(light), Some(normal)) => light.radiance(normal, direction),
+            (&Emitter::Sphere(sphere), Some(normal)) => sphere.material.emission(normal, direction),
+            (&Emitter::Quad(quad), Some(normal)) => quad.material.emission(normal, direction),
+            (&Emitter::Mesh(mesh), Some(normal)) => mesh.material.emission(normal, direction),
+            _ => Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Uniform for point lights, over the cone for spots, cosine weighted off surfaces
+    pub fn sample_direction(&self, normal: Option<&Vec3>, u1: f64, u2: f64) -> (Vec3, f64) {
+        match (self, normal) {
+            (&Emitter::Point(_), _) => (uniform_sphere(u1, u2), 1.0 / (4.0 * PI)),
+            (&Emitter::Spot(light), _) => {
+                let cos_max = light.cone_angle.to_radians().cos();
+                let frame = Frame::from_normal(&light.direction.unit());
+                (frame.to_world(&uniform_cone(cos_max, u1, u2)), 1.0 / (2.0 * PI * (1.0 - cos_max)))
+            }
+            (_, Some(normal)) => {
+                let local = cosine_hemisphere(u1, u2);
+                (Frame::from_normal(normal).to_world(&local), cosine_hemisphere_pdf(local.z))
+            }
+            (_, None) => (Vec3::new(0.0, 0.0, 1.0), 0.0),
+        }
+    }
+
+    pub fn direction_pdf(&self, normal: Option<&Vec3>, direction: &Vec3) -> f64 {
+        match (self, normal) {
+            (&Emitter::Point(_), _) => 1.0 / (4.0 * PI),
+            (&Emitter::Spot(light), _) => {
+                let cos_max = light.cone_angle.to_radians().cos();
+                if light.direction.unit().dot(direction) <= cos_max {
+                    return 0.0;
+                }
+                1.0 / (2.0 * PI * (1.0 - cos_max))
+            }
+            (_, Some(normal)) => cosine_hemisphere_pdf(normal.dot(direction)),
+            (_, None) => 0.0,
+        }
+    }
+
+    // Point, spot and area lights are invisible, camera paths can't run into them
+    pub fn hittable(&self) -> bool {
+        match *self {
+            Emitter::Point(_) | Emitter::Spot(_) | Emitter::Area(_) => false,
+            Emitter::Sphere(_) | Emitter::Quad(_) | Emitter::Mesh(_) => true,
+        }
+    }
+}
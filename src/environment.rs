@@ -0,0 +1,397 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use vec3::Vec3;
+
+// Light from infinitely far away in every direction, as an equirectangular image.
+// The top row is straight up (-y), the left edge looks towards -z and longitude grows
+// towards +x. `rotation` turns the map around the vertical axis, in degrees.
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+    pub rotation: f64,
+    pub strength: f64,
+    // Shadow rays towards it per shading point
+    pub samples: u32,
+    // Running totals of luminance * sin(latitude), along each row and then over the
+    // rows, to pick bright texels more often
+    row_cdfs: Vec<Vec<f64>>,
+    marginal_cdf: Vec<f64>,
+}
+
+// Direction picked on the environment, `pdf` is per solid angle
+#[derive(Debug)]
+pub struct EnvironmentSample {
+    pub direction: Vec3,
+    pub radiance: Vec3,
+    pub pdf: f64,
+}
+
+#[derive(Debug)]
+pub enum EnvironmentError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for EnvironmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EnvironmentError::Io(ref err) => write!(f, "{}", err),
+            EnvironmentError::Format(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for EnvironmentError {
+    fn from(err: io::Error) -> EnvironmentError {
+        EnvironmentError::Io(err)
+    }
+}
+
+// Pixels can be huge, the checkpoint hash only needs something that changes with them
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let checksum = self.pixels.iter().enumerate().fold(0.0, |sum, (index, pixel)| {
+            sum + (index % 97 + 1) as f64 * (pixel.x + 2.0 * pixel.y + 3.0 * pixel.z)
+        });
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("checksum", &checksum)
+            .field("rotation", &self.rotation)
+            .field("strength", &self.strength)
+            .field("samples", &self.samples)
+            .finish()
+    }
+}
+
+// Radiance .hdr (RGBE) or PFM, by extension
+pub fn load_environment(path: &Path) -> Result<EnvironmentMap, EnvironmentError> {
+    let bytes = fs::read(path)?;
+    let (width, height, pixels) = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("hdr") => parse_rgbe(&bytes)?,
+        Some("pfm") => parse_pfm(&bytes)?,
+        _ => return Err(EnvironmentError::Format(String::from("expected a .hdr or .pfm file"))),
+    };
+    EnvironmentMap::new(width, height, pixels)
+}
+
+impl EnvironmentMap {
+    // Pixels row by row from the top
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Result<EnvironmentMap, EnvironmentError> {
+        if width == 0 || height == 0 || width.checked_mul(height) != Some(pixels.len()) {
+            return Err(EnvironmentError::Format(String::from("environment needs width * height pixels")));
+        }
+        // Broken pixels from odd HDR writers would poison the luminance totals, they go black
+        let pixels: Vec<Vec3> = pixels.into_iter().map(|pixel| {
+            let clean = |value: f64| if value.is_finite() && value > 0.0 { value } else { 0.0 };
+            Vec3::new(clean(pixel.x), clean(pixel.y), clean(pixel.z))
+        }).collect();
+
+        let mut row_cdfs = Vec::with_capacity(height);
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total = 0.0;
+
+        for row in 0..height {
+            // Rows near the poles cover less of the sphere
+            let sin_theta = (PI * (row as f64 + 0.5) / height as f64).sin();
+            let mut row_total = 0.0;
+            let cdf = (0..width).map(|column| {
+                row_total += luminance(&pixels[row * width + column]) * sin_theta;
+                row_total
+            }).collect();
+
+            row_cdfs.push(cdf);
+            total += row_total;
+            marginal_cdf.push(total);
+        }
+
+        Ok(EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            strength: 1.0,
+            samples: 16,
+            row_cdfs,
+            marginal_cdf,
+        })
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.to_uv(direction);
+
+        // Bilinear between texel centres, wrapping around horizontally
+        let x = u * self.width as f64 - 0.5;
+        let y = (v * self.height as f64 - 0.5).max(0.0).min(self.height as f64 - 1.0);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let column = |offset: f64| ((x0 + offset) as i64).rem_euclid(self.width as i64) as usize;
+        let row = |offset: f64| ((y0 + offset) as usize).min(self.height - 1);
+
+        let top = self.pixel(column(0.0), row(0.0)).mix(self.pixel(column(1.0), row(0.0)), tx);
+        let bottom = self.pixel(column(0.0), row(1.0)).mix(self.pixel(column(1.0), row(1.0)), tx);
+        top.mix(&bottom, ty) * self.strength
+    }
+
+    // Texel by luminance, then uniformly inside it
+    pub fn sample(&self, u1: f64, u2: f64) -> Option<EnvironmentSample> {
+        let total = self.marginal_cdf[self.height - 1];
+        if total <= 0.0 {
+            return None;
+        }
+
+        let (row, v_offset) = pick(&self.marginal_cdf, u2 * total);
+        let row_cdf = &self.row_cdfs[row];
+        let (column, u_offset) = pick(row_cdf, u1 * row_cdf[self.width - 1]);
+
+        let u = (column as f64 + u_offset) / self.width as f64;
+        let v = (row as f64 + v_offset) / self.height as f64;
+        let direction = self.direction_at(u, v);
+        let pdf = self.pdf(&direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(EnvironmentSample {
+            radiance: self.radiance(&direction),
+            direction,
+            pdf,
+        })
+    }
+
+    // Per solid angle, of sample() picking `direction`
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        let total = self.marginal_cdf[self.height - 1];
+        let (u, v) = self.to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if total <= 0.0 || sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let column = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = ((v * self.height as f64) as usize).min(self.height - 1);
+        let row_cdf = &self.row_cdfs[row];
+        let texel = row_cdf[column] - if column == 0 { 0.0 } else { row_cdf[column - 1] };
+
+        // Texel probability spread over its area in the image, then over the sphere
+        let pdf_uv = texel / total * (self.width * self.height) as f64;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn pixel(&self, column: usize, row: usize) -> &Vec3 {
+        &self.pixels[row * self.width + column]
+    }
+
+    fn to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let direction = direction.unit();
+        let phi = direction.x.atan2(-direction.z) - self.rotation.to_radians();
+        let theta = (-direction.y).clamp(-1.0, 1.0).acos();
+        ((phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+
+    fn direction_at(&self, u: f64, v: f64) -> Vec3 {
+        let phi = u * 2.0 * PI + self.rotation.to_radians();
+        let theta = v * PI;
+        Vec3::new(theta.sin() * phi.sin(), -theta.cos(), -theta.sin() * phi.cos())
+    }
+}
+
+fn luminance(color: &Vec3) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// First entry of a running total above `target`, and where `target` falls inside it
+fn pick(cdf: &[f64], target: f64) -> (usize, f64) {
+    let index = match cdf.binary_search_by(|total| total.partial_cmp(&target).unwrap()) {
+        Ok(index) => index + 1,
+        Err(index) => index,
+    };
+    let index = index.min(cdf.len() - 1);
+
+    let previous = if index == 0 { 0.0 } else { cdf[index - 1] };
+    let width = cdf[index] - previous;
+    let offset = if width > 0.0 { ((target - previous) / width).clamp(0.0, 1.0) } else { 0.5 };
+    (index, offset)
+}
+
+// Radiance RGBE: text header, "-Y height +X width", then scanlines either flat or
+// run-length encoded one channel at a time
+pub fn parse_rgbe(bytes: &[u8]) -> Result<(usize, usize, Vec<Vec3>), EnvironmentError> {
+    let format_error = |message: &str| EnvironmentError::Format(String::from(message));
+    let mut position = 0;
+    let mut next_line = || -> Option<String> {
+        let start = position;
+        let end = start + bytes[start..].iter().position(|&byte| byte == b'\n')?;
+        position = end + 1;
+        Some(String::from_utf8_lossy(&bytes[start..end]).trim().to_string())
+    };
+
+    let magic = next_line().ok_or_else(|| format_error("empty file"))?;
+    if !magic.starts_with("#?") {
+        return Err(format_error("not a Radiance HDR file"));
+    }
+    loop {
+        let line = next_line().ok_or_else(|| format_error("missing resolution"))?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(EnvironmentError::Format(format!("unsupported {}", line)));
+        }
+    }
+
+    let resolution = next_line().ok_or_else(|| format_error("missing resolution"))?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    if fields.len() != 4 || fields[0] != "-Y" || fields[2] != "+X" {
+        return Err(EnvironmentError::Format(format!("unsupported resolution line {}", resolution)));
+    }
+    let height: usize = fields[1].parse().map_err(|_| format_error("invalid height"))?;
+    let width: usize = fields[3].parse().map_err(|_| format_error("invalid width"))?;
+    if width == 0 || height == 0 {
+        return Err(format_error("empty image"));
+    }
+
+    let mut data = &bytes[position..];
+    let too_large = || format_error("image too large");
+    let truncated = || format_error("truncated pixel data");
+
+    // Even run-length encoded, every scanline takes some bytes. A header asking for more
+    // than the file can hold is caught before allocating for it.
+    let run_length_encodable = (8..32768).contains(&width);
+    let smallest_scanline = if run_length_encodable { 4 + 8 * width.div_ceil(127) } else { width.checked_mul(4).ok_or_else(too_large)? };
+    let count = width.checked_mul(height).ok_or_else(too_large)?;
+    if smallest_scanline.checked_mul(height).is_none_or(|length| length > data.len()) {
+        return Err(truncated());
+    }
+
+    let mut pixels = Vec::with_capacity(count);
+    let mut scanline = vec![[0u8; 4]; width];
+
+    for _ in 0..height {
+        let run_length_encoded = run_length_encodable && data.len() >= 4 && data[0] == 2 && data[1] == 2 && data[2] & 0x80 == 0;
+        if run_length_encoded {
+            if ((data[2] as usize) << 8 | data[3] as usize) != width {
+                return Err(format_error("scanline width mismatch"));
+            }
+            data = &data[4..];
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let (&count, rest) = data.split_first().ok_or_else(truncated)?;
+                    if count > 128 {
+                        // A run of one value
+                        let count = count as usize - 128;
+                        let (&value, rest) = rest.split_first().ok_or_else(truncated)?;
+                        if x + count > width {
+                            return Err(format_error("run past the end of a scanline"));
+                        }
+                        for texel in &mut scanline[x..x + count] {
+                            texel[channel] = value;
+                        }
+                        x += count;
+                        data = rest;
+                    } else {
+                        let count = count as usize;
+                        if count == 0 || x + count > width || rest.len() < count {
+                            return Err(format_error("invalid literal run"));
+                        }
+                        for (texel, &value) in scanline[x..x + count].iter_mut().zip(rest) {
+                            texel[channel] = value;
+                        }
+                        x += count;
+                        data = &rest[count..];
+                    }
+                }
+            }
+        } else {
+            if data.len() < width * 4 {
+                return Err(truncated());
+            }
+            for (texel, chunk) in scanline.iter_mut().zip(data.chunks(4)) {
+                texel.copy_from_slice(chunk);
+            }
+            data = &data[width * 4..];
+        }
+
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                return Vec3::new(0.0, 0.0, 0.0);
+            }
+            let scale = 2.0_f64.powi(e as i32 - 136);
+            Vec3::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+        }));
+    }
+
+    Ok((width, height, pixels))
+}
+
+// Portable float map: "PF" (color) or "Pf" (grey), size, then a scale whose sign gives
+// the byte order. Rows are stored bottom to top.
+pub fn parse_pfm(bytes: &[u8]) -> Result<(usize, usize, Vec<Vec3>), EnvironmentError> {
+    let format_error = |message: &str| EnvironmentError::Format(String::from(message));
+
+    // Four whitespace separated fields, then a single whitespace byte before the data
+    let mut fields = Vec::new();
+    let mut position = 0;
+    while fields.len() < 4 {
+        while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        let start = position;
+        while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        if start == position {
+            return Err(format_error("truncated PFM header"));
+        }
+        fields.push(String::from_utf8_lossy(&bytes[start..position]).to_string());
+    }
+    let data = &bytes[(position + 1).min(bytes.len())..];
+
+    let channels = match fields[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(format_error("not a PFM file")),
+    };
+    let width: usize = fields[1].parse().map_err(|_| format_error("invalid width"))?;
+    let height: usize = fields[2].parse().map_err(|_| format_error("invalid height"))?;
+    let scale: f64 = fields[3].parse().map_err(|_| format_error("invalid scale"))?;
+    if width == 0 || height == 0 {
+        return Err(format_error("empty image"));
+    }
+    let length = width
+        .checked_mul(height)
+        .and_then(|count| count.checked_mul(channels * 4))
+        .ok_or_else(|| format_error("image too large"))?;
+    if data.len() < length {
+        return Err(format_error("truncated pixel data"));
+    }
+
+    let little_endian = scale < 0.0;
+    let value = |index: usize| {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&data[index * 4..index * 4 + 4]);
+        let value = if little_endian { f32::from_le_bytes(word) } else { f32::from_be_bytes(word) };
+        value as f64
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in (0..height).rev() {
+        for column in 0..width {
+            let index = (row * width + column) * channels;
+            if channels == 3 {
+                pixels.push(Vec3::new(value(index), value(index + 1), value(index + 2)));
+            } else {
+                let grey = value(index);
+                pixels.push(Vec3::new(grey, grey, grey));
+            }
+        }
+    }
+
+    Ok((width, height, pixels))
+}
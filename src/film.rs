@@ -0,0 +1,52 @@
+use image::{Color, Image};
+use vec3::Vec3;
+
+// Running sum of every sample taken per pixel. Pixels keep their own sample
+// count so an interrupted pass can simply be picked up where it stopped.
+#[derive(Debug, Clone)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub sums: Vec<Vec3>,
+    pub samples: Vec<u32>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Film {
+        let pixel_count = width as usize * height as usize;
+
+        Film {
+            width,
+            height,
+            sums: vec![Vec3::new(0.0, 0.0, 0.0); pixel_count],
+            samples: vec![0; pixel_count],
+        }
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn add_sample(&mut self, x: u32, y: u32, color: &Color) {
+        let index = self.index(x, y);
+        self.sums[index] = &self.sums[index] + &Vec3::new(color.r, color.g, color.b);
+        self.samples[index] += 1;
+    }
+
+    pub fn min_samples(&self) -> u32 {
+        self.samples.iter().cloned().min().unwrap_or(0)
+    }
+
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+
+        for (pixel, (sum, &samples)) in image.pixels.iter_mut().zip(self.sums.iter().zip(&self.samples)) {
+            if samples > 0 {
+                let average = sum.clone() / samples as f64;
+                *pixel = Color { r: average.x, g: average.y, b: average.z };
+            }
+        }
+
+        image
+    }
+}
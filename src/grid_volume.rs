@@ -0,0 +1,242 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use medium::Medium;
+use ray::{box_overlap, Ray};
+use sampler::Sampler;
+use scene::Scene;
+use vec3::Vec3;
+
+// Densities on a regular grid of voxels, `width` along x, `height` along y and `depth`
+// along z. Voxel values sit at the voxels' centres and get interpolated in between.
+pub struct DensityGrid {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    // x changing fastest, then y, then z
+    pub densities: Vec<f32>,
+    // Highest of them, no interpolated density goes over it
+    max_density: f64,
+}
+
+#[derive(Debug)]
+pub enum GridError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GridError::Io(ref err) => write!(f, "{}", err),
+            GridError::Format(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for GridError {
+    fn from(err: io::Error) -> GridError {
+        GridError::Io(err)
+    }
+}
+
+// Grids can be huge, the checkpoint hash only needs something that changes with them
+impl fmt::Debug for DensityGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let checksum = self.densities.iter().enumerate().fold(0.0, |sum, (index, &density)| {
+            sum + (index % 97 + 1) as f64 * density as f64
+        });
+        f.debug_struct("DensityGrid")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("depth", &self.depth)
+            .field("checksum", &checksum)
+            .finish()
+    }
+}
+
+pub fn load_density_grid(path: &Path) -> Result<DensityGrid, GridError> {
+    parse_density_grid(&fs::read(path)?)
+}
+
+// Raw voxels: width, height and depth as little-endian u32, then a little-endian f32
+// per voxel, in the grid's order
+pub fn parse_density_grid(bytes: &[u8]) -> Result<DensityGrid, GridError> {
+    let format_error = |message: &str| GridError::Format(String::from(message));
+    let word = |index: usize| {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&bytes[index * 4..index * 4 + 4]);
+        word
+    };
+
+    if bytes.len() < 12 {
+        return Err(format_error("truncated grid header"));
+    }
+    let width = u32::from_le_bytes(word(0)) as usize;
+    let height = u32::from_le_bytes(word(1)) as usize;
+    let depth = u32::from_le_bytes(word(2)) as usize;
+    // Headers can claim anything, sizes that don't fit can't be in the file either
+    let count = width.checked_mul(height).and_then(|area| area.checked_mul(depth));
+    let length = count.and_then(|count| count.checked_mul(4)).and_then(|voxels| voxels.checked_add(12));
+    let (count, length) = match (count, length) {
+        (Some(count), Some(length)) => (count, length),
+        _ => return Err(format_error("grid too large")),
+    };
+    if bytes.len() < length {
+        return Err(format_error("truncated voxel data"));
+    }
+
+    let densities = (0..count).map(|index| f32::from_le_bytes(word(3 + index))).collect();
+    DensityGrid::new(width, height, depth, densities)
+}
+
+impl DensityGrid {
+    pub fn new(width: usize, height: usize, depth: usize, densities: Vec<f32>) -> Result<DensityGrid, GridError> {
+        let format_error = |message: &str| Err(GridError::Format(String::from(message)));
+        if width == 0 || height == 0 || depth == 0 {
+            return format_error("empty grid");
+        }
+        if width.checked_mul(height).and_then(|area| area.checked_mul(depth)) != Some(densities.len()) {
+            return format_error("grid needs width * height * depth densities");
+        }
+        if densities.iter().any(|density| !density.is_finite() || *density < 0.0) {
+            return format_error("negative or invalid density");
+        }
+
+        let max_density = densities.iter().fold(0.0, |max: f64, &density| max.max(density as f64));
+        Ok(DensityGrid { width, height, depth, densities, max_density })
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    // Trilinear between the voxels around (u, v, w), each from 0 to 1 across the grid
+    pub fn density(&self, u: f64, v: f64, w: f64) -> f64 {
+        let (x0, x1, fx) = neighbours(u, self.width);
+        let (y0, y1, fy) = neighbours(v, self.height);
+        let (z0, z1, fz) = neighbours(w, self.depth);
+        let voxel = |x: usize, y: usize, z: usize| self.densities[(z * self.height + y) * self.width + x] as f64;
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let at_z0 = lerp(lerp(voxel(x0, y0, z0), voxel(x1, y0, z0), fx), lerp(voxel(x0, y1, z0), voxel(x1, y1, z0), fx), fy);
+        let at_z1 = lerp(lerp(voxel(x0, y0, z1), voxel(x1, y0, z1), fx), lerp(voxel(x0, y1, z1), voxel(x1, y1, z1), fx), fy);
+        lerp(at_z0, at_z1, fz)
+    }
+}
+
+// Voxels on either side of `coordinate` and how far it is from the first, clamped at
+// the edges
+fn neighbours(coordinate: f64, size: usize) -> (usize, usize, f64) {
+    let position = (coordinate * size as f64 - 0.5).max(0.0).min(size as f64 - 1.0);
+    let first = (position as usize).min(size - 1);
+    let second = (first + 1).min(size - 1);
+    (first, second, position - first as f64)
+}
+
+// Smoke or cloud in the box from `min` to `max`, with `grid` stretched over it. A
+// voxel density of 1 has an extinction of `density` per unit of distance, of which
+// `albedo` scatters and the rest gets absorbed.
+#[derive(Debug)]
+pub struct GridVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub grid: DensityGrid,
+    pub density: f64,
+    pub albedo: Vec3,
+    pub anisotropy: f64,
+}
+
+impl GridVolume {
+    pub fn extinction(&self, point: &Vec3) -> f64 {
+        let size = &self.max - &self.min;
+        let u = (point.x - self.min.x) / size.x;
+        let v = (point.y - self.min.y) / size.y;
+        let w = (point.z - self.min.z) / size.z;
+        self.density * self.grid.density(u, v, w)
+    }
+
+    // Same as the volume at `point`, as if it were all like there
+    pub fn medium(&self, point: &Vec3) -> Medium {
+        let extinction = self.extinction(point);
+        Medium {
+            absorption: (Vec3::new(1.0, 1.0, 1.0) - &self.albedo) * extinction,
+            scattering: &self.albedo * extinction,
+            anisotropy: self.anisotropy,
+        }
+    }
+
+    // Where the first `distance` of `ray`, whose direction is a unit vector, is inside
+    pub fn overlap(&self, ray: &Ray, distance: f64) -> Option<(f64, f64)> {
+        box_overlap(ray, &self.min, &self.max, distance)
+    }
+
+    // Where along `ray` light arriving at its origin was last scattered or absorbed,
+    // if before `distance` (delta tracking: tentative collisions at the grid's highest
+    // extinction, real ones as often as the extinction there is of that)
+    pub fn sample_collision(&self, ray: &Ray, distance: f64, sampler: &mut Sampler) -> Option<f64> {
+        let majorant = self.density * self.grid.max_density();
+        let (mut t, end) = self.overlap(ray, distance)?;
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        loop {
+            t -= (1.0 - sampler.next_f64()).ln() / majorant;
+            if t >= end {
+                return None;
+            }
+            let point = &ray.origin + &(&ray.direction * t);
+            if sampler.next_f64() * majorant < self.extinction(&point) {
+                return Some(t);
+            }
+        }
+    }
+
+    // Unbiased estimate of the light left after `distance` along `ray` (ratio tracking:
+    // at each tentative collision, the chance that it wasn't a real one)
+    pub fn transmittance(&self, ray: &Ray, distance: f64, sampler: &mut Sampler) -> f64 {
+        let majorant = self.density * self.grid.max_density();
+        let (mut t, end) = match self.overlap(ray, distance) {
+            Some(overlap) if majorant > 0.0 => overlap,
+            _ => return 1.0,
+        };
+
+        let mut transmittance = 1.0;
+        loop {
+            t -= (1.0 - sampler.next_f64()).ln() / majorant;
+            if t >= end || transmittance <= 0.0 {
+                return transmittance;
+            }
+            let point = &ray.origin + &(&ray.direction * t);
+            transmittance *= 1.0 - self.extinction(&point) / majorant;
+        }
+    }
+}
+
+// First collision in any of the scene's grid volumes along the first `distance` of
+// `ray`, which has a unit direction
+pub fn sample_collision<'a>(ray: &Ray, distance: f64, scene: &'a Scene, sampler: &mut Sampler) -> Option<(f64, &'a GridVolume)> {
+    let mut collision: Option<(f64, &GridVolume)> = None;
+    for volume in &scene.volumes {
+        let reach = collision.map_or(distance, |(t, _)| t);
+        if let Some(t) = volume.sample_collision(ray, reach, sampler) {
+            collision = Some((t, volume));
+        }
+    }
+    collision
+}
+
+// Through all of the scene's grid volumes
+pub fn grid_transmittance(ray: &Ray, distance: f64, scene: &Scene, sampler: &mut Sampler) -> f64 {
+    let mut transmittance = 1.0;
+    for volume in &scene.volumes {
+        if transmittance <= 0.0 {
+            break;
+        }
+        transmittance *= volume.transmittance(ray, distance, sampler);
+    }
+    transmittance
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Candela distribution of a luminaire from an IES LM-63 file (type C photometry).
+// Vertical angles start at 0 straight down the luminaire's nadir, horizontal angles
+// go around it.
+#[derive(Debug)]
+pub struct IesProfile {
+    pub vertical_angles: Vec<f64>,
+    pub horizontal_angles: Vec<f64>,
+    // One row of candelas over the vertical angles per horizontal angle
+    pub candela: Vec<Vec<f64>>,
+    max_candela: f64,
+}
+
+#[derive(Debug)]
+pub enum IesError {
+    Io(io::Error),
+    Syntax(String),
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IesError::Io(ref err) => write!(f, "{}", err),
+            IesError::Syntax(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for IesError {
+    fn from(err: io::Error) -> IesError {
+        IesError::Io(err)
+    }
+}
+
+pub fn load_ies(path: &Path) -> Result<IesProfile, IesError> {
+    parse_ies(&fs::read_to_string(path)?)
+}
+
+pub fn parse_ies(contents: &str) -> Result<IesProfile, IesError> {
+    let mut lines = contents.lines();
+
+    // Keyword lines up to TILT, whatever the header version
+    let tilt = loop {
+        match lines.next() {
+            Some(line) if line.trim_start().starts_with("TILT=") => break line.trim()[5..].to_string(),
+            Some(_) => {}
+            None => return Err(IesError::Syntax(String::from("missing TILT line"))),
+        }
+    };
+
+    let rest: Vec<&str> = lines.collect();
+    let mut numbers = Numbers { tokens: rest.iter().flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ',')).filter(|token| !token.is_empty()) };
+
+    match tilt.as_str() {
+        "NONE" => {}
+        "INCLUDE" => {
+            // The tilt only matters for lamps mounted at an angle, skip it
+            numbers.next("lamp to luminaire geometry")?;
+            let pairs = numbers.count("tilt angle count")?;
+            for _ in 0..2 * pairs {
+                numbers.next("tilt data")?;
+            }
+        }
+        other => return Err(IesError::Syntax(format!("unsupported TILT={}", other))),
+    }
+
+    numbers.next("number of lamps")?;
+    numbers.next("lumens per lamp")?;
+    let multiplier = numbers.next("candela multiplier")?;
+    let vertical_count = numbers.count("number of vertical angles")?;
+    let horizontal_count = numbers.count("number of horizontal angles")?;
+    let photometric_type = numbers.next("photometric type")?;
+    if photometric_type != 1.0 {
+        return Err(IesError::Syntax(format!("unsupported photometric type {}", photometric_type)));
+    }
+    // Units, luminous opening size, ballast factor, future use, input watts
+    for name in &["units type", "width", "length", "height", "ballast factor", "future use", "input watts"] {
+        numbers.next(name)?;
+    }
+
+    if vertical_count == 0 || horizontal_count == 0 {
+        return Err(IesError::Syntax(String::from("profile needs at least one angle each way")));
+    }
+
+    let vertical_angles = numbers.list(vertical_count, "vertical angle")?;
+    let horizontal_angles = numbers.list(horizontal_count, "horizontal angle")?;
+    if !ascending(&vertical_angles) || !ascending(&horizontal_angles) {
+        return Err(IesError::Syntax(String::from("angles must be increasing")));
+    }
+
+    let mut candela = Vec::new();
+    let mut max_candela: f64 = 0.0;
+    for _ in 0..horizontal_count {
+        let row: Vec<f64> = numbers.list(vertical_count, "candela value")?.iter().map(|value| value * multiplier).collect();
+        max_candela = row.iter().cloned().fold(max_candela, f64::max);
+        candela.push(row);
+    }
+
+    Ok(IesProfile {
+        vertical_angles,
+        horizontal_angles,
+        candela,
+        max_candela,
+    })
+}
+
+impl IesProfile {
+    // Relative to the brightest direction, so a light keeps its force as its peak.
+    // Both angles in degrees, directions outside the measured range are dark.
+    pub fn intensity(&self, vertical: f64, horizontal: f64) -> f64 {
+        if self.max_candela <= 0.0 {
+            return 0.0;
+        }
+
+        let first = self.vertical_angles[0];
+        let last = self.vertical_angles[self.vertical_angles.len() - 1];
+        if vertical < first || vertical > last {
+            return 0.0;
+        }
+
+        let horizontal = self.fold_horizontal(horizontal);
+        let (h0, h1, ht) = bracket(&self.horizontal_angles, horizontal);
+        let (v0, v1, vt) = bracket(&self.vertical_angles, vertical);
+
+        let lerp = |row: &Vec<f64>| row[v0] * (1.0 - vt) + row[v1] * vt;
+        let value = lerp(&self.candela[h0]) * (1.0 - ht) + lerp(&self.candela[h1]) * ht;
+        value / self.max_candela
+    }
+
+    // Files only store the part of the distribution that isn't repeated by symmetry
+    fn fold_horizontal(&self, angle: f64) -> f64 {
+        let angle = angle.rem_euclid(360.0);
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+
+        if self.horizontal_angles.len() == 1 {
+            // Same all the way around
+            self.horizontal_angles[0]
+        } else if last == 90.0 {
+            // Symmetric in each quadrant
+            let half = if angle > 180.0 { 360.0 - angle } else { angle };
+            if half > 90.0 { 180.0 - half } else { half }
+        } else if last == 180.0 {
+            // Symmetric about the 0-180 plane
+            if angle > 180.0 { 360.0 - angle } else { angle }
+        } else {
+            angle
+        }
+    }
+}
+
+// Indices of the angles around `angle` and how far it is between them,
+// clamped to the ends of the table
+fn bracket(angles: &[f64], angle: f64) -> (usize, usize, f64) {
+    if angle <= angles[0] {
+        return (0, 0, 0.0);
+    }
+    for i in 1..angles.len() {
+        if angle <= angles[i] {
+            let t = (angle - angles[i - 1]) / (angles[i] - angles[i - 1]);
+            return (i - 1, i, t);
+        }
+    }
+    let last = angles.len() - 1;
+    (last, last, 0.0)
+}
+
+fn ascending(angles: &[f64]) -> bool {
+    angles.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+struct Numbers<I> {
+    tokens: I,
+}
+
+impl<'a, I: Iterator<Item = &'a str>> Numbers<I> {
+    fn next(&mut self, name: &str) -> Result<f64, IesError> {
+        let token = self.tokens.next().ok_or_else(|| IesError::Syntax(format!("missing {}", name)))?;
+        token.parse().map_err(|_| IesError::Syntax(format!("invalid number for {}: {}", name, token)))
+    }
+
+    fn count(&mut self, name: &str) -> Result<usize, IesError> {
+        let value = self.next(name)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(IesError::Syntax(format!("invalid {}: {}", name, value)));
+        }
+        Ok(value as usize)
+    }
+
+    fn list(&mut self, count: usize, name: &str) -> Result<Vec<f64>, IesError> {
+        (0..count).map(|_| self.next(name)).collect()
+    }
+}
@@ -0,0 +1,75 @@
+#[derive(Debug, Clone)]
+pub struct Color { pub r: f64, pub g: f64, pub b: f64 }
+
+#[derive(Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    // Starts out black, so a render stopped early still leaves a valid image
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Color { r: 0.0, g: 0.0, b: 0.0 }; (width * height) as usize],
+        }
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        let index = (y * self.width + x) as usize;
+        self.pixels[index] = color;
+    }
+
+    pub fn to_ppm(&self) -> String {
+        let mut pixels_str = String::new();
+
+        for color in &self.pixels {
+            let color_str = format!(" {} {} {}", (color.r * 255.0) as u8, (color.g * 255.0) as u8, (color.b * 255.0) as u8);
+            pixels_str.push_str(&color_str);
+        }
+
+        format!("P3 {} {} 255\n{}\n\n", self.width, self.height, pixels_str)
+    }
+}
+
+#[derive(Debug)]
+pub enum PpmError {
+    UnsupportedFormat,
+    Malformed,
+}
+
+// Reads back the plain (P3) format written by `Image::to_ppm`
+pub fn parse_ppm(contents: &str) -> Result<Image, PpmError> {
+    let mut tokens = contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace());
+
+    if tokens.next() != Some("P3") {
+        return Err(PpmError::UnsupportedFormat);
+    }
+
+    let mut next_number = || -> Result<u32, PpmError> {
+        tokens.next().and_then(|token| token.parse().ok()).ok_or(PpmError::Malformed)
+    };
+
+    let width = next_number()?;
+    let height = next_number()?;
+    let max_value = next_number()? as f64;
+    if max_value == 0.0 {
+        return Err(PpmError::Malformed);
+    }
+
+    let mut image = Image::new(width, height);
+    for pixel in image.pixels.iter_mut() {
+        let r = next_number()? as f64 / max_value;
+        let g = next_number()? as f64 / max_value;
+        let b = next_number()? as f64 / max_value;
+        *pixel = Color { r, g, b };
+    }
+
+    Ok(image)
+}
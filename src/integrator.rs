@@ -0,0 +1,169 @@
+use std::fmt;
+
+use bidirectional::trace_bidirectional;
+use image::Color;
+use occlusion::AmbientOcclusion;
+use path_tracer::trace_path;
+use photon_map::{photon_radiance, progressive_radius, shoot_photons, PhotonMap, PhotonMaps};
+use ray::{trace, Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use tracer::{display_color, trace_ray};
+
+// How the light reaching the camera along a ray is worked out. The renderer makes
+// the camera rays and averages the colours into the film, integrators only follow
+// one ray into the scene. Debug output goes into checkpoint hashes, so it should
+// show every setting that changes the image, and nothing worked out while rendering.
+pub trait Integrator: fmt::Debug {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color;
+
+    // Called before each pass over the image, `pass` counting from 0, also when a
+    // resumed render starts in the middle of one
+    fn start_pass(&mut self, _scene: &Scene, _seed: u64, _pass: u32) {}
+}
+
+// Mirror and glass reflections with direct light and a flat ambient term, fast.
+// Ambient occlusion keeps the ambient term out of creases, at the cost of its rays.
+#[derive(Debug, Default)]
+pub struct Whitted {
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+}
+
+impl Integrator for Whitted {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+        trace_ray(ray, scene, self.ambient_occlusion.as_ref(), sampler)
+    }
+}
+
+// Global illumination, needs many samples per pixel to lose its noise
+#[derive(Debug)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+        trace_path(ray, scene, sampler)
+    }
+}
+
+// Light paths joined to camera paths, for scenes lit through small openings or by
+// light the camera paths rarely find
+#[derive(Debug)]
+pub struct Bidirectional;
+
+impl Integrator for Bidirectional {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+        trace_bidirectional(ray, scene, sampler)
+    }
+}
+
+// Photons shot from the lights before every pass and gathered where camera rays
+// land, for caustics and bounce light. The gather radii shrink from pass to pass, so
+// the image converges with the samples per pixel (progressive photon mapping).
+// Directional lights and the background light surfaces directly but shoot no photons.
+pub struct PhotonMapper {
+    pub photons: u32,
+    pub caustic_photons: u32,
+    pub radius: f64,
+    pub caustic_radius: f64,
+    maps: PhotonMaps,
+}
+
+// Pixel index no pixel has, photons get their own stream of samples
+const PHOTON_STREAM: u64 = u64::MAX;
+
+impl PhotonMapper {
+    // Photon counts are per pass, radii those of the first pass
+    pub fn new(photons: u32, caustic_photons: u32, radius: f64, caustic_radius: f64) -> PhotonMapper {
+        PhotonMapper {
+            photons,
+            caustic_photons,
+            radius,
+            caustic_radius,
+            maps: PhotonMaps::empty(),
+        }
+    }
+}
+
+impl Default for PhotonMapper {
+    fn default() -> PhotonMapper {
+        PhotonMapper::new(100_000, 200_000, 0.2, 0.05)
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+        display_color(photon_radiance(ray, scene, &self.maps, sampler))
+    }
+
+    fn start_pass(&mut self, scene: &Scene, seed: u64, pass: u32) {
+        let mut sampler = Sampler::new(seed, PHOTON_STREAM, pass as u64);
+        self.maps = PhotonMaps {
+            global: PhotonMap::new(shoot_photons(scene, self.photons, false, &mut sampler)),
+            caustics: PhotonMap::new(shoot_photons(scene, self.caustic_photons, true, &mut sampler)),
+            radius: progressive_radius(self.radius, pass),
+            caustic_radius: progressive_radius(self.caustic_radius, pass),
+        };
+    }
+}
+
+// Only the settings, the maps change with every pass
+impl fmt::Debug for PhotonMapper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PhotonMapper")
+            .field("photons", &self.photons)
+            .field("caustic_photons", &self.caustic_photons)
+            .field("radius", &self.radius)
+            .field("caustic_radius", &self.caustic_radius)
+            .finish()
+    }
+}
+
+// Ambient occlusion as grey levels, white out in the open and where nothing is hit
+impl Integrator for AmbientOcclusion {
+    fn trace(&self, ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            return Color { r: 1.0, g: 1.0, b: 1.0 };
+        }
+
+        let point = &ray.origin + &(&ray.direction * intersection.t);
+        let normal = &intersection.normal;
+        let facing = if normal.dot(&ray.direction) > 0.0 { normal * -1.0 } else { normal.clone() };
+        let visibility = self.visibility(&point, &facing, scene, sampler);
+        Color { r: visibility, g: visibility, b: visibility }
+    }
+}
+
+// Surface normals as colours, x y z to red green blue, for checking geometry.
+// Nothing hit stays black.
+#[derive(Debug)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn trace(&self, ray: Ray, scene: &Scene, _sampler: &mut Sampler) -> Color {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            return Color { r: 0.0, g: 0.0, b: 0.0 };
+        }
+
+        let normal = intersection.normal.unit();
+        Color {
+            r: normal.x * 0.5 + 0.5,
+            g: normal.y * 0.5 + 0.5,
+            b: normal.z * 0.5 + 0.5,
+        }
+    }
+}
+
+// With default settings, as named on the command line and in scene files
+pub fn integrator_by_name(name: &str) -> Option<Box<dyn Integrator>> {
+    match name {
+        "whitted" => Some(Box::new(Whitted::default())),
+        "path" => Some(Box::new(PathTracer)),
+        "bdpt" => Some(Box::new(Bidirectional)),
+        "photons" => Some(Box::new(PhotonMapper::default())),
+        "ao" => Some(Box::new(AmbientOcclusion::default())),
+        "normals" => Some(Box::new(Normals)),
+        _ => None,
+    }
+}
@@ -0,0 +1,31 @@
+pub mod bidirectional;
+pub mod brdf;
+pub mod camera;
+pub mod checkpoint;
+pub mod emitter;
+pub mod environment;
+pub mod film;
+pub mod grid_volume;
+pub mod ies;
+pub mod image;
+pub mod integrator;
+pub mod light;
+pub mod lighting;
+pub mod material;
+pub mod medium;
+pub mod obj;
+pub mod occlusion;
+pub mod optics;
+pub mod path_tracer;
+pub mod photon_map;
+pub mod random_walk;
+pub mod ray;
+pub mod render;
+pub mod sampler;
+pub mod scene;
+pub mod scene_file;
+pub mod sky;
+pub mod tracer;
+pub mod vec2;
+pub mod vec3;
+pub mod warp;
@@ -0,0 +1,284 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use ies::IesProfile;
+use ray::INFINITY;
+use scene::SurfaceSample;
+use vec3::Vec3;
+use warp::{uniform_cone, uniform_disk, uniform_sphere, Frame};
+
+#[derive(Debug)]
+pub enum Light {
+    Point(PointLight),
+    Area(AreaLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+}
+
+// `force` is the intensity, it falls off with the square of the distance
+#[derive(Debug)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub force: f64,
+    pub profile: Option<LightProfile>,
+}
+
+// Spreads `force` over its surface, so from far away and facing it, it looks like a
+// point light of the same force. Each shading point casts `samples` shadow rays to it.
+#[derive(Debug)]
+pub struct AreaLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub force: f64,
+    pub shape: AreaShape,
+    pub samples: u32,
+}
+
+// Centred on the light's position. Apart from the sphere they only light the side
+// their normal points to.
+#[derive(Debug)]
+pub enum AreaShape {
+    // Normal along edge_u x edge_v
+    Rectangle { edge_u: Vec3, edge_v: Vec3 },
+    Disk { normal: Vec3, radius: f64 },
+    Sphere { radius: f64 },
+}
+
+// Parallel light from infinitely far away (the sun), without falloff.
+// `direction` is the way the light travels.
+#[derive(Debug)]
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub strength: f64,
+}
+
+// Point light shining into a cone of `cone_angle` degrees around `direction`,
+// dimming towards the edge as cos(angle to the axis)^falloff
+#[derive(Debug)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub force: f64,
+    pub cone_angle: f64,
+    pub falloff: f64,
+    pub profile: Option<LightProfile>,
+}
+
+// IES distribution scaling a point or spot light's force by direction, with its
+// 0 degree vertical angle along `nadir`. Horizontal angles start at the tangent
+// of warp::Frame::from_normal(nadir).
+#[derive(Debug)]
+pub struct LightProfile {
+    pub ies: Arc<IesProfile>,
+    pub nadir: Vec3,
+}
+
+// Light arriving at a shading point from one point of a light
+#[derive(Debug)]
+pub struct LightSample {
+    // Unit vector towards the light
+    pub direction: Vec3,
+    // How far a shadow ray has to go unblocked, INFINITY for directional lights
+    pub distance: f64,
+    // Irradiance on a surface facing the light
+    pub irradiance: Vec3,
+}
+
+impl Light {
+    // Shadow rays per shading point
+    pub fn samples(&self) -> u32 {
+        match *self {
+            Light::Area(ref light) => light.samples.max(1),
+            _ => 1,
+        }
+    }
+
+    // Lights without one add nothing to the ambient term
+    pub fn position(&self) -> Option<&Vec3> {
+        match *self {
+            Light::Point(ref light) => Some(&light.position),
+            Light::Area(ref light) => Some(&light.position),
+            Light::Directional(_) => None,
+            Light::Spot(ref light) => Some(&light.position),
+        }
+    }
+
+    // None when no light reaches `from`, like behind a flat light or outside a spot's cone
+    pub fn sample(&self, from: &Vec3, u1: f64, u2: f64) -> Option<LightSample> {
+        match *self {
+            Light::Point(ref light) => {
+                let intensity = light.intensity(&(from - &light.position).unit());
+                Some(inverse_square(from, &light.position, intensity))
+            }
+            Light::Area(ref light) => light.sample(from, u1, u2),
+            Light::Directional(ref light) => Some(LightSample {
+                direction: (&light.direction * -1.0).unit(),
+                distance: INFINITY,
+                irradiance: &light.color * light.strength,
+            }),
+            Light::Spot(ref light) => light.sample(from),
+        }
+    }
+}
+
+impl PointLight {
+    // Towards the unit vector `direction` from the light, before the distance falloff
+    pub fn intensity(&self, direction: &Vec3) -> Vec3 {
+        &self.color * (self.force * profile_scale(&self.profile, direction))
+    }
+}
+
+impl AreaLight {
+    // Uniform over the whole surface, the back of flat shapes included
+    pub fn sample_surface(&self, u1: f64, u2: f64) -> SurfaceSample {
+        match self.shape {
+            AreaShape::Rectangle { ref edge_u, ref edge_v } => {
+                let offset = &(edge_u * (u1 - 0.5)) + &(edge_v * (u2 - 0.5));
+                SurfaceSample {
+                    point: &self.position + &offset,
+                    normal: edge_u.cross(edge_v).unit(),
+                    pdf: 1.0 / edge_u.cross(edge_v).mag(),
+                }
+            }
+            AreaShape::Disk { ref normal, radius } => {
+                let frame = Frame::from_normal(&normal.unit());
+                let (x, y) = uniform_disk(u1, u2);
+                SurfaceSample {
+                    point: &self.position + &frame.to_world(&Vec3::new(x * radius, y * radius, 0.0)),
+                    normal: frame.normal,
+                    pdf: 1.0 / (PI * radius * radius),
+                }
+            }
+            AreaShape::Sphere { radius } => {
+                let normal = uniform_sphere(u1, u2);
+                SurfaceSample {
+                    point: &self.position + &(&normal * radius),
+                    normal,
+                    pdf: 1.0 / (4.0 * PI * radius * radius),
+                }
+            }
+        }
+    }
+
+    // Leaving a point of the surface with `normal` towards `direction`, the same
+    // everywhere on the side it lights. `force` over the area facing the light's axis,
+    // which for the sphere is its cross-section.
+    pub fn radiance(&self, normal: &Vec3, direction: &Vec3) -> Vec3 {
+        if normal.dot(direction) <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let area = match self.shape {
+            AreaShape::Rectangle { ref edge_u, ref edge_v } => edge_u.cross(edge_v).mag(),
+            AreaShape::Disk { radius, .. } | AreaShape::Sphere { radius } => PI * radius * radius,
+        };
+        &self.color * (self.force / area)
+    }
+
+    fn sample(&self, from: &Vec3, u1: f64, u2: f64) -> Option<LightSample> {
+        let (position, weight) = match self.shape {
+            AreaShape::Rectangle { ref edge_u, ref edge_v } => {
+                let offset = &(edge_u * (u1 - 0.5)) + &(edge_v * (u2 - 0.5));
+                flat_sample(from, &self.position + &offset, &edge_u.cross(edge_v).unit())
+            }
+            AreaShape::Disk { ref normal, radius } => {
+                let frame = Frame::from_normal(&normal.unit());
+                let (x, y) = uniform_disk(u1, u2);
+                let offset = frame.to_world(&Vec3::new(x * radius, y * radius, 0.0));
+                flat_sample(from, &self.position + &offset, &frame.normal)
+            }
+            AreaShape::Sphere { radius } => sample_sphere_light(&self.position, radius, from, u1, u2),
+        };
+
+        if weight <= 0.0 {
+            return None;
+        }
+        Some(inverse_square(from, &position, &self.color * (self.force * weight)))
+    }
+}
+
+impl SpotLight {
+    // Towards the unit vector `direction` from the light, before the distance falloff.
+    // Black outside the cone.
+    pub fn intensity(&self, direction: &Vec3) -> Vec3 {
+        let cos_axis = self.direction.unit().dot(direction);
+        if cos_axis <= self.cone_angle.to_radians().cos() {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        &self.color * (self.force * cos_axis.powf(self.falloff) * profile_scale(&self.profile, direction))
+    }
+
+    fn sample(&self, from: &Vec3) -> Option<LightSample> {
+        let direction = (from - &self.position).unit();
+        if self.direction.unit().dot(&direction) <= self.cone_angle.to_radians().cos() {
+            return None;
+        }
+
+        Some(inverse_square(from, &self.position, self.intensity(&direction)))
+    }
+}
+
+fn profile_scale(profile: &Option<LightProfile>, direction: &Vec3) -> f64 {
+    let profile = match *profile {
+        Some(ref profile) => profile,
+        None => return 1.0,
+    };
+
+    let frame = Frame::from_normal(&profile.nadir.unit());
+    let local = frame.to_local(direction);
+    let vertical = local.z.clamp(-1.0, 1.0).acos().to_degrees();
+    let horizontal = local.y.atan2(local.x).to_degrees();
+    profile.ies.intensity(vertical, horizontal)
+}
+
+fn inverse_square(from: &Vec3, position: &Vec3, intensity: Vec3) -> LightSample {
+    let to_light = position - from;
+    let distance_sq = to_light.dot(&to_light);
+    let distance = distance_sq.sqrt();
+
+    LightSample {
+        direction: &to_light * (1.0 / distance),
+        distance,
+        irradiance: intensity * (1.0 / distance_sq),
+    }
+}
+
+// Lambertian emitter, dimmer seen at an angle and dark from behind
+fn flat_sample(from: &Vec3, position: Vec3, normal: &Vec3) -> (Vec3, f64) {
+    let cos_light = normal.dot(&(from - &position).unit()).max(0.0);
+    (position, cos_light)
+}
+
+// Uniform over the cone the sphere covers seen from `from`. The sphere glows with
+// force / (PI r^2), so the irradiance it gives is that times the cone's solid angle.
+// Returns the point and its weight relative to a point light of the same force there.
+fn sample_sphere_light(center: &Vec3, radius: f64, from: &Vec3, u1: f64, u2: f64) -> (Vec3, f64) {
+    let to_center = center - from;
+    let distance_sq = to_center.dot(&to_center);
+
+    if distance_sq <= radius * radius {
+        // Inside the light, it is everywhere around
+        return (center.clone(), 1.0);
+    }
+
+    let distance = distance_sq.sqrt();
+    let cos_max = (1.0 - radius * radius / distance_sq).max(0.0).sqrt();
+    let frame = Frame::from_normal(&(&to_center * (1.0 / distance)));
+    let direction = frame.to_world(&uniform_cone(cos_max, u1, u2));
+
+    // Nearest intersection of the sampled direction with the sphere
+    let along = direction.dot(&to_center);
+    let t = along - (radius * radius - (distance_sq - along * along)).max(0.0).sqrt();
+    let position = from + &(&direction * t);
+
+    let solid_angle = 2.0 * PI * (1.0 - cos_max);
+    let to_sample = &position - from;
+    let sample_distance_sq = to_sample.dot(&to_sample);
+
+    // point light falloff is force / d^2, undo it for this sample's distance
+    (position, sample_distance_sq * solid_angle / (PI * radius * radius))
+}
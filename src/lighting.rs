@@ -0,0 +1,275 @@
+use emitter::surface_emitters;
+use grid_volume::grid_transmittance;
+use material::Material;
+use medium::Medium;
+use ray::{trace, Ray, RayIntersection, INFINITY};
+use sampler::Sampler;
+use scene::{Scene, SurfaceSample};
+use vec3::Vec3;
+
+// Secondary rays start this far off the surface so they don't hit it again
+const SURFACE_OFFSET: f64 = 0.0001;
+// Shadow rays towards a point on an emitter stop this short of it, so they don't hit the emitter itself
+pub const SHADOW_EPSILON: f64 = 0.001;
+
+// How light samples are weighted. Tracers that also pick up emitters their BSDF
+// samples happen to hit weigh both against each other, so each counts most where
+// it is the less noisy way to find the light (Veach's multiple importance sampling).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mis {
+    Off,
+    PowerHeuristic,
+}
+
+impl Mis {
+    // Weight of a sample from the strategy with density `pdf`, when `other_pdf` is
+    // the density the other one would have picked the same direction with. Both
+    // scaled by how many samples their strategy takes.
+    pub fn weight(self, pdf: f64, other_pdf: f64) -> f64 {
+        match self {
+            Mis::Off => 1.0,
+            Mis::PowerHeuristic => {
+                if pdf <= 0.0 {
+                    return 0.0;
+                }
+                pdf * pdf / (pdf * pdf + other_pdf * other_pdf)
+            }
+        }
+    }
+}
+
+// Start secondary rays just off the surface, on the side they leave towards
+pub fn offset_ray_origin(point: &Vec3, normal: &Vec3, direction: &Vec3) -> Vec3 {
+    if direction.dot(normal) > 0.0 {
+        point + &(normal * SURFACE_OFFSET)
+    } else {
+        point - &(normal * SURFACE_OFFSET)
+    }
+}
+
+// Nothing between a point on a surface with `normal` and `target`, but maybe volumes
+pub fn visible(point: &Vec3, normal: &Vec3, target: &Vec3, scene: &Scene) -> bool {
+    let to_target = target - point;
+    let mut distance = to_target.mag();
+    let direction = &to_target * (1.0 / distance);
+
+    let mut ray = Ray {
+        origin: offset_ray_origin(point, normal, &direction),
+        direction,
+    };
+    loop {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= distance - SHADOW_EPSILON {
+            return true;
+        }
+        if intersection.material.medium().is_none() {
+            return false;
+        }
+
+        let hit = &ray.origin + &(&ray.direction * intersection.t);
+        distance -= intersection.t;
+        ray = Ray {
+            origin: offset_ray_origin(&hit, &intersection.normal, &ray.direction),
+            direction: ray.direction,
+        };
+    }
+}
+
+// Medium a ray travels through up to `intersection`: the inside of a volume it is
+// leaving, otherwise the scene's (so objects inside volumes don't get fog around them)
+pub fn segment_medium<'a>(ray: &Ray, intersection: &RayIntersection<'a>, scene: &'a Scene) -> Option<&'a Medium> {
+    match intersection.material.medium() {
+        Some(medium) if intersection.t < INFINITY && ray.direction.dot(&intersection.normal) > 0.0 => Some(medium),
+        _ => scene.medium.as_ref(),
+    }
+}
+
+// Light left of what travels `distance` along `ray` unblocked, through media and the
+// volumes on the way, zero if anything solid is in between. Grid volumes need `ray`'s
+// direction to be a unit vector.
+pub fn shadow_transmittance(ray: &Ray, distance: f64, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+    let mut origin = ray.origin.clone();
+    let mut remaining = distance;
+    let speed = ray.direction.mag();
+
+    loop {
+        let segment_ray = Ray { origin, direction: ray.direction.clone() };
+        let intersection = trace(&segment_ray, scene);
+        let length = (intersection.t * speed).min(remaining);
+        if let Some(medium) = segment_medium(&segment_ray, &intersection, scene) {
+            transmittance = transmittance * medium.transmittance(length);
+        }
+
+        if intersection.t >= INFINITY || intersection.t * speed >= remaining {
+            if scene.volumes.is_empty() || transmittance.max_component() <= 0.0 {
+                return transmittance;
+            }
+            return transmittance * grid_transmittance(ray, distance, scene, sampler);
+        }
+        if intersection.material.medium().is_none() || transmittance.max_component() <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let hit = &segment_ray.origin + &(&segment_ray.direction * intersection.t);
+        origin = offset_ray_origin(&hit, &intersection.normal, &ray.direction);
+        remaining -= length;
+    }
+}
+
+// Light from the scene's lights reflected towards `wo`
+pub fn direct_lighting(
+    wo: &Vec3,
+    ray_hit: &Vec3,
+    normal: &Vec3,
+    material: &dyn Material,
+    scene: &Scene,
+    sampler: &mut Sampler,
+) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    for light in &scene.lights {
+        // Several shadow rays to different points of area lights give soft shadows
+        let samples = light.samples();
+        let mut light_color = Vec3::new(0.0, 0.0, 0.0);
+
+        for _ in 0..samples {
+            let sample = match light.sample(ray_hit, sampler.next_f64(), sampler.next_f64()) {
+                Some(sample) => sample,
+                None => continue,
+            };
+
+            let light_ray = Ray {
+                origin: offset_ray_origin(ray_hit, normal, &sample.direction),
+                direction: sample.direction.clone(),
+            };
+            // Directional lights are at INFINITY, which is also what misses report
+            let transmittance = shadow_transmittance(&light_ray, sample.distance, scene, sampler);
+            if transmittance.max_component() > 0.0 {
+                let light_on_surface = normal.dot(&sample.direction).abs();
+                let irradiance = sample.irradiance * &transmittance * light_on_surface;
+                light_color = light_color + material.evaluate(normal, wo, &sample.direction) * &irradiance;
+            }
+        }
+
+        color = color + light_color * (1.0 / samples as f64);
+    }
+
+    color
+}
+
+// Shadow rays towards bright parts of an environment or sky, those that escape the scene bring its light
+pub fn environment_lighting(
+    wo: &Vec3,
+    ray_hit: &Vec3,
+    normal: &Vec3,
+    material: &dyn Material,
+    scene: &Scene,
+    sampler: &mut Sampler,
+    mis: Mis,
+) -> Vec3 {
+    let samples = scene.background.samples();
+    if samples == 0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    for _ in 0..samples {
+        let sample = match scene.background.sample(sampler.next_f64(), sampler.next_f64()) {
+            Some(sample) => sample,
+            None => continue,
+        };
+
+        let light_ray = Ray {
+            origin: offset_ray_origin(ray_hit, normal, &sample.direction),
+            direction: sample.direction.clone(),
+        };
+        let transmittance = shadow_transmittance(&light_ray, INFINITY, scene, sampler);
+        if transmittance.max_component() <= 0.0 {
+            continue;
+        }
+
+        let light_on_surface = normal.dot(&sample.direction).abs();
+        let bsdf_pdf = material.pdf(normal, wo, &sample.direction);
+        let weight = light_on_surface / sample.pdf * mis.weight(samples as f64 * sample.pdf, bsdf_pdf);
+        color = color + material.evaluate(normal, wo, &sample.direction) * &sample.radiance * &transmittance * weight;
+    }
+
+    color * (1.0 / samples as f64)
+}
+
+// One point picked on every emissive sphere, quad and mesh, weighted by how it is seen from the hit
+pub fn emitter_lighting(
+    wo: &Vec3,
+    ray_hit: &Vec3,
+    normal: &Vec3,
+    material: &dyn Material,
+    scene: &Scene,
+    sampler: &mut Sampler,
+    mis: Mis,
+) -> Vec3 {
+    let samples = sample_emitters(scene, sampler);
+
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+    for emitter_sample in &samples {
+        if let Some((light_ray, distance, contribution)) = emitter_contribution(wo, ray_hit, normal, material, emitter_sample, mis) {
+            let transmittance = shadow_transmittance(&light_ray, distance, scene, sampler);
+            if transmittance.max_component() > 0.0 {
+                color = color + contribution * &transmittance;
+            }
+        }
+    }
+
+    color
+}
+
+// One point on every emissive sphere, quad and mesh, for next event estimation
+pub fn sample_emitters<'a>(scene: &'a Scene, sampler: &mut Sampler) -> Vec<(&'a dyn Material, SurfaceSample)> {
+    surface_emitters(scene)
+        .iter()
+        .filter_map(|emitter| emitter.sample_surface(sampler.next_f64(), sampler.next_f64()))
+        .collect()
+}
+
+// Light from one emitter sample if nothing is in the way, with the shadow ray and how
+// far it has to get
+fn emitter_contribution(
+    wo: &Vec3,
+    ray_hit: &Vec3,
+    normal: &Vec3,
+    material: &dyn Material,
+    &(emitter, ref sample): &(&dyn Material, SurfaceSample),
+    mis: Mis,
+) -> Option<(Ray, f64, Vec3)> {
+    let to_light = &sample.point - ray_hit;
+    let distance_sq = to_light.dot(&to_light);
+    let distance = distance_sq.sqrt();
+    if distance <= SHADOW_EPSILON {
+        return None;
+    }
+    let light_direction = &to_light * (1.0 / distance);
+
+    // Area to solid angle: the emitter looks smaller far away and seen edge-on
+    let emitted = emitter.emission(&sample.normal, &(&light_direction * -1.0));
+    let cos_light = sample.normal.dot(&light_direction).abs();
+    if emitted.max_component() <= 0.0 || cos_light <= 0.0 {
+        return None;
+    }
+
+    let light_on_surface = normal.dot(&light_direction).abs();
+    let light_pdf = emitter_pdf(sample.pdf, distance_sq, cos_light);
+    let bsdf_pdf = material.pdf(normal, wo, &light_direction);
+    let weight = light_on_surface / light_pdf * mis.weight(light_pdf, bsdf_pdf);
+    let contribution = material.evaluate(normal, wo, &light_direction) * &emitted * weight;
+
+    let light_ray = Ray {
+        origin: offset_ray_origin(ray_hit, normal, &light_direction),
+        direction: light_direction,
+    };
+    Some((light_ray, distance - SHADOW_EPSILON, contribution))
+}
+
+// Per solid angle, of picking a point on an emitter with `area_pdf` per unit of area
+pub fn emitter_pdf(area_pdf: f64, distance_sq: f64, cos_light: f64) -> f64 {
+    area_pdf * distance_sq / cos_light
+}
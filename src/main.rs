@@ -1,496 +1,208 @@
+extern crate ctrlc;
+extern crate rust_tracer;
+
+use std::env;
 use std::io::prelude::*;
 use std::fs::File;
-use std::path::Path;
-use vec2::Vec2;
-use vec3::Vec3;
-
-mod vec2;
-mod vec3;
-
-const RAY_MIN_LENGTH: f64 = 0.000000001;
-const INFINITY: f64 = 100000000000.0;
-
-const IOR_AIR: f64 = 1.00;
-// const IOR_WATER: f64 = 1.3333;
-// const IOR_ICE: f64 = 1.31;
-const IOR_GLASS: f64 = 1.52;
-// const IOR_DIAMOND: f64 = 2.42;
-
-#[derive(Debug)]
-struct Screen { width: u32, height: u32 }
-
-#[derive(Debug)]
-struct Color { r: f64, g: f64, b: f64 }
-
-#[derive(Debug)]
-struct CameraSize {
-    width: f64,
-    height: f64,
-}
-
-#[derive(Debug)]
-struct Camera {
-    origin: Vec3,
-    target: Vec3,
-    forward: Vec3,
-    right: Vec3,
-    up: Vec3,
-    size: CameraSize,
-}
-
-#[derive(Debug, Clone)]
-struct Material {
-    color: Vec3,
-    damping: f64,
-    reflectivity: f64,
-    refractivity: f64,
-    ior: f64,
-}
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+use rust_tracer::camera::create_camera;
+use rust_tracer::checkpoint::Checkpoint;
+use rust_tracer::film::Film;
+use rust_tracer::image::Image;
+use rust_tracer::integrator::{integrator_by_name, Integrator, Whitted};
+use rust_tracer::render::{render, CancelToken, CheckpointSettings, Progress, RenderSettings, RenderStatus, Screen};
+use rust_tracer::scene::create_scene;
+use rust_tracer::scene_file::{load_scene, View};
+use rust_tracer::vec3::Vec3;
+
+const PROGRESS_BAR_WIDTH: usize = 40;
+const DEFAULT_SEED: u64 = 0;
+
+const USAGE: &str = "Usage: rust-tracer [options]
+
+Options:
+    --scene <path>              Scene file to render (default the built-in demo scene)
+    --spp <n>                   Samples per pixel (default 1)
+    --integrator <name>         whitted, path, bdpt, photons, ao or normals (default the scene's, or whitted)
+    --checkpoint <path>         Accumulation checkpoint file (default out.checkpoint)
+    --checkpoint-interval <s>   Seconds between checkpoint saves (default 60)
+    --resume                    Continue adding samples to the checkpoint file";
 
 #[derive(Debug)]
-struct Light {
-    position: Vec3,
-    color: Vec3,
-    force: f64,
-}
-
-#[derive(Debug)]
-struct Scene {
-    lights: Vec<Light>,
-    spheres: Vec<Sphere>,
-    planes: Vec<Plane>,
-}
-
-#[derive(Debug)]
-struct Ray {
-    origin: Vec3,
-    direction: Vec3,
-}
-
-#[derive(Debug)]
-struct RayIntersection {
-    t: f64,
-    normal: Vec3,
-    material: Material,
-}
-
-#[derive(Debug)]
-struct Sphere {
-    position: Vec3,
-    radius: f64,
-    material: Material,
-}
-
-#[derive(Debug)]
-struct Plane {
-    position: Vec3,
-    normal: Vec3,
-    material: Material,
+struct Args {
+    scene_path: Option<PathBuf>,
+    integrator: Option<Box<dyn Integrator>>,
+    samples_per_pixel: u32,
+    checkpoint_path: PathBuf,
+    checkpoint_interval: Duration,
+    resume: bool,
 }
 
 fn main() {
-    let screen = Screen { width: 800 * 1, height: 600 * 1 };
-
-    let origin = Vec3::new(0.0, -0.6, 2.0);
-    let target = Vec3::new(0.0, 0.0, 0.0);
-    let up_guide = Vec3::new(0.0, 1.0, 0.0);
-    let fov = 45.0;
-    let aspect_ratio = screen.width as f64 / screen.height as f64;
-
-    let camera = create_camera(origin, target, fov, aspect_ratio, up_guide);
-    let scene = create_scene();
-
-    let mut pixels_str = String::new();
-
-    for y in 0..screen.height {
-        for x in 0..screen.width {
-            // -1.0 to 1.0
-            let normalized_pixel_location = Vec2::new(
-                x as f64 / screen.width as f64 * 2.0 - 1.0,
-                y as f64 / screen.height as f64 * 2.0 - 1.0
-            );
-
-            let color = render_pixel(normalized_pixel_location, &camera, &scene);
-
-            let color_str = format!(" {} {} {}", (color.r * 255.0) as u8, (color.g * 255.0) as u8, (color.b * 255.0) as u8);
-            pixels_str.push_str(&color_str);
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(1);
         }
-    }
-
-    write_to_file("out.ppm", pixels_str, &screen);
-}
-
-fn write_to_file(path_str: &str, pixels_str: String, screen: &Screen) {
-    let ppm_path = Path::new(path_str);
-    let output_str  = format!("P3 {} {} 255\n{}\n\n", screen.width, screen.height, pixels_str);
-
-    let mut ppmfile = match File::create(&ppm_path) {
-        Err(_why) => panic!("Couldn't create ppm file"),
-        Ok(file) => file,
     };
 
-    match ppmfile.write_all(output_str.as_bytes()) {
-        Err(_why) => panic!("Couldn't write to file"),
-        Ok(_) => println!("Success"),
-    }
-}
-
-fn render_pixel(normailized_pixel: Vec2, camera: &Camera, scene: &Scene) -> Color {
-    let camera_ray = create_ray_from_camera(&camera, &normailized_pixel);
-    trace_ray(camera_ray, &scene)
-}
+    let screen = Screen { width: 800, height: 600 };
 
-fn create_scene() -> Scene {
-    let sphere1 = Sphere {
-        position: Vec3::new(0.0, 1.0, -3.0),
-        radius: 0.9,
-        material: Material {
-            color: Vec3::new(1.0, 0.2, 0.2),
-            damping: 1.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: IOR_GLASS,
-        },
-    };
-    let sphere2 = Sphere {
-        position: Vec3::new(-1.0, 1.0, -2.0),
-        radius: 0.4,
-        material: Material {
-            color: Vec3::new(0.0, 0.7, 0.0),
-            damping: 1.0,
-            reflectivity: 0.5,
-            refractivity: 0.0,
-            ior: IOR_GLASS,
-        },
-    };
-    let sphere3 = Sphere {
-        position: Vec3::new(1.0, 1.2, -2.0),
-        radius: 0.6,
-        material: Material {
-            color: Vec3::new(0.5, 0.5, 0.5),
-            damping: 1.0,
-            reflectivity: 1.0,
-            refractivity: 1.0,
-            ior: IOR_GLASS,
-        },
-    };
-    let plane_floor = Plane {
-        position: Vec3::new(0.0, 1.9, 0.0),
-        normal: Vec3::new(0.0, -1.0, 0.0),
-        material: Material {
-            color: Vec3::new(0.1, 0.1, 0.1),
-            damping: 1.0,
-            reflectivity: 0.4,
-            refractivity: 1.0,
-            ior: IOR_GLASS,
-        },
-    };
-    let plane_ceiling = Plane {
-        position: Vec3::new(0.0, -6.5, 0.0),
-        normal: Vec3::new(0.0, 1.0, 0.0),
-        material: Material {
-            color: Vec3::new(0.2, 0.2, 0.2),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
-        },
-    };
-    let plane_left = Plane {
-        position: Vec3::new(-2.3, 0.0, 0.0),
-        normal: Vec3::new(-1.0, 0.0, 0.0),
-        material: Material {
-            color: Vec3::new(0.2, 0.2, 0.2),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
-        },
-    };
-    let plane_right = Plane {
-        position: Vec3::new(2.3, 0.0, 0.0),
-        normal: Vec3::new(1.0, 0.0, 0.0),
-        material: Material {
-            color: Vec3::new(0.2, 0.2, 0.2),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
-        },
-    };
-    let plane_backwall = Plane {
-        position: Vec3::new(0.0, 0.0, -3.5),
-        normal: Vec3::new(0.0, 0.0, 1.0),
-        material: Material {
-            color: Vec3::new(0.2, 0.2, 0.2),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
-        },
-    };
-    let plane_wall = Plane {
-        position: Vec3::new(0.0, 0.0, 4.0),
-        normal: Vec3::new(0.0, 0.0, -1.0),
-        material: Material {
-            color: Vec3::new(0.2, 0.2, 0.2),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
+    let (scene, view, scene_integrator) = match args.scene_path {
+        Some(ref path) => match load_scene(path) {
+            Ok(scene_file) => (scene_file.scene, scene_file.view, scene_file.integrator),
+            Err(err) => {
+                eprintln!("Can't load scene {}: {}", path.display(), err);
+                process::exit(1);
+            }
         },
+        None => (create_scene(), None, None),
     };
-    let light1 = Light {
-        position: Vec3::new(2.2, -6.0, 0.0),
-        color: Vec3::new(1.0, 1.0, 1.0),
-        force: 50.0
-    };
-    let light2 = Light {
-        position: Vec3::new(-2.2, -6.0, 2.0),
-        color: Vec3::new(1.0, 1.0, 1.0),
-        force: 39.0
-    };
-
-    let lights = vec![light1, light2];
-    let spheres = vec![sphere1, sphere2, sphere3];
-    let planes = vec![plane_ceiling, plane_floor, plane_wall, plane_backwall, plane_left, plane_right];
-
-    Scene {
-        lights,
-        spheres,
-        planes,
-    }
-}
-fn create_camera(origin: Vec3, target: Vec3, fov: f64, aspect_ratio: f64, up_guide: Vec3) -> Camera {
-    let height = (fov.to_radians() * 0.5).tan();
-    let width = height * aspect_ratio;
-    let forward = (&target - &origin).unit(); 
-    let right = forward.cross(&up_guide).unit();
-    let up = right.cross(&forward).unit();
-    let size = CameraSize { width, height };
-
-    Camera {
-        origin,
-        target,
-        forward, 
-        right,
-        up,
-        size,
-    }
-}
-
-fn create_ray_from_camera(camera :&Camera, px: &Vec2) -> Ray {
-    let x_offset = camera.right.clone() * px.x * camera.size.width;
-    let y_offset = camera.up.clone() * px.y * camera.size.height;
-    let ray_direction = camera.forward.clone() + x_offset + y_offset;
 
-    Ray {
-        origin: camera.origin.clone(),
-        direction: ray_direction,
-    }
-}
-
-fn trace_ray(initial_ray: Ray, scene: &Scene) -> Color {
-    let mut final_color = Vec3::new(0.10, 0.10, 0.11);
-    let mut frac = 1.0;
-    let mut ray = initial_ray;
-
-    for _raybounce in 0..6 {
-        let mut next_ray: Option<Ray> = None;
-        let mut next_frac = 1.0;
-        let mut break_bounce_loop = false;
-        let intersection = trace(&ray, &scene);
-        
-        if intersection.t >= INFINITY {
-            break_bounce_loop = true;
-        } else {
-            let ray_hit = &ray.origin + &(&ray.direction * intersection.t);
-            let mut diffuse = Vec3::new(0.0, 0.0, 0.0);
-            let mut specular = Vec3::new(0.0, 0.0, 0.0);
-            let refraction = Vec3::new(0.0, 0.0, 0.0);
-
-            for light in &scene.lights {
-                let ray_hit_to_light = &light.position - &ray_hit;
-                let light_direction = ray_hit_to_light.unit();
-                let light_dst_sq = ray_hit_to_light.dot(&ray_hit_to_light);
-                let distance_fade = 1.0 / light_dst_sq;
-
-                // === Diffuse
-                let mut brightness = 0.2 * distance_fade;
-                
-                let light_ray = Ray {
-                    origin: &ray_hit + &(&intersection.normal * 0.001),
-                    direction: light_direction.unit(),
-                };
-                let light_intersection = trace(&light_ray, &scene);
+    let view = view.unwrap_or(View {
+        origin: Vec3::new(0.0, -0.6, 2.0),
+        target: Vec3::new(0.0, 0.0, 0.0),
+        up: Vec3::new(0.0, 1.0, 0.0),
+        fov: 45.0,
+    });
+    let aspect_ratio = screen.width as f64 / screen.height as f64;
 
-                if light_intersection.t * light_intersection.t > light_dst_sq {
-                    let mut light_on_surface = intersection.normal.dot(&light_direction);
-                    if light_on_surface < 0.0 {
-                        light_on_surface = 0.0;
-                    }
-                    brightness += 0.8 * light_on_surface * (light.force * distance_fade);
-                }
+    let integrator = args.integrator
+        .or(scene_integrator)
+        .unwrap_or_else(|| Box::new(Whitted::default()));
 
-                diffuse = diffuse + &intersection.material.color * brightness;
-                
-                // === Specular
-                let reflected_light_direction = light_direction.reflect(&intersection.normal);
-                let mut specular_factor = reflected_light_direction.dot(&ray.direction);
-                if specular_factor < 0.0 {
-                    specular_factor = 0.0;
-                }
-                let damped_specular = specular_factor.powf(intersection.material.damping);
-                specular = specular + damped_specular * distance_fade;
+    let camera = create_camera(view.origin, view.target, view.fov, aspect_ratio, view.up);
 
-                // === Reflection
-                if intersection.material.reflectivity > 0.0 {
-                    next_ray = Some(Ray {
-                        origin: ray_hit.clone(),
-                        direction: ray.direction.reflect(&intersection.normal),
-                    });
-                    next_frac *= intersection.material.reflectivity;
-                } else if intersection.material.refractivity > 0.0 {
-                    next_ray = Some(Ray {
-                        origin: &ray_hit + &(&ray.direction * 0.0001),
-                        direction: ray.direction.refract(&intersection.normal, IOR_AIR / intersection.material.ior),
-                    });
-                    next_frac *= intersection.material.refractivity;
-                } else {
-                    break_bounce_loop = true;
-                }
+    let (mut film, seed) = if args.resume {
+        match Checkpoint::resume(&args.checkpoint_path, &scene, &camera, &screen, &*integrator) {
+            Ok(checkpoint) => {
+                println!("Resuming from {} samples per pixel", checkpoint.film.min_samples());
+                (checkpoint.film, checkpoint.seed)
             }
-
-            let diff_spec_refrac = (diffuse + specular).mix(&refraction, intersection.material.refractivity);
-
-            final_color = final_color + diff_spec_refrac * frac;
-        }
-
-        if break_bounce_loop || frac < 0.01 {
-            break;
-        } else  {
-            if let Some(next_ray_safe) = next_ray {
-                frac = next_frac;
-                ray = next_ray_safe;
-            } else {
-                break;
+            Err(err) => {
+                eprintln!("Can't resume from {}: {}", args.checkpoint_path.display(), err);
+                process::exit(1);
             }
         }
-    }
+    } else {
+        (Film::new(screen.width, screen.height), DEFAULT_SEED)
+    };
 
-    // add contrast
-    final_color = (final_color - 0.5) * 1.10 + 0.5;
+    let mut settings = RenderSettings {
+        integrator,
+        samples_per_pixel: args.samples_per_pixel,
+        seed,
+        checkpoint: Some(CheckpointSettings {
+            path: args.checkpoint_path,
+            interval: args.checkpoint_interval,
+        }),
+    };
 
-    if final_color.x < 0.0 { final_color.x = 0.0; }
-    if final_color.y < 0.0 { final_color.y = 0.0; }
-    if final_color.z < 0.0 { final_color.z = 0.0; }
+    // Ctrl-C stops the render at the next pixel, what was rendered so far still gets written
+    let cancel = CancelToken::new();
+    let handler_cancel = cancel.clone();
+    if ctrlc::set_handler(move || handler_cancel.cancel()).is_err() {
+        eprintln!("Couldn't install Ctrl-C handler, render can't be interrupted cleanly");
+    }
 
-    if final_color.x > 1.0 { final_color.x = 1.0; }
-    if final_color.y > 1.0 { final_color.y = 1.0; }
-    if final_color.z > 1.0 { final_color.z = 1.0; }
+    let status = render(&scene, &camera, &screen, &mut settings, &mut film, &cancel, print_progress);
+    eprintln!();
 
+    match status {
+        Ok(RenderStatus::Complete) => {}
+        Ok(RenderStatus::Cancelled) => println!("Render cancelled, writing partial image"),
+        Err(err) => eprintln!("Couldn't save checkpoint: {}, writing partial image", err),
+    }
 
-    Color { r: final_color.x, g: final_color.y, b: final_color.z }
+    write_to_file("out.ppm", &film.to_image());
 }
 
-fn trace(ray: &Ray, scene: &Scene) -> RayIntersection {
-    let mut closest_intersection = RayIntersection {
-        t: INFINITY,
-        normal: Vec3::new(0.0, 0.0, 0.0),
-        material: Material{
-            color: Vec3::new(0.0, 0.0, 0.0),
-            damping: 0.0,
-            reflectivity: 0.0,
-            refractivity: 0.0,
-            ior: 0.0,
-        }
+fn parse_args<I: Iterator<Item = String>>(mut raw_args: I) -> Result<Args, String> {
+    let mut args = Args {
+        scene_path: None,
+        integrator: None,
+        samples_per_pixel: 1,
+        checkpoint_path: PathBuf::from("out.checkpoint"),
+        checkpoint_interval: Duration::from_secs(60),
+        resume: false,
     };
 
-    for sphere in &scene.spheres {
-        match intersect_sphere(ray, sphere) {
-            Ok(intersection) => {
-                if intersection.t < closest_intersection.t {
-                    closest_intersection = intersection;
-                }
-            },
-            _ => {}
-        }
-    }
-
-    for plane in &scene.planes {
-        match intersect_plane(ray, plane) {
-            Ok(intersection) => {
-                if intersection.t < closest_intersection.t {
-                    closest_intersection = intersection;
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "--scene" => {
+                args.scene_path = Some(PathBuf::from(require_value(&arg, raw_args.next())?));
+            }
+            "--integrator" => {
+                let name = require_value(&arg, raw_args.next())?;
+                match integrator_by_name(&name) {
+                    Some(integrator) => args.integrator = Some(integrator),
+                    None => return Err(format!("Unknown integrator {}", name)),
                 }
-            },
-            _ => {}
+            }
+            "--spp" => {
+                args.samples_per_pixel = parse_value(&arg, raw_args.next())?;
+            }
+            "--checkpoint" => {
+                args.checkpoint_path = PathBuf::from(require_value(&arg, raw_args.next())?);
+            }
+            "--checkpoint-interval" => {
+                args.checkpoint_interval = Duration::from_secs(parse_value(&arg, raw_args.next())?);
+            }
+            "--resume" => args.resume = true,
+            _ => return Err(format!("Unknown option {}", arg)),
         }
     }
 
-    closest_intersection
+    Ok(args)
 }
 
-fn intersect_plane(ray: &Ray, plane: &Plane) -> Result<RayIntersection, RayError> {
-    // First, check if we intersect
-    let d_dot_n = ray.direction.dot(&plane.normal);
-
-    if d_dot_n == 0.0 {
-        // We just assume the ray is not embedded in the plane
-        return Err(RayError::NoIntersections)
-    }
-
-    // Find point of intersection
-    let t = (&plane.position - &ray.origin).dot(&plane.normal) / d_dot_n;
-
-    if t <= RAY_MIN_LENGTH || t >= INFINITY {
-        // Outside relevant range
-        return Err(RayError::NoIntersections)
-    }
-
-    Ok(RayIntersection {
-        t: t,
-        normal: plane.normal.clone(),
-        material: plane.material.clone(),
-    })
+fn require_value(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("Missing value for {}", option))
 }
 
-enum RayError {
-    NoIntersections
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = require_value(option, value)?;
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", option, value))
 }
 
-fn intersect_sphere(ray: &Ray, sphere: &Sphere) -> Result<RayIntersection, RayError> {
-    // Transform ray so we can consider origin-centred sphere
-    let local_ray = Ray {
-        origin: &ray.origin - &sphere.position,
-        direction: ray.direction.clone(),
+fn print_progress(progress: &Progress) {
+    let fraction = progress.fraction();
+    let filled = (fraction * PROGRESS_BAR_WIDTH as f64) as usize;
+    let eta = match progress.eta() {
+        Some(eta) => format_duration(eta),
+        None => String::from("--:--"),
     };
 
-    // Calculate quadratic coefficients
-    let a = local_ray.direction.dot(&local_ray.direction);
-    let b = 2.0 * local_ray.direction.dot(&local_ray.origin);
-    let c = local_ray.origin.dot(&local_ray.origin) - sphere.radius * sphere.radius;
+    eprint!(
+        "\r[{}{}] {:>3}% ETA {}",
+        "#".repeat(filled),
+        " ".repeat(PROGRESS_BAR_WIDTH - filled),
+        (fraction * 100.0) as u32,
+        eta
+    );
+    let _ = io::stderr().flush();
+}
 
-    // Check whether we intersect
-    let discriminant = b * b - 4.0 * a * c;
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
 
-    if discriminant < 0.0 {
-        return Err(RayError::NoIntersections)
-    }
+fn write_to_file(path_str: &str, image: &Image) {
+    let ppm_path = Path::new(path_str);
+    let output_str = image.to_ppm();
 
-    // Find two points of intersection, t1 close and t2 far
-    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-    // let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+    let mut ppmfile = match File::create(ppm_path) {
+        Err(_why) => panic!("Couldn't create ppm file"),
+        Ok(file) => file,
+    };
 
-    // First check if close intersection is valid
-    if t1 > RAY_MIN_LENGTH {
-        Ok(RayIntersection {
-            t: t1,
-            normal: (&ray.origin + &(&ray.direction * t1) - &sphere.position).unit(),
-            material: sphere.material.clone(),
-        })
-    } else {
-        // Neither are valid
-        return Err(RayError::NoIntersections)
+    match ppmfile.write_all(output_str.as_bytes()) {
+        Err(_why) => panic!("Couldn't write to file"),
+        Ok(_) => println!("Success"),
     }
 }
@@ -0,0 +1,113 @@
+use material::{black, facing_normal, reflect, BsdfSample, Material};
+use optics::fresnel_dielectric;
+use sampler::Sampler;
+use vec3::Vec3;
+
+pub const IOR_AIR: f64 = 1.00;
+pub const IOR_WATER: f64 = 1.3333;
+pub const IOR_ICE: f64 = 1.31;
+pub const IOR_GLASS: f64 = 1.52;
+pub const IOR_DIAMOND: f64 = 2.42;
+
+// Smooth boundary between air and a clear medium of index `ior`. Light is split
+// between reflection and refraction by Fresnel, and `absorption` (per channel,
+// per unit of distance travelled inside, Beer-Lambert) gives thick glass or deep
+// water its colour.
+#[derive(Debug, Clone)]
+pub struct Dielectric {
+    pub ior: f64,
+    pub absorption: Vec3,
+}
+
+// Presets for common transparent materials. Tint them with struct update syntax:
+// Dielectric { absorption: Vec3::new(0.8, 0.1, 0.6), ..Dielectric::glass() }
+impl Dielectric {
+    pub fn glass() -> Dielectric {
+        Dielectric { ior: IOR_GLASS, absorption: black() }
+    }
+
+    // Pure water absorbs red the most, which is only visible once it gets deep
+    pub fn water() -> Dielectric {
+        Dielectric { ior: IOR_WATER, absorption: Vec3::new(0.34, 0.06, 0.01) }
+    }
+
+    pub fn ice() -> Dielectric {
+        Dielectric { ior: IOR_ICE, absorption: Vec3::new(0.25, 0.04, 0.01) }
+    }
+
+    pub fn diamond() -> Dielectric {
+        Dielectric { ior: IOR_DIAMOND, absorption: black() }
+    }
+
+    // (reflected, fresnel reflectance, refracted direction unless total internal reflection)
+    fn split(&self, normal: &Vec3, wo: &Vec3) -> (Vec3, f64, Option<Vec3>) {
+        let entering = normal.dot(wo) > 0.0;
+        let (eta_i, eta_t) = if entering {
+            (IOR_AIR, self.ior)
+        } else {
+            (self.ior, IOR_AIR)
+        };
+        let normal = facing_normal(normal, wo);
+
+        let reflectance = fresnel_dielectric(normal.dot(wo), eta_i, eta_t);
+        let reflected = reflect(wo, &normal);
+        let refracted = (wo * -1.0).refract(&normal, eta_i / eta_t);
+
+        (reflected, reflectance, refracted)
+    }
+}
+
+impl Material for Dielectric {
+    fn evaluate(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> Vec3 {
+        black()
+    }
+
+    // Picks reflection or refraction with the Fresnel probabilities, so the weight is always 1
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let (reflected, reflectance, refracted) = self.split(normal, wo);
+
+        let (direction, pdf) = match refracted {
+            Some(refracted) if sampler.next_f64() >= reflectance => (refracted, 1.0 - reflectance),
+            _ => (reflected, reflectance),
+        };
+
+        Some(BsdfSample {
+            direction,
+            weight: Vec3::new(1.0, 1.0, 1.0),
+            pdf,
+            specular: true,
+        })
+    }
+
+    fn pdf(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        let (reflected, reflectance, refracted) = self.split(normal, wo);
+
+        let mut lobes = vec![BsdfSample {
+            direction: reflected,
+            weight: Vec3::new(reflectance, reflectance, reflectance),
+            pdf: reflectance,
+            specular: true,
+        }];
+
+        // None means total internal reflection, then the reflectance above was already 1.0
+        if let Some(refracted) = refracted {
+            let transmittance = 1.0 - reflectance;
+            lobes.push(BsdfSample {
+                direction: refracted,
+                weight: Vec3::new(transmittance, transmittance, transmittance),
+                pdf: transmittance,
+                specular: true,
+            });
+        }
+
+        lobes
+    }
+
+    fn absorption(&self) -> Vec3 {
+        self.absorption.clone()
+    }
+}
@@ -0,0 +1,35 @@
+use material::{black, BsdfSample, Material};
+use sampler::Sampler;
+use vec3::Vec3;
+
+// Glows with `radiance` on the side its outward normal points to and reflects nothing.
+// Any sphere, quad or mesh made of it is an area light.
+#[derive(Debug, Clone)]
+pub struct Emissive {
+    pub radiance: Vec3,
+}
+
+impl Material for Emissive {
+    fn evaluate(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> Vec3 {
+        black()
+    }
+
+    fn sample(&self, _normal: &Vec3, _wo: &Vec3, _sampler: &mut Sampler) -> Option<BsdfSample> {
+        None
+    }
+
+    fn pdf(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn emission(&self, normal: &Vec3, wo: &Vec3) -> Vec3 {
+        if normal.dot(wo) <= 0.0 {
+            return black();
+        }
+        self.radiance.clone()
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
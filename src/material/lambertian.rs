@@ -0,0 +1,41 @@
+use std::f64::consts::PI;
+
+use material::{black, facing_normal, BsdfSample, Material};
+use sampler::Sampler;
+use vec3::Vec3;
+use warp::{cosine_hemisphere, cosine_hemisphere_pdf, Frame};
+
+#[derive(Debug, Clone)]
+pub struct Lambertian {
+    pub albedo: Vec3,
+}
+
+impl Material for Lambertian {
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let normal = facing_normal(normal, wo);
+        if normal.dot(wi) <= 0.0 {
+            return black();
+        }
+        &self.albedo * (1.0 / PI)
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let normal = facing_normal(normal, wo);
+        let local = cosine_hemisphere(sampler.next_f64(), sampler.next_f64());
+
+        Some(BsdfSample {
+            direction: Frame::from_normal(&normal).to_world(&local),
+            weight: self.albedo.clone(),
+            pdf: cosine_hemisphere_pdf(local.z),
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        cosine_hemisphere_pdf(facing_normal(normal, wo).dot(wi))
+    }
+
+    fn diffuse_albedo(&self) -> Vec3 {
+        self.albedo.clone()
+    }
+}
@@ -0,0 +1,86 @@
+use brdf::{ggx_reflection, ggx_reflection_pdf, is_smooth, roughness_to_alpha, sample_ggx_half_vector};
+use material::{black, facing_normal, reflect, BsdfSample, Material};
+use optics::fresnel_schlick;
+use sampler::Sampler;
+use vec3::Vec3;
+use warp::Frame;
+
+// Conductor with a GGX microfacet surface. `color` is the reflectance at normal
+// incidence (Schlick), roughness 0.0 is a polished mirror.
+#[derive(Debug, Clone)]
+pub struct Metal {
+    pub color: Vec3,
+    pub roughness: f64,
+}
+
+impl Metal {
+    pub fn gold(roughness: f64) -> Metal {
+        Metal { color: Vec3::new(1.0, 0.78, 0.34), roughness }
+    }
+
+    pub fn silver(roughness: f64) -> Metal {
+        Metal { color: Vec3::new(0.97, 0.96, 0.91), roughness }
+    }
+
+    pub fn copper(roughness: f64) -> Metal {
+        Metal { color: Vec3::new(0.96, 0.64, 0.54), roughness }
+    }
+}
+
+impl Material for Metal {
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if is_smooth(self.roughness) {
+            return black();
+        }
+
+        let normal = facing_normal(normal, wo);
+        let half = (wo + wi).unit();
+        let alpha = roughness_to_alpha(self.roughness);
+
+        fresnel_schlick(&self.color, wo.dot(&half)) * ggx_reflection(&normal, wo, wi, alpha)
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        if is_smooth(self.roughness) {
+            return self.specular_lobes(normal, wo).pop();
+        }
+
+        let facing = facing_normal(normal, wo);
+        let alpha = roughness_to_alpha(self.roughness);
+        let half = Frame::from_normal(&facing).to_world(&sample_ggx_half_vector(alpha, sampler.next_f64(), sampler.next_f64()));
+        let direction = reflect(wo, &half);
+
+        let pdf = ggx_reflection_pdf(&facing, wo, &direction, alpha);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            weight: self.evaluate(normal, wo, &direction) * (facing.dot(&direction) / pdf),
+            direction,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        if is_smooth(self.roughness) {
+            return 0.0;
+        }
+        ggx_reflection_pdf(&facing_normal(normal, wo), wo, wi, roughness_to_alpha(self.roughness))
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        if !is_smooth(self.roughness) {
+            return Vec::new();
+        }
+
+        let normal = facing_normal(normal, wo);
+        vec![BsdfSample {
+            direction: reflect(wo, &normal),
+            weight: fresnel_schlick(&self.color, normal.dot(wo)),
+            pdf: 1.0,
+            specular: true,
+        }]
+    }
+}
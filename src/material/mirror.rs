@@ -0,0 +1,32 @@
+use material::{black, facing_normal, reflect, BsdfSample, Material};
+use sampler::Sampler;
+use vec3::Vec3;
+
+// Perfect mirror, `reflectance` tints what it reflects
+#[derive(Debug, Clone)]
+pub struct Mirror {
+    pub reflectance: Vec3,
+}
+
+impl Material for Mirror {
+    fn evaluate(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> Vec3 {
+        black()
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, _sampler: &mut Sampler) -> Option<BsdfSample> {
+        self.specular_lobes(normal, wo).pop()
+    }
+
+    fn pdf(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        vec![BsdfSample {
+            direction: reflect(wo, &facing_normal(normal, wo)),
+            weight: self.reflectance.clone(),
+            pdf: 1.0,
+            specular: true,
+        }]
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Debug;
+
+use medium::Medium;
+use sampler::Sampler;
+use vec3::Vec3;
+
+mod dielectric;
+mod emissive;
+mod lambertian;
+mod metal;
+mod mirror;
+mod plastic;
+mod principled;
+mod subsurface;
+mod volume;
+
+pub use self::dielectric::{Dielectric, IOR_AIR, IOR_DIAMOND, IOR_GLASS, IOR_ICE, IOR_WATER};
+pub use self::emissive::Emissive;
+pub use self::lambertian::Lambertian;
+pub use self::metal::Metal;
+pub use self::mirror::Mirror;
+pub use self::plastic::Plastic;
+pub use self::principled::Principled;
+pub use self::subsurface::Subsurface;
+pub use self::volume::Volume;
+
+// An incoming direction picked by `Material::sample`
+#[derive(Debug, Clone)]
+pub struct BsdfSample {
+    pub direction: Vec3,
+    // bsdf * |cos| / pdf, what the light coming from `direction` gets multiplied by
+    pub weight: Vec3,
+    pub pdf: f64,
+    // Perfect mirror or refraction: evaluate() and pdf() can't see it, only sampling finds it
+    pub specular: bool,
+}
+
+// How a surface scatters light. All directions point away from the surface:
+// `wo` towards the viewer, `wi` towards where the light comes from.
+// `normal` is the outward geometric normal, materials work out which side they are
+// seen from themselves (opaque ones are two-sided, dielectrics care about entering/exiting).
+pub trait Material: Debug + Send + Sync {
+    // BSDF value, without the cosine. Zero for specular lobes.
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3;
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample>;
+
+    // Density `sample` picks wi with, per solid angle. Zero for specular lobes.
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64;
+
+    // Every specular direction with its weight, for tracers that follow them
+    // all deterministically instead of sampling one
+    fn specular_lobes(&self, _normal: &Vec3, _wo: &Vec3) -> Vec<BsdfSample> {
+        Vec::new()
+    }
+
+    // Radiance leaving the surface towards `wo` by itself
+    fn emission(&self, _normal: &Vec3, _wo: &Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // Objects made of emissive materials get sampled as lights
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // Rough diffuse reflectance, only used to scale the flat ambient term
+    fn diffuse_albedo(&self) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // Beer-Lambert coefficient of the object's interior
+    fn absorption(&self) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // Participating medium filling the object, for boundaries light passes straight through
+    fn medium(&self) -> Option<&Medium> {
+        None
+    }
+
+    // Translucent materials the path tracer follows light into
+    fn subsurface(&self) -> Option<&Subsurface> {
+        None
+    }
+}
+
+// The normal flipped to the side `wo` is on
+pub fn facing_normal(normal: &Vec3, wo: &Vec3) -> Vec3 {
+    if normal.dot(wo) < 0.0 {
+        normal * -1.0
+    } else {
+        normal.clone()
+    }
+}
+
+// Mirror `w` around `normal`, both pointing away from the surface
+pub fn reflect(w: &Vec3, normal: &Vec3) -> Vec3 {
+    &(normal * (2.0 * w.dot(normal))) - w
+}
+
+pub fn black() -> Vec3 {
+    Vec3::new(0.0, 0.0, 0.0)
+}
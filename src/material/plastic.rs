@@ -0,0 +1,137 @@
+use std::f64::consts::PI;
+
+use brdf::{ggx_reflection, ggx_reflection_pdf, is_smooth, roughness_to_alpha, sample_ggx_half_vector};
+use material::{black, facing_normal, reflect, BsdfSample, Material, IOR_AIR};
+use optics::fresnel_dielectric;
+use sampler::Sampler;
+use vec3::Vec3;
+use warp::{cosine_hemisphere, cosine_hemisphere_pdf, Frame};
+
+const PLASTIC_IOR: f64 = 1.5;
+
+// Diffuse base under a clear coat (plastic, paint, varnish). The coat reflects by
+// Fresnel, GGX when rough or a perfect mirror at roughness 0.0, and the base only
+// gets the light that made it through the coat on the way in and on the way out.
+#[derive(Debug, Clone)]
+pub struct Plastic {
+    pub diffuse: Vec3,
+    pub roughness: f64,
+    pub ior: f64,
+}
+
+impl Plastic {
+    pub fn new(diffuse: Vec3, roughness: f64) -> Plastic {
+        Plastic { diffuse, roughness, ior: PLASTIC_IOR }
+    }
+
+    fn coat_reflectance(&self, cos_theta: f64) -> f64 {
+        fresnel_dielectric(cos_theta, IOR_AIR, self.ior)
+    }
+
+    // Chance to sample the coat rather than the base, about how much each reflects
+    fn specular_probability(&self, cos_o: f64) -> f64 {
+        let coat = self.coat_reflectance(cos_o);
+        let base = (1.0 - coat) * (self.diffuse.x + self.diffuse.y + self.diffuse.z) / 3.0;
+
+        if coat + base <= 0.0 {
+            return 1.0;
+        }
+        coat / (coat + base)
+    }
+
+    // Facing normal expected
+    fn base(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let transmitted_in = 1.0 - self.coat_reflectance(normal.dot(wi));
+        let transmitted_out = 1.0 - self.coat_reflectance(normal.dot(wo));
+        &self.diffuse * (transmitted_in * transmitted_out / PI)
+    }
+}
+
+impl Material for Plastic {
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let normal = facing_normal(normal, wo);
+        if normal.dot(wi) <= 0.0 {
+            return black();
+        }
+
+        let base = self.base(&normal, wo, wi);
+        if is_smooth(self.roughness) {
+            return base;
+        }
+
+        let half = (wo + wi).unit();
+        let coat = self.coat_reflectance(wo.dot(&half)) * ggx_reflection(&normal, wo, wi, roughness_to_alpha(self.roughness));
+        base + coat
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let facing = facing_normal(normal, wo);
+        let specular_probability = self.specular_probability(facing.dot(wo));
+
+        let direction = if sampler.next_f64() < specular_probability {
+            if is_smooth(self.roughness) {
+                let reflectance = self.coat_reflectance(facing.dot(wo));
+                return Some(BsdfSample {
+                    direction: reflect(wo, &facing),
+                    weight: Vec3::new(1.0, 1.0, 1.0) * (reflectance / specular_probability),
+                    pdf: specular_probability,
+                    specular: true,
+                });
+            }
+
+            let alpha = roughness_to_alpha(self.roughness);
+            let half = Frame::from_normal(&facing).to_world(&sample_ggx_half_vector(alpha, sampler.next_f64(), sampler.next_f64()));
+            reflect(wo, &half)
+        } else {
+            Frame::from_normal(&facing).to_world(&cosine_hemisphere(sampler.next_f64(), sampler.next_f64()))
+        };
+
+        // Either way the direction could have come from the other lobe too
+        let pdf = self.pdf(normal, wo, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            weight: self.evaluate(normal, wo, &direction) * (facing.dot(&direction) / pdf),
+            direction,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        let normal = facing_normal(normal, wo);
+        let cos_i = normal.dot(wi);
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let specular_probability = self.specular_probability(normal.dot(wo));
+        let diffuse_pdf = (1.0 - specular_probability) * cosine_hemisphere_pdf(cos_i);
+
+        if is_smooth(self.roughness) {
+            return diffuse_pdf;
+        }
+        diffuse_pdf + specular_probability * ggx_reflection_pdf(&normal, wo, wi, roughness_to_alpha(self.roughness))
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        if !is_smooth(self.roughness) {
+            return Vec::new();
+        }
+
+        let normal = facing_normal(normal, wo);
+        let reflectance = self.coat_reflectance(normal.dot(wo));
+        vec![BsdfSample {
+            direction: reflect(wo, &normal),
+            weight: Vec3::new(reflectance, reflectance, reflectance),
+            pdf: 1.0,
+            specular: true,
+        }]
+    }
+
+    fn diffuse_albedo(&self) -> Vec3 {
+        &self.diffuse * (1.0 - self.coat_reflectance(1.0))
+    }
+}
@@ -0,0 +1,337 @@
+use std::f64::consts::PI;
+
+use brdf::{ggx_reflection, ggx_reflection_pdf, is_smooth, roughness_to_alpha, sample_ggx_half_vector};
+use material::{black, facing_normal, reflect, BsdfSample, Material, IOR_AIR};
+use optics::{fresnel_dielectric, fresnel_schlick};
+use sampler::Sampler;
+use vec3::Vec3;
+use warp::{cosine_hemisphere, cosine_hemisphere_pdf, Frame};
+
+// Reflectance of the clear coat at normal incidence, a polyurethane-like ior of 1.5
+const CLEARCOAT_F0: f64 = 0.04;
+// Disney's "specular" 0.5 is 4% reflectance, 1.0 is 8%
+const SPECULAR_F0_SCALE: f64 = 0.08;
+
+// Disney's principled BSDF (Burley 2012, with the 2015 transmission extension), all
+// parameters in [0, 1] so artists can blend between looks. Diffuse uses Burley's
+// retro-reflective model blended towards a Hanrahan-Krueger subsurface approximation,
+// specular and clear coat are GGX lobes and transmission is a smooth refraction.
+//
+// Differences from the paper: the clear coat is a GGX lobe whose roughness goes from
+// 0.3 to 0.0 with `clearcoat_gloss` (the paper uses GTR1) so a full gloss coat is a
+// mirror, and transmission ignores roughness.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_color: Vec3,
+    pub subsurface: f64,
+    pub metallic: f64,
+    pub specular: f64,
+    pub specular_tint: f64,
+    pub roughness: f64,
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    pub clearcoat: f64,
+    pub clearcoat_gloss: f64,
+    pub transmission: f64,
+    pub ior: f64,
+}
+
+// Relative chance of sampling each lobe, about how much light each one reflects
+struct LobeProbabilities {
+    diffuse: f64,
+    specular: f64,
+    clearcoat: f64,
+    transmission: f64,
+}
+
+impl Principled {
+    // The defaults of Disney's BRDF explorer: a dielectric with a 4% specular
+    pub fn new(base_color: Vec3) -> Principled {
+        Principled {
+            base_color,
+            subsurface: 0.0,
+            metallic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            roughness: 0.5,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+
+    // Base color with its luminance taken out, so tints only change the hue
+    fn tint(&self) -> Vec3 {
+        let color = &self.base_color;
+        let luminance = 0.3 * color.x + 0.6 * color.y + 0.1 * color.z;
+
+        if luminance <= 0.0 {
+            return Vec3::new(1.0, 1.0, 1.0);
+        }
+        color * (1.0 / luminance)
+    }
+
+    fn specular_f0(&self) -> Vec3 {
+        let dielectric = Vec3::new(1.0, 1.0, 1.0).mix(&self.tint(), self.specular_tint) * (self.specular * SPECULAR_F0_SCALE);
+        dielectric.mix(&self.base_color, self.metallic)
+    }
+
+    // Share of the light that reaches the diffuse and sheen lobes
+    fn diffuse_weight(&self) -> f64 {
+        (1.0 - self.metallic) * (1.0 - self.transmission)
+    }
+
+    fn transmission_weight(&self) -> f64 {
+        (1.0 - self.metallic) * self.transmission
+    }
+
+    fn clearcoat_roughness(&self) -> f64 {
+        0.3 * (1.0 - self.clearcoat_gloss)
+    }
+
+    fn clearcoat_reflectance(&self, cos_theta: f64) -> f64 {
+        0.25 * self.clearcoat * (CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * schlick_weight(cos_theta))
+    }
+
+    // Entering or leaving matters for the transmissive part, `cos_theta` is measured
+    // against the microfacet normal
+    fn dielectric_reflectance(&self, normal: &Vec3, wo: &Vec3, cos_theta: f64) -> f64 {
+        if normal.dot(wo) > 0.0 {
+            fresnel_dielectric(cos_theta, IOR_AIR, self.ior)
+        } else {
+            fresnel_dielectric(cos_theta, self.ior, IOR_AIR)
+        }
+    }
+
+    // The transmissive share reflects like the glass it is, with total internal
+    // reflection, the rest uses Schlick with the artist's specular and metallic colour
+    fn specular_reflectance(&self, normal: &Vec3, wo: &Vec3, cos_theta: f64) -> Vec3 {
+        let transmission = self.transmission_weight();
+        let schlick = fresnel_schlick(&self.specular_f0(), cos_theta) * (1.0 - transmission);
+
+        if transmission <= 0.0 {
+            return schlick;
+        }
+        schlick + self.dielectric_reflectance(normal, wo, cos_theta) * transmission
+    }
+
+    fn lobe_probabilities(&self, normal: &Vec3, wo: &Vec3) -> LobeProbabilities {
+        let cos_o = normal.dot(wo).abs();
+        let brightness = (self.base_color.x + self.base_color.y + self.base_color.z) / 3.0;
+
+        let diffuse = self.diffuse_weight() * brightness.max(self.sheen);
+        let specular = self.specular_reflectance(normal, wo, cos_o).max_component();
+        let clearcoat = self.clearcoat_reflectance(cos_o);
+        let transmission = self.transmission(normal, wo).map_or(0.0, |(_, weight)| weight.max_component());
+
+        let total = diffuse + specular + clearcoat + transmission;
+        if total <= 0.0 {
+            return LobeProbabilities { diffuse: 1.0, specular: 0.0, clearcoat: 0.0, transmission: 0.0 };
+        }
+
+        LobeProbabilities {
+            diffuse: diffuse / total,
+            specular: specular / total,
+            clearcoat: clearcoat / total,
+            transmission: transmission / total,
+        }
+    }
+
+    // Diffuse, subsurface and sheen. Facing normal expected, both directions above it.
+    fn diffuse(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let cos_o = normal.dot(wo);
+        let cos_i = normal.dot(wi);
+        let half = (wo + wi).unit();
+        let cos_d = wi.dot(&half);
+
+        let fresnel_o = schlick_weight(cos_o);
+        let fresnel_i = schlick_weight(cos_i);
+
+        // Burley: grazing retro-reflection grows with roughness
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let burley = (1.0 + (fd90 - 1.0) * fresnel_i) * (1.0 + (fd90 - 1.0) * fresnel_o);
+
+        // Hanrahan-Krueger: flatter, with the glow at the silhouette of thin scattering layers
+        let fss90 = self.roughness * cos_d * cos_d;
+        let fss = (1.0 + (fss90 - 1.0) * fresnel_i) * (1.0 + (fss90 - 1.0) * fresnel_o);
+        let subsurface = 1.25 * (fss * (1.0 / (cos_i + cos_o) - 0.5) + 0.5);
+
+        let diffuse = &self.base_color * ((burley + (subsurface - burley) * self.subsurface) / PI);
+        let sheen_color = Vec3::new(1.0, 1.0, 1.0).mix(&self.tint(), self.sheen_tint);
+        let sheen = sheen_color * (self.sheen * schlick_weight(cos_d));
+
+        (diffuse + sheen) * self.diffuse_weight()
+    }
+
+    // (refracted direction, its weight), None when nothing gets through. Tinted by
+    // the base colour once per crossing of the surface.
+    fn transmission(&self, normal: &Vec3, wo: &Vec3) -> Option<(Vec3, Vec3)> {
+        let weight = self.transmission_weight();
+        if weight <= 0.0 {
+            return None;
+        }
+
+        let eta = if normal.dot(wo) > 0.0 { IOR_AIR / self.ior } else { self.ior / IOR_AIR };
+        let facing = facing_normal(normal, wo);
+
+        let refracted = (wo * -1.0).refract(&facing, eta)?;
+        let transmittance = 1.0 - self.dielectric_reflectance(normal, wo, facing.dot(wo));
+
+        Some((refracted, &self.base_color * (weight * transmittance)))
+    }
+}
+
+fn mirror_sample(wo: &Vec3, normal: &Vec3, reflectance: Vec3, probability: f64) -> BsdfSample {
+    BsdfSample {
+        direction: reflect(wo, normal),
+        weight: reflectance / probability,
+        pdf: probability,
+        specular: true,
+    }
+}
+
+// (1 - cos)^5, the shape of Schlick's Fresnel approximation
+fn schlick_weight(cos_theta: f64) -> f64 {
+    let m = (1.0 - cos_theta).clamp(0.0, 1.0);
+    let m_sq = m * m;
+    m_sq * m_sq * m
+}
+
+impl Material for Principled {
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        let facing = facing_normal(normal, wo);
+        if facing.dot(wi) <= 0.0 {
+            return black();
+        }
+
+        let mut value = self.diffuse(&facing, wo, wi);
+        let half = (wo + wi).unit();
+        let cos_h = wo.dot(&half);
+
+        if !is_smooth(self.roughness) {
+            let alpha = roughness_to_alpha(self.roughness);
+            value = value + self.specular_reflectance(normal, wo, cos_h) * ggx_reflection(&facing, wo, wi, alpha);
+        }
+
+        if self.clearcoat > 0.0 && !is_smooth(self.clearcoat_roughness()) {
+            let alpha = roughness_to_alpha(self.clearcoat_roughness());
+            let coat = self.clearcoat_reflectance(cos_h) * ggx_reflection(&facing, wo, wi, alpha);
+            value = value + Vec3::new(coat, coat, coat);
+        }
+
+        value
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let facing = facing_normal(normal, wo);
+        let cos_o = facing.dot(wo);
+        let probabilities = self.lobe_probabilities(normal, wo);
+        let frame = Frame::from_normal(&facing);
+
+        let choice = sampler.next_f64();
+        let (u1, u2) = (sampler.next_f64(), sampler.next_f64());
+        let specular_end = probabilities.diffuse + probabilities.specular;
+        let clearcoat_end = specular_end + probabilities.clearcoat;
+
+        let direction = if choice < probabilities.diffuse {
+            frame.to_world(&cosine_hemisphere(u1, u2))
+        } else if choice < specular_end {
+            if is_smooth(self.roughness) {
+                let reflectance = self.specular_reflectance(normal, wo, cos_o);
+                return Some(mirror_sample(wo, &facing, reflectance, probabilities.specular));
+            }
+            let half = frame.to_world(&sample_ggx_half_vector(roughness_to_alpha(self.roughness), u1, u2));
+            reflect(wo, &half)
+        } else if choice < clearcoat_end {
+            if is_smooth(self.clearcoat_roughness()) {
+                let reflectance = self.clearcoat_reflectance(cos_o);
+                let reflectance = Vec3::new(reflectance, reflectance, reflectance);
+                return Some(mirror_sample(wo, &facing, reflectance, probabilities.clearcoat));
+            }
+            let half = frame.to_world(&sample_ggx_half_vector(roughness_to_alpha(self.clearcoat_roughness()), u1, u2));
+            reflect(wo, &half)
+        } else {
+            let (direction, weight) = self.transmission(normal, wo)?;
+            return Some(BsdfSample {
+                direction,
+                weight: weight / probabilities.transmission,
+                pdf: probabilities.transmission,
+                specular: true,
+            });
+        };
+
+        // Any of the rough lobes could have picked this direction
+        let pdf = self.pdf(normal, wo, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            weight: self.evaluate(normal, wo, &direction) * (facing.dot(&direction) / pdf),
+            direction,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        let facing = facing_normal(normal, wo);
+        let cos_i = facing.dot(wi);
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let probabilities = self.lobe_probabilities(normal, wo);
+        let mut pdf = probabilities.diffuse * cosine_hemisphere_pdf(cos_i);
+
+        if !is_smooth(self.roughness) {
+            pdf += probabilities.specular * ggx_reflection_pdf(&facing, wo, wi, roughness_to_alpha(self.roughness));
+        }
+        if !is_smooth(self.clearcoat_roughness()) {
+            pdf += probabilities.clearcoat * ggx_reflection_pdf(&facing, wo, wi, roughness_to_alpha(self.clearcoat_roughness()));
+        }
+
+        pdf
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        let facing = facing_normal(normal, wo);
+        let cos_o = facing.dot(wo);
+        let mut reflectance = black();
+
+        if is_smooth(self.roughness) {
+            reflectance = reflectance + self.specular_reflectance(normal, wo, cos_o);
+        }
+        if self.clearcoat > 0.0 && is_smooth(self.clearcoat_roughness()) {
+            reflectance = reflectance + self.clearcoat_reflectance(cos_o);
+        }
+
+        let mut lobes = Vec::new();
+        if reflectance.max_component() > 0.0 {
+            lobes.push(BsdfSample {
+                direction: reflect(wo, &facing),
+                pdf: 1.0,
+                weight: reflectance,
+                specular: true,
+            });
+        }
+
+        if let Some((direction, weight)) = self.transmission(normal, wo) {
+            lobes.push(BsdfSample {
+                direction,
+                weight,
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
+        lobes
+    }
+
+    fn diffuse_albedo(&self) -> Vec3 {
+        &self.base_color * self.diffuse_weight()
+    }
+}
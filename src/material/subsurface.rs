@@ -0,0 +1,82 @@
+use material::{BsdfSample, Material, Plastic, IOR_AIR};
+use medium::Medium;
+use optics::fresnel_dielectric;
+use sampler::Sampler;
+use vec3::Vec3;
+
+const SUBSURFACE_IOR: f64 = 1.4;
+
+// Translucent material like skin, wax, marble or milk: light goes in through a smooth
+// coat, bounces around inside and comes out somewhere else, softening shadows and
+// glowing through thin parts. `albedo` is about the colour thick parts end up with,
+// `mean_free_path` how far light gets between bounces inside, per channel, in scene
+// units. Objects need to be closed.
+//
+// The path tracer follows the light inside (a random walk). Other tracers see a
+// smooth plastic with the same colour.
+#[derive(Debug, Clone)]
+pub struct Subsurface {
+    pub albedo: Vec3,
+    pub mean_free_path: Vec3,
+    pub ior: f64,
+    // Henyey-Greenstein g of the scattering inside
+    pub anisotropy: f64,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Vec3, mean_free_path: Vec3) -> Subsurface {
+        Subsurface { albedo, mean_free_path, ior: SUBSURFACE_IOR, anisotropy: 0.0 }
+    }
+
+    pub fn coat_reflectance(&self, cos_theta: f64) -> f64 {
+        fresnel_dielectric(cos_theta, IOR_AIR, self.ior)
+    }
+
+    // What's inside. Its single scattering albedo is the one that gives about `albedo`
+    // after all the bounces (Chiang et al., "Practical and controllable subsurface
+    // scattering for production path tracing").
+    pub fn medium(&self) -> Medium {
+        let single_scattering = |albedo: f64| {
+            let root = 4.09712 + 4.20863 * albedo - (9.59217 + 41.6808 * albedo + 17.7126 * albedo * albedo).sqrt();
+            1.0 - root * root
+        };
+        let extinction = Vec3::new(1.0 / self.mean_free_path.x, 1.0 / self.mean_free_path.y, 1.0 / self.mean_free_path.z);
+        let albedo = Vec3::new(single_scattering(self.albedo.x), single_scattering(self.albedo.y), single_scattering(self.albedo.z));
+
+        Medium {
+            absorption: &extinction * &(Vec3::new(1.0, 1.0, 1.0) - &albedo),
+            scattering: &extinction * &albedo,
+            anisotropy: self.anisotropy,
+        }
+    }
+
+    fn surface(&self) -> Plastic {
+        Plastic { diffuse: self.albedo.clone(), roughness: 0.0, ior: self.ior }
+    }
+}
+
+impl Material for Subsurface {
+    fn evaluate(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        self.surface().evaluate(normal, wo, wi)
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, sampler: &mut Sampler) -> Option<BsdfSample> {
+        self.surface().sample(normal, wo, sampler)
+    }
+
+    fn pdf(&self, normal: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        self.surface().pdf(normal, wo, wi)
+    }
+
+    fn specular_lobes(&self, normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        self.surface().specular_lobes(normal, wo)
+    }
+
+    fn diffuse_albedo(&self) -> Vec3 {
+        self.surface().diffuse_albedo()
+    }
+
+    fn subsurface(&self) -> Option<&Subsurface> {
+        Some(self)
+    }
+}
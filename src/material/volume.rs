@@ -0,0 +1,38 @@
+use material::{black, BsdfSample, Material};
+use medium::Medium;
+use sampler::Sampler;
+use vec3::Vec3;
+
+// Surface that isn't there, only marking where `medium` starts and ends. Light goes
+// through unchanged, what happens inside is up to the tracer.
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub medium: Medium,
+}
+
+impl Material for Volume {
+    fn evaluate(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> Vec3 {
+        black()
+    }
+
+    fn sample(&self, normal: &Vec3, wo: &Vec3, _sampler: &mut Sampler) -> Option<BsdfSample> {
+        self.specular_lobes(normal, wo).pop()
+    }
+
+    fn pdf(&self, _normal: &Vec3, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn specular_lobes(&self, _normal: &Vec3, wo: &Vec3) -> Vec<BsdfSample> {
+        vec![BsdfSample {
+            direction: wo * -1.0,
+            weight: Vec3::new(1.0, 1.0, 1.0),
+            pdf: 1.0,
+            specular: true,
+        }]
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        Some(&self.medium)
+    }
+}
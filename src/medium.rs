@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use lighting::{emitter_pdf, sample_emitters, shadow_transmittance, SHADOW_EPSILON};
+use ray::{Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use vec3::Vec3;
+use warp::Frame;
+
+// Homogeneous participating medium, like fog, smoke or murky water, filling the whole
+// scene or the inside of an object. Coefficients are per unit of distance: light gets
+// absorbed at `absorption` and bounced into another direction at `scattering`, mostly
+// onwards as `anisotropy` goes towards 1 and back the way it came towards -1
+// (Henyey-Greenstein's g).
+#[derive(Debug, Clone)]
+pub struct Medium {
+    pub absorption: Vec3,
+    pub scattering: Vec3,
+    pub anisotropy: f64,
+}
+
+impl Medium {
+    pub fn extinction(&self) -> Vec3 {
+        &self.absorption + &self.scattering
+    }
+
+    // Light left after `distance`, which may be INFINITY
+    pub fn transmittance(&self, distance: f64) -> Vec3 {
+        let extinction = self.extinction();
+        let channel = |extinction: f64| if extinction > 0.0 { (-extinction * distance).exp() } else { 1.0 };
+        Vec3::new(channel(extinction.x), channel(extinction.y), channel(extinction.z))
+    }
+
+    // Where light arriving along a ray `distance` long was last scattered, if it was
+    // before the ray's end. Picked by the extinction of a random channel, so that
+    // coloured media don't get noisy. Either way, with what the ray's throughput gets
+    // multiplied by.
+    pub fn sample_distance(&self, distance: f64, sampler: &mut Sampler) -> (Option<f64>, Vec3) {
+        let extinction = self.extinction();
+        let channel = [extinction.x, extinction.y, extinction.z][((sampler.next_f64() * 3.0) as usize).min(2)];
+        let scattered_at = if channel > 0.0 { -(1.0 - sampler.next_f64()).ln() / channel } else { INFINITY };
+
+        if scattered_at < distance {
+            let transmittance = self.transmittance(scattered_at);
+            let pdf = average(&(&extinction * &transmittance));
+            return (Some(scattered_at), &self.scattering * &transmittance * (1.0 / pdf));
+        }
+
+        let transmittance = self.transmittance(distance);
+        let pdf = average(&transmittance);
+        if pdf <= 0.0 {
+            return (None, Vec3::new(0.0, 0.0, 0.0));
+        }
+        (None, transmittance * (1.0 / pdf))
+    }
+
+    // Per solid angle, of light going on towards `wo` after arriving from `wi`
+    pub fn phase(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        henyey_greenstein(-wo.dot(wi), self.anisotropy)
+    }
+
+    // wi, in proportion to the phase function, so the sample's weight is 1
+    pub fn sample_phase(&self, wo: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        let g = self.anisotropy;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u1
+        } else {
+            let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+            ((1.0 + g * g - ratio * ratio) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        // Around the way the light carries on, opposite to wo
+        let frame = Frame::from_normal(&(wo * -1.0));
+        frame.to_world(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+    }
+}
+
+// Normalised over the sphere, `cos_theta` between the directions light travels in
+// before and after scattering
+pub fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+}
+
+fn average(color: &Vec3) -> f64 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+// Light from the scene's lights, emissive objects and environment scattered towards
+// `wo` at a point inside `medium`, for the path tracer's scattering events
+pub fn medium_lighting(point: &Vec3, wo: &Vec3, medium: &Medium, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    for light in &scene.lights {
+        let samples = light.samples();
+        let mut light_color = Vec3::new(0.0, 0.0, 0.0);
+
+        for _ in 0..samples {
+            let sample = match light.sample(point, sampler.next_f64(), sampler.next_f64()) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let ray = Ray { origin: point.clone(), direction: sample.direction.clone() };
+            let transmittance = shadow_transmittance(&ray, sample.distance, scene, sampler);
+            light_color = light_color + sample.irradiance * &transmittance * medium.phase(wo, &sample.direction);
+        }
+
+        color = color + light_color * (1.0 / samples as f64);
+    }
+
+    for (emitter, sample) in sample_emitters(scene, sampler) {
+        let to_light = &sample.point - point;
+        let distance_sq = to_light.dot(&to_light);
+        let distance = distance_sq.sqrt();
+        let direction = &to_light * (1.0 / distance);
+        let cos_light = sample.normal.dot(&direction).abs();
+        let emitted = emitter.emission(&sample.normal, &(&direction * -1.0));
+        if distance <= SHADOW_EPSILON || cos_light <= 0.0 || emitted.max_component() <= 0.0 {
+            continue;
+        }
+
+        let ray = Ray { origin: point.clone(), direction: direction.clone() };
+        let transmittance = shadow_transmittance(&ray, distance - SHADOW_EPSILON, scene, sampler);
+        let pdf = emitter_pdf(sample.pdf, distance_sq, cos_light);
+        color = color + emitted * &transmittance * (medium.phase(wo, &direction) / pdf);
+    }
+
+    let samples = scene.background.samples();
+    for _ in 0..samples {
+        let sample = match scene.background.sample(sampler.next_f64(), sampler.next_f64()) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let ray = Ray { origin: point.clone(), direction: sample.direction.clone() };
+        let transmittance = shadow_transmittance(&ray, INFINITY, scene, sampler);
+        let weight = medium.phase(wo, &sample.direction) / (sample.pdf * samples as f64);
+        color = color + sample.radiance * &transmittance * weight;
+    }
+
+    color
+}
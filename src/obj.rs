@@ -0,0 +1,105 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use vec3::Vec3;
+
+// Geometry of a Wavefront OBJ file. Only vertex positions and faces are read,
+// polygons are split into triangle fans.
+#[derive(Debug)]
+pub struct ObjMesh {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+    // Nothing to render, or to pick points on when it glows
+    NoFaces,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io(ref err) => write!(f, "{}", err),
+            ObjError::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+            ObjError::NoFaces => write!(f, "no faces"),
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> ObjError {
+        ObjError::Io(err)
+    }
+}
+
+pub fn load_obj(path: &Path) -> Result<ObjMesh, ObjError> {
+    parse_obj(&fs::read_to_string(path)?)
+}
+
+pub fn parse_obj(contents: &str) -> Result<ObjMesh, ObjError> {
+    let mut mesh = ObjMesh {
+        vertices: Vec::new(),
+        triangles: Vec::new(),
+    };
+
+    for (index, line) in contents.lines().enumerate() {
+        let syntax_error = |message: String| ObjError::Syntax { line: index + 1, message };
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let mut coordinates = [0.0; 3];
+                for coordinate in coordinates.iter_mut() {
+                    let token = tokens.next().ok_or_else(|| syntax_error(String::from("vertex needs 3 coordinates")))?;
+                    *coordinate = match token.parse::<f64>() {
+                        Ok(value) if value.is_finite() => value,
+                        _ => return Err(syntax_error(format!("invalid coordinate {}", token))),
+                    };
+                }
+                mesh.vertices.push(Vec3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            Some("f") => {
+                let mut corners = Vec::new();
+                for token in tokens {
+                    let corner = vertex_index(token, mesh.vertices.len()).ok_or_else(|| syntax_error(format!("invalid vertex index {}", token)))?;
+                    corners.push(corner);
+                }
+                if corners.len() < 3 {
+                    return Err(syntax_error(String::from("face needs at least 3 vertices")));
+                }
+                for i in 1..corners.len() - 1 {
+                    mesh.triangles.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            // Normals, texture coordinates, groups, materials...
+            _ => {}
+        }
+    }
+
+    if mesh.triangles.is_empty() {
+        return Err(ObjError::NoFaces);
+    }
+    Ok(mesh)
+}
+
+// "7", "7/2" or "7/2/5", 1-based or negative to count back from the last vertex
+fn vertex_index(token: &str, vertex_count: usize) -> Option<usize> {
+    let index: i64 = token.split('/').next()?.parse().ok()?;
+
+    let index = if index < 0 {
+        vertex_count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if index < 0 || index >= vertex_count as i64 {
+        return None;
+    }
+    Some(index as usize)
+}
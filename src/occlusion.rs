@@ -0,0 +1,46 @@
+use lighting::offset_ray_origin;
+use ray::{trace, Ray};
+use sampler::Sampler;
+use scene::Scene;
+use vec3::Vec3;
+use warp::{cosine_hemisphere, Frame};
+
+// How open the surroundings of a point are: the share of `samples` rays, cosine
+// weighted over the hemisphere, that get `max_distance` away without hitting anything.
+// Rendered on its own by the `ao` integrator, or darkening the Whitted tracer's
+// ambient term in creases and where objects touch.
+#[derive(Debug, Clone)]
+pub struct AmbientOcclusion {
+    pub samples: u32,
+    pub max_distance: f64,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> AmbientOcclusion {
+        AmbientOcclusion {
+            samples: 16,
+            max_distance: 1.0,
+        }
+    }
+}
+
+impl AmbientOcclusion {
+    // 1 out in the open, 0 fully enclosed. Over the hemisphere `normal` points to.
+    pub fn visibility(&self, point: &Vec3, normal: &Vec3, scene: &Scene, sampler: &mut Sampler) -> f64 {
+        let samples = self.samples.max(1);
+        let frame = Frame::from_normal(normal);
+
+        let open = (0..samples)
+            .filter(|_| {
+                let direction = frame.to_world(&cosine_hemisphere(sampler.next_f64(), sampler.next_f64()));
+                let ray = Ray {
+                    origin: offset_ray_origin(point, normal, &direction),
+                    direction,
+                };
+                trace(&ray, scene).t >= self.max_distance
+            })
+            .count();
+
+        open as f64 / samples as f64
+    }
+}
@@ -0,0 +1,35 @@
+use vec3::Vec3;
+
+// Fraction of light reflected at a smooth boundary between two dielectrics,
+// averaged over both polarizations. `cos_i` is the cosine between the incident
+// direction and the normal on the incident side. Returns 1.0 past the critical angle.
+pub fn fresnel_dielectric(cos_i: f64, eta_i: f64, eta_t: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).sqrt();
+
+    if sin_t >= 1.0 {
+        // Total internal reflection
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin_t * sin_t).sqrt();
+    let r_parallel = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    let r_perpendicular = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) * 0.5
+}
+
+// Fraction of light per channel left after travelling `distance` through an absorbing medium
+pub fn beer_lambert(absorption: &Vec3, distance: f64) -> Vec3 {
+    Vec3::new(
+        (-absorption.x * distance).exp(),
+        (-absorption.y * distance).exp(),
+        (-absorption.z * distance).exp(),
+    )
+}
+
+// Schlick's approximation for conductors and tinted specular, `f0` is the reflectance at normal incidence
+pub fn fresnel_schlick(f0: &Vec3, cos_theta: f64) -> Vec3 {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    f0.mix(&Vec3::new(1.0, 1.0, 1.0), weight)
+}
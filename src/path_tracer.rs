@@ -0,0 +1,212 @@
+use image::Color;
+use lighting::{direct_lighting, emitter_lighting, emitter_pdf, environment_lighting, offset_ray_origin, segment_medium, Mis};
+use grid_volume::sample_collision;
+use material::{facing_normal, reflect, Material};
+use medium::medium_lighting;
+use optics::beer_lambert;
+use random_walk::{random_walk, EXIT};
+use ray::{trace, Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use tracer::display_color;
+use vec3::Vec3;
+
+// Hard stop for paths stuck bouncing between mirrors
+const MAX_BOUNCES: u32 = 32;
+// Bounces every path gets before Russian roulette may end it
+const MIN_BOUNCES: u32 = 3;
+// Even bright paths may end at each roulette, so none goes on forever
+const MAX_SURVIVAL: f64 = 0.95;
+
+// Monte Carlo path tracing: one direction sampled from the BSDF per bounce, so light
+// bouncing off diffuse surfaces reaches the image too. Lights are sampled at every
+// hit (next event estimation). Emitters and the environment found by a BSDF sample
+// count too, weighted against those light samples with `mis`, or not at all without it
+// unless the bounce was specular, where sampling lights can't work. In media and grid
+// volumes paths may scatter before reaching the next surface, lights are sampled
+// there too. Paths into subsurface materials walk through the object and carry on
+// from where they come out.
+pub fn trace_path(initial_ray: Ray, scene: &Scene, sampler: &mut Sampler) -> Color {
+    display_color(path_radiance(initial_ray, scene, Mis::PowerHeuristic, sampler))
+}
+
+pub fn path_radiance(initial_ray: Ray, scene: &Scene, mis: Mis, sampler: &mut Sampler) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut ray = initial_ray;
+    // The camera sees lights directly, like a specular bounce would
+    let mut specular_bounce = true;
+    // Density the BSDF picked the current ray's direction with
+    let mut bsdf_pdf = 0.0;
+    // How lights found by the current ray are weighed, those sampled from a point in
+    // a medium aren't weighed against the phase function
+    let mut light_mis = mis;
+    // From the last vertex to the current ray's origin, across volume boundaries
+    let mut travelled = 0.0;
+
+    for bounces in 0..MAX_BOUNCES {
+        let intersection = trace(&ray, scene);
+
+        // === Scattering in a medium on the way
+        // The first of a collision in a grid volume and one in the medium around
+        let direction = ray.direction.unit();
+        let distance = if intersection.t >= INFINITY { INFINITY } else { intersection.t * ray.direction.mag() };
+        let unit_ray = Ray { origin: ray.origin.clone(), direction: direction.clone() };
+        let mut scattering = sample_collision(&unit_ray, distance, scene, sampler).map(|(scattered_at, volume)| {
+            let point = &unit_ray.origin + &(&direction * scattered_at);
+            (scattered_at, volume.medium(&point), volume.albedo.clone())
+        });
+        if let Some(medium) = segment_medium(&ray, &intersection, scene) {
+            let reach = scattering.as_ref().map_or(distance, |&(scattered_at, _, _)| scattered_at);
+            let (scattered_at, weight) = medium.sample_distance(reach, sampler);
+            if let Some(scattered_at) = scattered_at {
+                scattering = Some((scattered_at, medium.clone(), weight));
+            } else {
+                throughput = throughput * &weight;
+            }
+        }
+
+        if let Some((scattered_at, medium, weight)) = scattering {
+            throughput = throughput * &weight;
+            let point = &ray.origin + &(&direction * scattered_at);
+            let wo = &direction * -1.0;
+            color = color + &throughput * &medium_lighting(&point, &wo, &medium, scene, sampler);
+
+            match russian_roulette(&throughput, bounces, sampler) {
+                Some(survival) => throughput = throughput * (1.0 / survival),
+                None => break,
+            }
+
+            let direction = medium.sample_phase(&wo, sampler.next_f64(), sampler.next_f64());
+            specular_bounce = false;
+            light_mis = Mis::Off;
+            travelled = 0.0;
+            ray = Ray { origin: point, direction };
+            continue;
+        }
+
+        if intersection.t >= INFINITY {
+            let background = &scene.background;
+            let samples = background.samples();
+            let weight = if specular_bounce || samples == 0 {
+                1.0
+            } else {
+                bsdf_weight(light_mis, bsdf_pdf, samples as f64 * background.pdf(&ray.direction))
+            };
+            color = color + &throughput * &background.radiance(&ray.direction) * weight;
+            break;
+        }
+
+        let ray_hit = &ray.origin + &(&ray.direction * intersection.t);
+        let material = intersection.material;
+        let normal = &intersection.normal;
+        let wo = (&ray.direction * -1.0).unit();
+
+        // Hit from the inside, the light travelled through the object to get here
+        if ray.direction.dot(normal) > 0.0 {
+            let distance = intersection.t * ray.direction.mag();
+            throughput = throughput * beer_lambert(&material.absorption(), distance);
+        }
+
+        // Volume boundaries only change the medium, the path goes on as if they weren't there
+        if material.medium().is_some() {
+            travelled += intersection.t * ray.direction.mag();
+            ray = Ray {
+                origin: offset_ray_origin(&ray_hit, normal, &ray.direction),
+                direction: ray.direction,
+            };
+            continue;
+        }
+
+        let emitted = material.emission(normal, &wo);
+        if emitted.max_component() > 0.0 {
+            let weight = if specular_bounce || !material.is_emissive() {
+                1.0
+            } else {
+                let distance = travelled + intersection.t * ray.direction.mag();
+                let cos_light = normal.dot(&wo).abs();
+                bsdf_weight(light_mis, bsdf_pdf, emitter_pdf(1.0 / intersection.area, distance * distance, cos_light))
+            };
+            color = color + &throughput * &emitted * weight;
+        }
+
+        // === Subsurface scattering
+        // Light that gets through the coat comes out somewhere else, which gets lit
+        // and bounced off instead
+        let (ray_hit, normal, wo, material) = match material.subsurface() {
+            None => (ray_hit, normal.clone(), wo, material),
+            Some(subsurface) => {
+                let facing = facing_normal(normal, &wo);
+                if sampler.next_f64() < subsurface.coat_reflectance(facing.dot(&wo)) {
+                    let direction = reflect(&wo, &facing);
+                    specular_bounce = true;
+                    light_mis = mis;
+                    travelled = 0.0;
+                    ray = Ray { origin: offset_ray_origin(&ray_hit, &facing, &direction), direction };
+                    continue;
+                }
+
+                match random_walk(&ray_hit, &facing, subsurface, scene, sampler) {
+                    Some(exit) => {
+                        throughput = throughput * &exit.weight;
+                        (exit.point, exit.normal.clone(), exit.normal, &EXIT as &dyn Material)
+                    }
+                    None => break,
+                }
+            }
+        };
+
+        // === Next event estimation
+        let mut direct = direct_lighting(&wo, &ray_hit, &normal, material, scene, sampler);
+        direct = direct + emitter_lighting(&wo, &ray_hit, &normal, material, scene, sampler, mis);
+        direct = direct + environment_lighting(&wo, &ray_hit, &normal, material, scene, sampler, mis);
+        color = color + &throughput * &direct;
+
+        // === Next bounce
+        let sample = match material.sample(&normal, &wo, sampler) {
+            Some(sample) => sample,
+            None => break,
+        };
+        throughput = throughput * &sample.weight;
+        specular_bounce = sample.specular;
+        bsdf_pdf = sample.pdf;
+        light_mis = mis;
+        travelled = 0.0;
+
+        // === Russian roulette
+        match russian_roulette(&throughput, bounces, sampler) {
+            Some(survival) => throughput = throughput * (1.0 / survival),
+            None => break,
+        }
+
+        ray = Ray {
+            origin: offset_ray_origin(&ray_hit, &normal, &sample.direction),
+            direction: sample.direction,
+        };
+    }
+
+    color
+}
+
+// Past the first few bounces paths end at random, the more likely the less light they
+// still carry. Survivors get divided by the returned chance of surviving, to stand in
+// for the ended ones.
+pub fn russian_roulette(throughput: &Vec3, bounces: u32, sampler: &mut Sampler) -> Option<f64> {
+    if bounces + 1 < MIN_BOUNCES {
+        return Some(1.0);
+    }
+
+    let survival = throughput.max_component().min(MAX_SURVIVAL);
+    if survival <= 0.0 || sampler.next_f64() >= survival {
+        return None;
+    }
+    Some(survival)
+}
+
+// Light found by a BSDF sample, when light sampling could have found it too
+fn bsdf_weight(mis: Mis, bsdf_pdf: f64, light_pdf: f64) -> f64 {
+    match mis {
+        Mis::Off => 0.0,
+        Mis::PowerHeuristic => mis.weight(bsdf_pdf, light_pdf),
+    }
+}
@@ -0,0 +1,341 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+use emitter::emitters;
+use lighting::{direct_lighting, emitter_lighting, environment_lighting, offset_ray_origin, Mis};
+use material::Material;
+use optics::beer_lambert;
+use path_tracer::russian_roulette;
+use ray::{trace, Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use vec3::Vec3;
+
+// Hard stop for photons and camera rays stuck between mirrors
+const MAX_BOUNCES: u32 = 32;
+// Photons only count on surfaces facing about the same way as the one being lit, so
+// they don't leak around corners
+const MIN_NORMAL_AGREEMENT: f64 = 0.9;
+// How fast the gather radius shrinks from pass to pass, between 0 and 1. Lower is
+// faster but noisier (Knaus and Zwicker's alpha).
+const RADIUS_ALPHA: f64 = 2.0 / 3.0;
+
+// Light arriving at a surface, `direction` is the way it travelled
+#[derive(Debug, Clone)]
+pub struct Photon {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub direction: Vec3,
+    pub power: Vec3,
+}
+
+// Photons in a kd-tree: every subslice has its median in the middle, split along
+// the axis stored next to it, with the photons before it on one side and after it on
+// the other
+#[derive(Debug)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<u8>,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> PhotonMap {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        PhotonMap { photons, axes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    pub fn for_each_within<F: FnMut(&Photon)>(&self, point: &Vec3, radius: f64, mut f: F) {
+        within(&self.photons, &self.axes, point, radius * radius, &mut f);
+    }
+
+    // Light from the photons around `point` reflected towards `wo`: what the BSDF
+    // makes of their power, spread over the disk they were gathered from
+    pub fn radiance(&self, point: &Vec3, normal: &Vec3, wo: &Vec3, material: &dyn Material, radius: f64) -> Vec3 {
+        let mut reflected = Vec3::new(0.0, 0.0, 0.0);
+        self.for_each_within(point, radius, |photon| {
+            if photon.normal.dot(normal) >= MIN_NORMAL_AGREEMENT {
+                let wi = &photon.direction * -1.0;
+                reflected = &reflected + &(material.evaluate(normal, wo, &wi) * &photon.power);
+            }
+        });
+        reflected * (1.0 / (PI * radius * radius))
+    }
+}
+
+fn build(photons: &mut [Photon], axes: &mut [u8]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    let axis = widest_axis(photons);
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| {
+        coordinate(&a.position, axis).partial_cmp(&coordinate(&b.position, axis)).unwrap_or(Ordering::Equal)
+    });
+    axes[middle] = axis;
+
+    let (photons_before, photons_after) = photons.split_at_mut(middle);
+    let (axes_before, axes_after) = axes.split_at_mut(middle);
+    build(photons_before, axes_before);
+    build(&mut photons_after[1..], &mut axes_after[1..]);
+}
+
+fn widest_axis(photons: &[Photon]) -> u8 {
+    let mut min = photons[0].position.clone();
+    let mut max = photons[0].position.clone();
+    for photon in photons {
+        let position = &photon.position;
+        min = Vec3::new(min.x.min(position.x), min.y.min(position.y), min.z.min(position.z));
+        max = Vec3::new(max.x.max(position.x), max.y.max(position.y), max.z.max(position.z));
+    }
+
+    let extent = &max - &min;
+    if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    }
+}
+
+fn coordinate(point: &Vec3, axis: u8) -> f64 {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z,
+    }
+}
+
+fn within<F: FnMut(&Photon)>(photons: &[Photon], axes: &[u8], point: &Vec3, radius_sq: f64, f: &mut F) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let middle = photons.len() / 2;
+    let photon = &photons[middle];
+    let offset = &photon.position - point;
+    if offset.dot(&offset) <= radius_sq {
+        f(photon);
+    }
+
+    // Only the side `point` is on, unless the sphere reaches across the split
+    let split = coordinate(point, axes[middle]) - coordinate(&photon.position, axes[middle]);
+    if split <= 0.0 || split * split <= radius_sq {
+        within(&photons[..middle], &axes[..middle], point, radius_sq, f);
+    }
+    if split >= 0.0 || split * split <= radius_sq {
+        within(&photons[middle + 1..], &axes[middle + 1..], point, radius_sq, f);
+    }
+}
+
+// Photons stored where they landed on their way from the lights: the global map keeps
+// every landing, the caustic map only those that came straight through glass or off
+// mirrors, which a density estimate needs many more of to look sharp
+#[derive(Debug)]
+pub struct PhotonMaps {
+    pub global: PhotonMap,
+    pub caustics: PhotonMap,
+    pub radius: f64,
+    pub caustic_radius: f64,
+}
+
+impl PhotonMaps {
+    pub fn empty() -> PhotonMaps {
+        PhotonMaps {
+            global: PhotonMap::new(Vec::new()),
+            caustics: PhotonMap::new(Vec::new()),
+            radius: 0.0,
+            caustic_radius: 0.0,
+        }
+    }
+}
+
+// `count` photons shared by all the scene's emitters, each carrying its share of the
+// emitted power. Caustic photons stop at the first surface they don't bounce off
+// specularly, after being stored there if it wasn't the first one.
+pub fn shoot_photons(scene: &Scene, count: u32, caustics: bool, sampler: &mut Sampler) -> Vec<Photon> {
+    let emitters = emitters(scene);
+    let mut photons = Vec::new();
+    if emitters.is_empty() {
+        return photons;
+    }
+
+    for _ in 0..count {
+        let index = ((sampler.next_f64() * emitters.len() as f64) as usize).min(emitters.len() - 1);
+        let emitter = &emitters[index];
+        let (point, normal, point_pdf) = emitter.sample_point(sampler.next_f64(), sampler.next_f64());
+        let (direction, direction_pdf) = emitter.sample_direction(normal.as_ref(), sampler.next_f64(), sampler.next_f64());
+        let emitted = emitter.emitted(normal.as_ref(), &direction);
+        if direction_pdf <= 0.0 || emitted.max_component() <= 0.0 {
+            continue;
+        }
+
+        let cos_light = normal.as_ref().map_or(1.0, |normal| normal.dot(&direction).abs());
+        let power = emitted * (cos_light * emitters.len() as f64 / (point_pdf * direction_pdf * count as f64));
+        // Of the power left, relative to what it started with
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = Ray {
+            origin: match normal {
+                Some(ref normal) => offset_ray_origin(&point, normal, &direction),
+                None => point,
+            },
+            direction,
+        };
+
+        for bounces in 0..MAX_BOUNCES {
+            let intersection = trace(&ray, scene);
+            if intersection.t >= INFINITY {
+                break;
+            }
+
+            let position = &ray.origin + &(&ray.direction * intersection.t);
+            let material = intersection.material;
+            let normal = &intersection.normal;
+            let wo = (&ray.direction * -1.0).unit();
+
+            if ray.direction.dot(normal) > 0.0 {
+                let distance = intersection.t * ray.direction.mag();
+                throughput = throughput * beer_lambert(&material.absorption(), distance);
+            }
+
+            if !caustics || bounces > 0 {
+                photons.push(Photon {
+                    position: position.clone(),
+                    normal: normal.clone(),
+                    direction: ray.direction.unit(),
+                    power: &power * &throughput,
+                });
+            }
+
+            let sample = match material.sample(normal, &wo, sampler) {
+                Some(sample) => sample,
+                None => break,
+            };
+            if caustics && !sample.specular {
+                break;
+            }
+            throughput = throughput * &sample.weight;
+
+            match russian_roulette(&throughput, bounces, sampler) {
+                Some(survival) => throughput = throughput * (1.0 / survival),
+                None => break,
+            }
+
+            ray = Ray {
+                origin: offset_ray_origin(&position, normal, &sample.direction),
+                direction: sample.direction,
+            };
+        }
+    }
+
+    photons
+}
+
+// Gather radius of a pass counting from 0, shrinking so the average over all passes
+// converges (Knaus and Zwicker, "Progressive photon mapping: a probabilistic approach")
+pub fn progressive_radius(initial: f64, pass: u32) -> f64 {
+    let mut radius_sq = initial * initial;
+    for i in 1..=pass {
+        radius_sq *= (i as f64 - 1.0 + RADIUS_ALPHA) / i as f64;
+    }
+    radius_sq.sqrt()
+}
+
+// Camera rays go on through glass and off mirrors. Where they land on anything else,
+// lights are sampled directly, caustics come from the caustic map, and light bounced
+// off other surfaces from the global map, looked up where one BSDF sample lands (final
+// gathering), since the map itself is too blotchy to be seen directly.
+pub fn photon_radiance(initial_ray: Ray, scene: &Scene, maps: &PhotonMaps, sampler: &mut Sampler) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut ray = initial_ray;
+
+    for _ in 0..MAX_BOUNCES {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            color = color + &throughput * &scene.background.radiance(&ray.direction);
+            break;
+        }
+
+        let ray_hit = &ray.origin + &(&ray.direction * intersection.t);
+        let material = intersection.material;
+        let normal = &intersection.normal;
+        let wo = (&ray.direction * -1.0).unit();
+
+        if ray.direction.dot(normal) > 0.0 {
+            let distance = intersection.t * ray.direction.mag();
+            throughput = throughput * beer_lambert(&material.absorption(), distance);
+        }
+
+        let mut local = material.emission(normal, &wo);
+        local = local + direct_lighting(&wo, &ray_hit, normal, material, scene, sampler);
+        local = local + emitter_lighting(&wo, &ray_hit, normal, material, scene, sampler, Mis::Off);
+        local = local + environment_lighting(&wo, &ray_hit, normal, material, scene, sampler, Mis::Off);
+        local = local + maps.caustics.radiance(&ray_hit, normal, &wo, material, maps.caustic_radius);
+        color = color + &throughput * &local;
+
+        let sample = match material.sample(normal, &wo, sampler) {
+            Some(sample) => sample,
+            None => break,
+        };
+        throughput = throughput * &sample.weight;
+        ray = Ray {
+            origin: offset_ray_origin(&ray_hit, normal, &sample.direction),
+            direction: sample.direction,
+        };
+
+        if !sample.specular {
+            color = color + &throughput * &gather(ray, scene, maps, sampler);
+            break;
+        }
+    }
+
+    color
+}
+
+// Global map radiance where `ray` lands, through glass and mirrors
+fn gather(mut ray: Ray, scene: &Scene, maps: &PhotonMaps, sampler: &mut Sampler) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+
+    for _ in 0..MAX_BOUNCES {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            break;
+        }
+
+        let ray_hit = &ray.origin + &(&ray.direction * intersection.t);
+        let material = intersection.material;
+        let normal = &intersection.normal;
+        let wo = (&ray.direction * -1.0).unit();
+
+        if ray.direction.dot(normal) > 0.0 {
+            let distance = intersection.t * ray.direction.mag();
+            throughput = throughput * beer_lambert(&material.absorption(), distance);
+        }
+
+        color = color + &throughput * &maps.global.radiance(&ray_hit, normal, &wo, material, maps.radius);
+
+        let sample = match material.sample(normal, &wo, sampler) {
+            Some(sample) if sample.specular => sample,
+            _ => break,
+        };
+        throughput = throughput * &sample.weight;
+        ray = Ray {
+            origin: offset_ray_origin(&ray_hit, normal, &sample.direction),
+            direction: sample.direction,
+        };
+    }
+
+    color
+}
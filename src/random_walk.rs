@@ -0,0 +1,90 @@
+use lighting::offset_ray_origin;
+use material::{Lambertian, Subsurface};
+use ray::{trace, Ray, INFINITY};
+use sampler::Sampler;
+use scene::Scene;
+use vec3::Vec3;
+use warp::{cosine_hemisphere, Frame};
+
+// Hard stop for walks in media that hardly absorb anything
+const MAX_STEPS: u32 = 1024;
+
+// Where light comes out of a translucent object: it leaves through the coat in no
+// particular direction, like off a white diffuse surface
+pub static EXIT: Lambertian = Lambertian { albedo: Vec3 { x: 1.0, y: 1.0, z: 1.0 } };
+
+#[derive(Debug)]
+pub struct WalkExit {
+    pub point: Vec3,
+    // Pointing out of the object
+    pub normal: Vec3,
+    // What was left of the light on the way
+    pub weight: Vec3,
+}
+
+// Follows light that went through the coat at `point`, scattering through the
+// object's inside until it gets out again. `normal` points out of the object, back
+// to the side the light came from, and the walk starts off the other way. None when
+// it was absorbed, or found no way out of an object that isn't closed.
+pub fn random_walk(point: &Vec3, normal: &Vec3, subsurface: &Subsurface, scene: &Scene, sampler: &mut Sampler) -> Option<WalkExit> {
+    let medium = subsurface.medium();
+    let extinction = medium.extinction();
+    // Distances come from one channel for the whole walk. Weighing the light against
+    // how likely any channel was to take the same walk keeps colours with very
+    // different mean free paths from getting noisy.
+    let channel = [extinction.x, extinction.y, extinction.z][((sampler.next_f64() * 3.0) as usize).min(2)];
+    let inward = normal * -1.0;
+    let direction = Frame::from_normal(&inward).to_world(&cosine_hemisphere(sampler.next_f64(), sampler.next_f64()));
+    let mut ray = Ray { origin: offset_ray_origin(point, normal, &direction), direction };
+    let mut light = Vec3::new(1.0, 1.0, 1.0);
+    // Per channel, of the walk so far, scaled along with `light`
+    let mut pdfs = Vec3::new(1.0, 1.0, 1.0);
+
+    for _ in 0..MAX_STEPS {
+        let intersection = trace(&ray, scene);
+        if intersection.t >= INFINITY {
+            return None;
+        }
+
+        let scattered_at = -(1.0 - sampler.next_f64()).ln() / channel;
+        if scattered_at >= intersection.t {
+            let transmittance = medium.transmittance(intersection.t);
+            let pdf = average(&(&pdfs * &transmittance));
+            if pdf <= 0.0 {
+                return None;
+            }
+            let normal = &intersection.normal;
+            return Some(WalkExit {
+                point: &ray.origin + &(&ray.direction * intersection.t),
+                normal: if normal.dot(&ray.direction) < 0.0 { normal * -1.0 } else { normal.clone() },
+                weight: light * &transmittance * (1.0 / pdf),
+            });
+        }
+
+        let transmittance = medium.transmittance(scattered_at);
+        light = light * &medium.scattering * &transmittance;
+        pdfs = pdfs * &extinction * &transmittance;
+        let pdf = average(&pdfs);
+        light = light * (1.0 / pdf);
+        pdfs = pdfs * (1.0 / pdf);
+
+        // Unlike paths, walks keep going at full strength until the light dims
+        let survival = light.max_component().min(1.0);
+        if survival < 1.0 {
+            if sampler.next_f64() >= survival {
+                return None;
+            }
+            light = light * (1.0 / survival);
+        }
+
+        let origin = &ray.origin + &(&ray.direction * scattered_at);
+        let wo = &ray.direction * -1.0;
+        ray = Ray { origin, direction: medium.sample_phase(&wo, sampler.next_f64(), sampler.next_f64()) };
+    }
+
+    None
+}
+
+fn average(color: &Vec3) -> f64 {
+    (color.x + color.y + color.z) / 3.0
+}
@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+
+use material::{Lambertian, Material};
+use scene::{Mesh, Plane, Quad, Scene, Sphere};
+use vec3::Vec3;
+
+pub const RAY_MIN_LENGTH: f64 = 0.000000001;
+pub const INFINITY: f64 = 100000000000.0;
+
+#[derive(Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+// What misses report, never shaded since t stays at INFINITY
+static NO_MATERIAL: Lambertian = Lambertian { albedo: Vec3 { x: 0.0, y: 0.0, z: 0.0 } };
+
+#[derive(Debug)]
+pub struct RayIntersection<'a> {
+    pub t: f64,
+    pub normal: Vec3,
+    pub material: &'a dyn Material,
+    // Of the whole object hit, emitters found by chance need it to weigh themselves
+    // against being sampled directly
+    pub area: f64,
+}
+
+pub enum RayError {
+    NoIntersections
+}
+
+pub fn trace<'a>(ray: &Ray, scene: &'a Scene) -> RayIntersection<'a> {
+    let mut closest_intersection = RayIntersection {
+        t: INFINITY,
+        normal: Vec3::new(0.0, 0.0, 0.0),
+        material: &NO_MATERIAL,
+        area: INFINITY,
+    };
+
+    for sphere in &scene.spheres {
+        if let Ok(intersection) = intersect_sphere(ray, sphere) {
+            if intersection.t < closest_intersection.t {
+                closest_intersection = intersection;
+            }
+        }
+    }
+
+    for plane in &scene.planes {
+        if let Ok(intersection) = intersect_plane(ray, plane) {
+            if intersection.t < closest_intersection.t {
+                closest_intersection = intersection;
+            }
+        }
+    }
+
+    for quad in &scene.quads {
+        if let Ok(intersection) = intersect_quad(ray, quad) {
+            if intersection.t < closest_intersection.t {
+                closest_intersection = intersection;
+            }
+        }
+    }
+
+    for mesh in &scene.meshes {
+        if let Ok(intersection) = intersect_mesh(ray, mesh, closest_intersection.t) {
+            closest_intersection = intersection;
+        }
+    }
+
+    closest_intersection
+}
+
+pub fn intersect_plane<'a>(ray: &Ray, plane: &'a Plane) -> Result<RayIntersection<'a>, RayError> {
+    // First, check if we intersect
+    let d_dot_n = ray.direction.dot(&plane.normal);
+
+    if d_dot_n == 0.0 {
+        // We just assume the ray is not embedded in the plane
+        return Err(RayError::NoIntersections)
+    }
+
+    // Find point of intersection
+    let t = (&plane.position - &ray.origin).dot(&plane.normal) / d_dot_n;
+
+    if t <= RAY_MIN_LENGTH || t >= INFINITY {
+        // Outside relevant range
+        return Err(RayError::NoIntersections)
+    }
+
+    Ok(RayIntersection {
+        t,
+        normal: plane.normal.clone(),
+        material: &*plane.material,
+        area: INFINITY,
+    })
+}
+
+pub fn intersect_sphere<'a>(ray: &Ray, sphere: &'a Sphere) -> Result<RayIntersection<'a>, RayError> {
+    // Transform ray so we can consider origin-centred sphere
+    let local_ray = Ray {
+        origin: &ray.origin - &sphere.position,
+        direction: ray.direction.clone(),
+    };
+
+    // Calculate quadratic coefficients
+    let a = local_ray.direction.dot(&local_ray.direction);
+    let b = 2.0 * local_ray.direction.dot(&local_ray.origin);
+    let c = local_ray.origin.dot(&local_ray.origin) - sphere.radius * sphere.radius;
+
+    // Check whether we intersect
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        return Err(RayError::NoIntersections)
+    }
+
+    // Find two points of intersection, t1 close and t2 far
+    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+
+    // First check if close intersection is valid, otherwise the ray starts inside
+    // the sphere and leaves through the far side
+    let t = if t1 > RAY_MIN_LENGTH {
+        t1
+    } else if t2 > RAY_MIN_LENGTH {
+        t2
+    } else {
+        // Neither are valid
+        return Err(RayError::NoIntersections)
+    };
+
+    // Always the outward normal, callers compare it with the ray direction to know the side
+    Ok(RayIntersection {
+        t,
+        normal: (&ray.origin + &(&ray.direction * t) - &sphere.position).unit(),
+        material: &*sphere.material,
+        area: 4.0 * PI * sphere.radius * sphere.radius,
+    })
+}
+
+pub fn intersect_quad<'a>(ray: &Ray, quad: &'a Quad) -> Result<RayIntersection<'a>, RayError> {
+    let normal = quad.edge_u.cross(&quad.edge_v);
+    let d_dot_n = ray.direction.dot(&normal);
+
+    if d_dot_n == 0.0 {
+        return Err(RayError::NoIntersections)
+    }
+
+    let t = (&quad.corner - &ray.origin).dot(&normal) / d_dot_n;
+
+    if t <= RAY_MIN_LENGTH || t >= INFINITY {
+        return Err(RayError::NoIntersections)
+    }
+
+    // Coordinates of the hit along both edges, inside when both are in 0..1
+    let hit = &ray.origin + &(&ray.direction * t) - &quad.corner;
+    let normal_sq = normal.dot(&normal);
+    let u = hit.cross(&quad.edge_v).dot(&normal) / normal_sq;
+    let v = quad.edge_u.cross(&hit).dot(&normal) / normal_sq;
+
+    if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+        return Err(RayError::NoIntersections)
+    }
+
+    Ok(RayIntersection {
+        t,
+        area: normal.mag(),
+        normal: normal.unit(),
+        material: &*quad.material,
+    })
+}
+
+// Closest triangle hit nearer than `max_t`
+pub fn intersect_mesh<'a>(ray: &Ray, mesh: &'a Mesh, max_t: f64) -> Result<RayIntersection<'a>, RayError> {
+    if !hits_box(ray, &mesh.bounds_min, &mesh.bounds_max, max_t) {
+        return Err(RayError::NoIntersections)
+    }
+
+    let mut closest = None;
+    let mut closest_t = max_t;
+
+    for index in 0..mesh.triangles.len() {
+        let (a, b, c) = mesh.triangle(index);
+        if let Some(t) = intersect_triangle(ray, a, b, c) {
+            if t < closest_t {
+                closest_t = t;
+                closest = Some(index);
+            }
+        }
+    }
+
+    match closest {
+        Some(index) => Ok(RayIntersection {
+            t: closest_t,
+            normal: mesh.triangle_normal(index).unit(),
+            material: &*mesh.material,
+            area: mesh.area(),
+        }),
+        None => Err(RayError::NoIntersections),
+    }
+}
+
+// Moller-Trumbore, works on either side of the triangle
+pub fn intersect_triangle(ray: &Ray, a: &Vec3, b: &Vec3, c: &Vec3) -> Option<f64> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+
+    if determinant.abs() < 1e-12 {
+        // Parallel to the triangle
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let to_origin = &ray.origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(&edge1);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inverse;
+    if t <= RAY_MIN_LENGTH || t >= INFINITY {
+        return None;
+    }
+
+    Some(t)
+}
+
+// Slab test, lets rays skip meshes they can't hit
+fn hits_box(ray: &Ray, min: &Vec3, max: &Vec3, max_t: f64) -> bool {
+    box_overlap(ray, min, max, max_t).is_some()
+}
+
+// Where `ray` is inside the box between 0 and `max_t`, if anywhere
+pub fn box_overlap(ray: &Ray, min: &Vec3, max: &Vec3, max_t: f64) -> Option<(f64, f64)> {
+    let mut t_near = 0.0_f64;
+    let mut t_far = max_t;
+
+    for &(origin, direction, low, high) in [
+        (ray.origin.x, ray.direction.x, min.x, max.x),
+        (ray.origin.y, ray.direction.y, min.y, max.y),
+        (ray.origin.z, ray.direction.z, min.z, max.z),
+    ].iter() {
+        let inverse = 1.0 / direction;
+        let t0 = (low - origin) * inverse;
+        let t1 = (high - origin) * inverse;
+        let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        t_near = t_near.max(t0);
+        t_far = t_far.min(t1);
+        if t_near > t_far {
+            return None;
+        }
+    }
+
+    Some((t_near, t_far))
+}
@@ -0,0 +1,168 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use camera::{create_ray_from_camera, Camera};
+use checkpoint::{scene_hash, write_checkpoint};
+use film::Film;
+use image::Color;
+use integrator::Integrator;
+use sampler::Sampler;
+use scene::Scene;
+use vec2::Vec2;
+
+#[derive(Debug)]
+pub struct Screen { pub width: u32, pub height: u32 }
+
+// Shared flag checked between pixels. Clones observe the same flag, so one can
+// be handed to a signal handler or another thread while the render holds the other.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    pub rows_done: u32,
+    pub rows_total: u32,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.rows_total == 0 {
+            return 1.0;
+        }
+        self.rows_done as f64 / self.rows_total as f64
+    }
+
+    // Linear extrapolation from the rows rendered so far
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+        let remaining = self.elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckpointSettings {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+#[derive(Debug)]
+pub struct RenderSettings {
+    pub integrator: Box<dyn Integrator>,
+    pub samples_per_pixel: u32,
+    pub seed: u64,
+    pub checkpoint: Option<CheckpointSettings>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RenderStatus {
+    Complete,
+    Cancelled,
+}
+
+// Adds samples to `film` one pass at a time until every pixel has
+// `samples_per_pixel` of them. Samples already in the film (from a resumed
+// checkpoint) are kept, so only the missing ones get rendered.
+// The film is left in a consistent state on cancellation or error, so the caller
+// can always write out whatever was accumulated.
+pub fn render<F>(
+    scene: &Scene,
+    camera: &Camera,
+    screen: &Screen,
+    settings: &mut RenderSettings,
+    film: &mut Film,
+    cancel: &CancelToken,
+    mut on_progress: F,
+) -> io::Result<RenderStatus>
+    where F: FnMut(&Progress)
+{
+    let hash = scene_hash(scene, camera, screen, &*settings.integrator);
+    let start = Instant::now();
+    let mut last_checkpoint = Instant::now();
+
+    let first_pass = film.min_samples();
+    let rows_total = settings.samples_per_pixel.saturating_sub(first_pass) * screen.height;
+    let mut rows_done = 0;
+
+    for pass in first_pass..settings.samples_per_pixel {
+        settings.integrator.start_pass(scene, settings.seed, pass);
+
+        for y in 0..screen.height {
+            for x in 0..screen.width {
+                if cancel.is_cancelled() {
+                    save_checkpoint(settings, hash, film)?;
+                    return Ok(RenderStatus::Cancelled);
+                }
+
+                let index = film.index(x, y);
+                if film.samples[index] > pass {
+                    // Already rendered before the previous run was interrupted
+                    continue;
+                }
+
+                let mut sampler = Sampler::new(settings.seed, index as u64, film.samples[index] as u64);
+
+                // -1.0 to 1.0, jittered inside the pixel
+                let normalized_pixel_location = Vec2::new(
+                    (x as f64 + sampler.next_f64()) / screen.width as f64 * 2.0 - 1.0,
+                    (y as f64 + sampler.next_f64()) / screen.height as f64 * 2.0 - 1.0
+                );
+
+                let color = render_pixel(normalized_pixel_location, camera, scene, &*settings.integrator, &mut sampler);
+                film.add_sample(x, y, &color);
+            }
+
+            rows_done += 1;
+            on_progress(&Progress {
+                rows_done,
+                rows_total,
+                elapsed: start.elapsed(),
+            });
+
+            if let Some(ref checkpoint) = settings.checkpoint {
+                if last_checkpoint.elapsed() >= checkpoint.interval {
+                    save_checkpoint(settings, hash, film)?;
+                    last_checkpoint = Instant::now();
+                }
+            }
+        }
+    }
+
+    save_checkpoint(settings, hash, film)?;
+    Ok(RenderStatus::Complete)
+}
+
+fn save_checkpoint(settings: &RenderSettings, scene_hash: u64, film: &Film) -> io::Result<()> {
+    match settings.checkpoint {
+        Some(ref checkpoint) => write_checkpoint(&checkpoint.path, scene_hash, settings.seed, film),
+        None => Ok(()),
+    }
+}
+
+pub fn render_pixel(normalized_pixel: Vec2, camera: &Camera, scene: &Scene, integrator: &dyn Integrator, sampler: &mut Sampler) -> Color {
+    let camera_ray = create_ray_from_camera(camera, &normalized_pixel);
+    integrator.trace(camera_ray, scene, sampler)
+}
@@ -0,0 +1,33 @@
+// Small deterministic PRNG (splitmix64). Every sample gets its own stream derived
+// from (seed, pixel, sample index), so a render can be stopped and resumed at any
+// pixel and still produce exactly the samples it would have produced in one go.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> Sampler {
+        let mut state = splitmix64(seed);
+        state = splitmix64(state ^ pixel);
+        state = splitmix64(state ^ sample);
+        Sampler { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        splitmix64(self.state)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
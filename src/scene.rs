@@ -0,0 +1,342 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use environment::{EnvironmentMap, EnvironmentSample};
+use grid_volume::GridVolume;
+use light::{Light, PointLight};
+use material::{Dielectric, Lambertian, Material, Plastic};
+use medium::Medium;
+use sky::Sky;
+use vec3::Vec3;
+use warp::{uniform_sphere, uniform_triangle};
+
+#[derive(Debug)]
+pub struct Scene {
+    pub lights: Vec<Light>,
+    pub spheres: Vec<Sphere>,
+    pub planes: Vec<Plane>,
+    pub quads: Vec<Quad>,
+    pub meshes: Vec<Mesh>,
+    pub background: Background,
+    // Filling the space between objects, like fog
+    pub medium: Option<Medium>,
+    // Smoke and clouds with their density varying over a box
+    pub volumes: Vec<GridVolume>,
+}
+
+// Nothing in it, against black
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene {
+            lights: Vec::new(),
+            spheres: Vec::new(),
+            planes: Vec::new(),
+            quads: Vec::new(),
+            meshes: Vec::new(),
+            background: Background::Color(Vec3::new(0.0, 0.0, 0.0)),
+            medium: None,
+            volumes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Background {
+    // Added to every pixel, a flat stand-in for light coming from everywhere
+    Color(Vec3),
+    // Seen by rays that miss everything, and lights the scene like a light would
+    Environment(EnvironmentMap),
+    // Daylight, usually with its sun among the lights
+    Sky(Box<Sky>),
+}
+
+impl Background {
+    // Seen by rays that miss everything
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        match *self {
+            Background::Color(ref color) => color.clone(),
+            Background::Environment(ref environment) => environment.radiance(direction),
+            Background::Sky(ref sky) => sky.radiance(direction),
+        }
+    }
+
+    // Shadow rays towards it per shading point, none for a flat color
+    pub fn samples(&self) -> u32 {
+        match *self {
+            Background::Color(_) => 0,
+            Background::Environment(ref environment) => environment.samples.max(1),
+            Background::Sky(ref sky) => sky.samples.max(1),
+        }
+    }
+
+    // Per solid angle, of sample() picking `direction`
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        match *self {
+            Background::Color(_) => 0.0,
+            Background::Environment(ref environment) => environment.pdf(direction),
+            Background::Sky(ref sky) => sky.sampling_map().pdf(direction),
+        }
+    }
+
+    // A direction to send a shadow ray in, and the light that comes from there
+    pub fn sample(&self, u1: f64, u2: f64) -> Option<EnvironmentSample> {
+        match *self {
+            Background::Color(_) => None,
+            Background::Environment(ref environment) => environment.sample(u1, u2),
+            Background::Sky(ref sky) => sky.sampling_map().sample(u1, u2).map(|sample| EnvironmentSample {
+                radiance: sky.radiance(&sample.direction),
+                ..sample
+            }),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::Color(Vec3::new(0.10, 0.10, 0.11))
+    }
+}
+
+#[derive(Debug)]
+pub struct Sphere {
+    pub position: Vec3,
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+#[derive(Debug)]
+pub struct Plane {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub material: Arc<dyn Material>,
+}
+
+// Parallelogram spanned by two edges from a corner, a rectangle when they are
+// perpendicular. Faces the way of edge_u x edge_v.
+#[derive(Debug)]
+pub struct Quad {
+    pub corner: Vec3,
+    pub edge_u: Vec3,
+    pub edge_v: Vec3,
+    pub material: Arc<dyn Material>,
+}
+
+// Triangles index into `vertices` and face the side their corners wind
+// counter-clockwise around, like OBJ files
+#[derive(Debug)]
+pub struct Mesh {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[usize; 3]>,
+    pub material: Arc<dyn Material>,
+    pub bounds_min: Vec3,
+    pub bounds_max: Vec3,
+    // Running total of triangle areas, to pick triangles by area
+    area_cdf: Vec<f64>,
+}
+
+// Meshes are picked from by area when they glow, and need some to pick from
+#[derive(Debug)]
+pub enum MeshError {
+    NoTriangles,
+    MissingVertex(usize),
+    NoArea,
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshError::NoTriangles => write!(f, "no triangles"),
+            MeshError::MissingVertex(index) => write!(f, "triangle uses missing vertex {}", index),
+            MeshError::NoArea => write!(f, "no area"),
+        }
+    }
+}
+
+// A point picked on the surface of a shape, `pdf` is per unit of area
+#[derive(Debug)]
+pub struct SurfaceSample {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub pdf: f64,
+}
+
+impl Sphere {
+    pub fn sample_surface(&self, u1: f64, u2: f64) -> SurfaceSample {
+        let normal = uniform_sphere(u1, u2);
+
+        SurfaceSample {
+            point: &self.position + &(&normal * self.radius),
+            normal,
+            pdf: 1.0 / (4.0 * PI * self.radius * self.radius),
+        }
+    }
+}
+
+impl Quad {
+    pub fn normal(&self) -> Vec3 {
+        self.edge_u.cross(&self.edge_v).unit()
+    }
+
+    pub fn sample_surface(&self, u1: f64, u2: f64) -> SurfaceSample {
+        SurfaceSample {
+            point: &self.corner + &(&self.edge_u * u1) + &self.edge_v * u2,
+            normal: self.normal(),
+            pdf: 1.0 / self.edge_u.cross(&self.edge_v).mag(),
+        }
+    }
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Arc<dyn Material>) -> Result<Mesh, MeshError> {
+        if triangles.is_empty() {
+            return Err(MeshError::NoTriangles);
+        }
+        if let Some(&index) = triangles.iter().flat_map(|triangle| triangle.iter()).find(|&&index| index >= vertices.len()) {
+            return Err(MeshError::MissingVertex(index));
+        }
+
+        let mut bounds_min = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut bounds_max = Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for vertex in &vertices {
+            bounds_min = Vec3::new(bounds_min.x.min(vertex.x), bounds_min.y.min(vertex.y), bounds_min.z.min(vertex.z));
+            bounds_max = Vec3::new(bounds_max.x.max(vertex.x), bounds_max.y.max(vertex.y), bounds_max.z.max(vertex.z));
+        }
+
+        let mut mesh = Mesh {
+            vertices,
+            triangles,
+            material,
+            bounds_min,
+            bounds_max,
+            area_cdf: Vec::new(),
+        };
+
+        let mut total = 0.0;
+        for index in 0..mesh.triangles.len() {
+            total += mesh.triangle_area(index);
+            mesh.area_cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Err(MeshError::NoArea);
+        }
+
+        Ok(mesh)
+    }
+
+    pub fn triangle(&self, index: usize) -> (&Vec3, &Vec3, &Vec3) {
+        let [a, b, c] = self.triangles[index];
+        (&self.vertices[a], &self.vertices[b], &self.vertices[c])
+    }
+
+    // Unnormalized, its length is twice the triangle's area
+    pub fn triangle_normal(&self, index: usize) -> Vec3 {
+        let (a, b, c) = self.triangle(index);
+        (b - a).cross(&(c - a))
+    }
+
+    fn triangle_area(&self, index: usize) -> f64 {
+        0.5 * self.triangle_normal(index).mag()
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area_cdf.last().cloned().unwrap_or(0.0)
+    }
+
+    // Triangle picked by area with u1, which is then reused for the point inside it
+    pub fn sample_surface(&self, u1: f64, u2: f64) -> SurfaceSample {
+        let target = u1 * self.area();
+        let index = match self.area_cdf.binary_search_by(|total| total.partial_cmp(&target).unwrap_or(Ordering::Less)) {
+            Ok(index) | Err(index) => index.min(self.triangles.len() - 1),
+        };
+
+        let previous = if index == 0 { 0.0 } else { self.area_cdf[index - 1] };
+        let triangle_area = self.area_cdf[index] - previous;
+        let u1 = if triangle_area > 0.0 { ((target - previous) / triangle_area).clamp(0.0, 1.0) } else { 0.0 };
+
+        let (a, b, c) = self.triangle(index);
+        let (weight_b, weight_c) = uniform_triangle(u1, u2);
+        let point = a * (1.0 - weight_b - weight_c) + &(b * weight_b) + &(c * weight_c);
+
+        SurfaceSample {
+            point,
+            normal: self.triangle_normal(index).unit(),
+            pdf: 1.0 / self.area(),
+        }
+    }
+}
+
+pub fn create_scene() -> Scene {
+    let wall = Arc::new(Lambertian { albedo: Vec3::new(0.2, 0.2, 0.2) });
+
+    let sphere1 = Sphere {
+        position: Vec3::new(0.0, 1.0, -3.0),
+        radius: 0.9,
+        material: Arc::new(Plastic::new(Vec3::new(1.0, 0.2, 0.2), 0.4)),
+    };
+    let sphere2 = Sphere {
+        position: Vec3::new(-1.0, 1.0, -2.0),
+        radius: 0.4,
+        material: Arc::new(Plastic::new(Vec3::new(0.0, 0.7, 0.0), 0.0)),
+    };
+    let sphere3 = Sphere {
+        position: Vec3::new(1.0, 1.2, -2.0),
+        radius: 0.6,
+        material: Arc::new(Dielectric::glass()),
+    };
+    let plane_floor = Plane {
+        position: Vec3::new(0.0, 1.9, 0.0),
+        normal: Vec3::new(0.0, -1.0, 0.0),
+        material: Arc::new(Plastic::new(Vec3::new(0.1, 0.1, 0.1), 0.0)),
+    };
+    let plane_ceiling = Plane {
+        position: Vec3::new(0.0, -6.5, 0.0),
+        normal: Vec3::new(0.0, 1.0, 0.0),
+        material: wall.clone(),
+    };
+    let plane_left = Plane {
+        position: Vec3::new(-2.3, 0.0, 0.0),
+        normal: Vec3::new(-1.0, 0.0, 0.0),
+        material: wall.clone(),
+    };
+    let plane_right = Plane {
+        position: Vec3::new(2.3, 0.0, 0.0),
+        normal: Vec3::new(1.0, 0.0, 0.0),
+        material: wall.clone(),
+    };
+    let plane_backwall = Plane {
+        position: Vec3::new(0.0, 0.0, -3.5),
+        normal: Vec3::new(0.0, 0.0, 1.0),
+        material: wall.clone(),
+    };
+    let plane_wall = Plane {
+        position: Vec3::new(0.0, 0.0, 4.0),
+        normal: Vec3::new(0.0, 0.0, -1.0),
+        material: wall,
+    };
+    let light1 = Light::Point(PointLight {
+        position: Vec3::new(2.2, -6.0, 0.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        force: 125.0,
+        profile: None,
+    });
+    let light2 = Light::Point(PointLight {
+        position: Vec3::new(-2.2, -6.0, 2.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        force: 98.0,
+        profile: None,
+    });
+
+    let lights = vec![light1, light2];
+    let spheres = vec![sphere1, sphere2, sphere3];
+    let planes = vec![plane_ceiling, plane_floor, plane_wall, plane_backwall, plane_left, plane_right];
+
+    Scene {
+        lights,
+        spheres,
+        planes,
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
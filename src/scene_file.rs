@@ -0,0 +1,786 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use environment::load_environment;
+use grid_volume::{load_density_grid, GridVolume};
+use material::{Dielectric, Emissive, Lambertian, Material, Metal, Mirror, Plastic, Principled, Subsurface, Volume};
+use ies::load_ies;
+use medium::Medium;
+use integrator::{integrator_by_name, Integrator, Whitted};
+use obj::load_obj;
+use occlusion::AmbientOcclusion;
+use light::{AreaLight, AreaShape, DirectionalLight, Light, LightProfile, PointLight, SpotLight};
+use scene::{Background, Mesh, Plane, Quad, Scene, Sphere};
+use sky::Sky;
+use vec3::Vec3;
+
+// Plain text scene description, one statement per line made of a keyword followed
+// by `key value...` pairs in any order. Missing keys keep their defaults, `#` starts
+// a comment and materials must be defined before objects refer to them by name.
+//
+//     camera origin 0 -0.6 2 target 0 0 0 fov 45
+//     integrator path
+//     integrator whitted ao_samples 16 ao_distance 0.5
+//     material red principled base_color 1 0.2 0.2 roughness 0.4 clearcoat 1
+//     sphere position 0 1 -3 radius 0.9 material red
+//     plane position 0 1.9 0 normal 0 -1 0 material red
+//     quad corner -1 -2 -1 edge_u 2 0 0 edge_v 0 0 2 material lamp
+//     mesh file teapot.obj position 0 1 -2 scale 0.5 material red
+//     light position 2 -6 0 color 1 1 1 force 100
+//     light position 0 -3 0 force 100 ies downlight.ies nadir 0 1 0
+//     light position 0 -3 0 force 80 shape disk normal 0 1 0 radius 0.5 samples 16
+//     directional_light direction 1 2 -1 color 1 0.95 0.9 strength 2
+//     spot_light position 0 -3 0 direction 0 1 0 cone_angle 30 falloff 4 force 100
+//     background color 0 0 0
+//     environment file sky.hdr rotation 90 strength 1.5 samples 16
+//     sky sun_direction 1 -2 -1 turbidity 3 ground_albedo 0.3 0.3 0.3 sun_strength 5
+//     medium absorption 0.01 0.01 0.01 scattering 0.05 0.05 0.05 anisotropy 0.6
+//     grid_volume file smoke.vol min -1 -1 -2 max 1 1 0 density 4 albedo 0.9 0.9 0.9
+//
+// Integrators: whitted, path, bdpt, photons, ao, normals. The command line's --integrator wins over the file's.
+// ao_samples and ao_distance set up ambient occlusion for ao, and turn it on to darken whitted's ambient term.
+//
+// Light shapes: point, rectangle (edge_u edge_v), disk (normal radius), sphere (radius).
+// Point and spot lights take an IES profile with `ies`, aimed along `nadir` (down by
+// default) or the spot's direction.
+//
+// The environment replaces the background and lights the scene, from a Radiance .hdr
+// or PFM equirectangular image turned `rotation` degrees around the vertical axis.
+// A sky does the same from a daylight model, and adds its sun as a directional light.
+//
+// A medium fills the space between objects, volume materials fill the objects they
+// are on instead, with the same keys. Coefficients are per unit of distance, anisotropy
+// between -1 (back towards the light) and 1 (onwards). The path tracer scatters light
+// in them, whitted fades into fog.
+//
+// Grid volumes stretch a density grid over the box from `min` to `max`, density 1
+// having an extinction of `density` per unit of distance, of which `albedo` scatters.
+// Grid files are raw: width, height and depth as little-endian u32, then a little-endian
+// f32 per voxel, x changing fastest, then y, then z. They also take an anisotropy.
+//
+// Subsurface materials let light into closed objects and out elsewhere. `albedo`, from
+// 0 to 1, is about the colour of thick parts, `mean_free_path` how far light gets
+// inside between bounces, per channel. Only the path tracer follows it inside.
+//
+// Mesh, IES, environment and grid files are relative to the scene file. Mesh files are OBJ. Material types and their keys:
+//
+//     emissive    radiance
+//     lambertian  albedo
+//     mirror      reflectance
+//     dielectric  ior absorption
+//     plastic     diffuse roughness ior
+//     metal       color roughness
+//     principled  base_color subsurface metallic specular specular_tint roughness
+//                 sheen sheen_tint clearcoat clearcoat_gloss transmission ior
+//     volume      absorption scattering anisotropy
+//     subsurface  albedo mean_free_path ior anisotropy
+
+#[derive(Debug)]
+pub struct SceneFile {
+    pub scene: Scene,
+    pub view: Option<View>,
+    pub integrator: Option<Box<dyn Integrator>>,
+}
+
+// Where the camera looks from, when the scene file says
+#[derive(Debug)]
+pub struct View {
+    pub origin: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub fov: f64,
+}
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneFileError::Io(ref err) => write!(f, "{}", err),
+            SceneFileError::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(err: io::Error) -> SceneFileError {
+        SceneFileError::Io(err)
+    }
+}
+
+pub fn load_scene(path: &Path) -> Result<SceneFile, SceneFileError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&fs::read_to_string(path)?, base_dir)
+}
+
+// `base_dir` is where relative mesh, IES, environment and grid paths start from
+pub fn parse_scene(contents: &str, base_dir: &Path) -> Result<SceneFile, SceneFileError> {
+    let mut scene = Scene {
+        background: Background::default(),
+        ..Scene::default()
+    };
+    let mut view = None;
+    let mut integrator = None;
+    let mut materials = HashMap::new();
+    let mut sky_line = None;
+    let mut environment_line = None;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = Tokens { tokens: line.split_whitespace() };
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        let result = match keyword {
+            "camera" => parse_view(&mut tokens).map(|parsed| view = Some(parsed)),
+            "integrator" => parse_integrator(&mut tokens).map(|parsed| integrator = Some(parsed)),
+            "material" => parse_named_material(&mut tokens).map(|(name, material)| {
+                materials.insert(name, material);
+            }),
+            "sphere" => parse_sphere(&mut tokens, &materials).map(|sphere| scene.spheres.push(sphere)),
+            "plane" => parse_plane(&mut tokens, &materials).map(|plane| scene.planes.push(plane)),
+            "quad" => parse_quad(&mut tokens, &materials).map(|quad| scene.quads.push(quad)),
+            "mesh" => parse_mesh(&mut tokens, &materials, base_dir).map(|mesh| scene.meshes.push(mesh)),
+            "light" => parse_light(&mut tokens, base_dir).map(|light| scene.lights.push(light)),
+            "directional_light" => parse_directional_light(&mut tokens).map(|light| scene.lights.push(light)),
+            "spot_light" => parse_spot_light(&mut tokens, base_dir).map(|light| scene.lights.push(light)),
+            // A sky adds its sun to the lights, which would be left without its sky by
+            // anything replacing it, or light the scene twice with a second sky
+            "background" => no_sky(sky_line)
+                .and_then(|_| parse_background(&mut tokens))
+                .map(|background| scene.background = background),
+            "environment" => no_sky(sky_line).and_then(|_| parse_environment(&mut tokens, base_dir)).map(|background| {
+                scene.background = background;
+                environment_line = Some(line_number);
+            }),
+            "sky" => match environment_line {
+                Some(first) => Err(format!("environment already set on line {}", first)),
+                None => no_sky(sky_line).and_then(|_| parse_sky(&mut tokens)).map(|(background, sun)| {
+                    scene.background = background;
+                    scene.lights.push(sun);
+                    sky_line = Some(line_number);
+                }),
+            },
+            "medium" => parse_medium(&mut tokens, keyword).map(|medium| scene.medium = Some(medium)),
+            "grid_volume" => parse_grid_volume(&mut tokens, base_dir).map(|volume| scene.volumes.push(volume)),
+            _ => Err(format!("unknown statement {}", keyword)),
+        };
+
+        if let Err(message) = result {
+            return Err(SceneFileError::Syntax { line: line_number, message });
+        }
+    }
+
+    Ok(SceneFile { scene, view, integrator })
+}
+
+struct Tokens<'a> {
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    fn word(&mut self, what: &str) -> Result<&'a str, String> {
+        self.next().ok_or_else(|| format!("missing {}", what))
+    }
+
+    fn number(&mut self, key: &str) -> Result<f64, String> {
+        let token = self.word(key)?;
+        token.parse().map_err(|_| format!("invalid number for {}: {}", key, token))
+    }
+
+    fn vec3(&mut self, key: &str) -> Result<Vec3, String> {
+        Ok(Vec3::new(self.number(key)?, self.number(key)?, self.number(key)?))
+    }
+}
+
+fn no_sky(sky_line: Option<usize>) -> Result<(), String> {
+    match sky_line {
+        Some(line) => Err(format!("sky already set on line {}", line)),
+        None => Ok(()),
+    }
+}
+
+fn unknown_key(statement: &str, key: &str) -> Result<(), String> {
+    Err(format!("unknown key {} for {}", key, statement))
+}
+
+fn parse_view(tokens: &mut Tokens) -> Result<View, String> {
+    let mut view = View {
+        origin: Vec3::new(0.0, 0.0, 0.0),
+        target: Vec3::new(0.0, 0.0, -1.0),
+        up: Vec3::new(0.0, 1.0, 0.0),
+        fov: 45.0,
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "origin" => view.origin = tokens.vec3(key)?,
+            "target" => view.target = tokens.vec3(key)?,
+            "up" => view.up = tokens.vec3(key)?,
+            "fov" => view.fov = tokens.number(key)?,
+            _ => unknown_key("camera", key)?,
+        }
+    }
+
+    Ok(view)
+}
+
+fn parse_integrator(tokens: &mut Tokens) -> Result<Box<dyn Integrator>, String> {
+    let name = tokens.word("integrator name")?;
+    let integrator: Box<dyn Integrator> = match name {
+        "whitted" => Box::new(Whitted { ambient_occlusion: parse_occlusion(tokens, name, None)? }),
+        "ao" => Box::new(parse_occlusion(tokens, name, Some(AmbientOcclusion::default()))?.unwrap_or_default()),
+        _ => {
+            let integrator = integrator_by_name(name).ok_or_else(|| format!("unknown integrator {}", name))?;
+            if let Some(key) = tokens.next() {
+                unknown_key("integrator", key)?;
+            }
+            integrator
+        }
+    };
+
+    Ok(integrator)
+}
+
+// Either key turns ambient occlusion on, with the default for the other
+fn parse_occlusion(tokens: &mut Tokens, integrator: &str, mut occlusion: Option<AmbientOcclusion>) -> Result<Option<AmbientOcclusion>, String> {
+    while let Some(key) = tokens.next() {
+        match key {
+            "ao_samples" => occlusion.get_or_insert_with(AmbientOcclusion::default).samples = tokens.number(key)? as u32,
+            "ao_distance" => occlusion.get_or_insert_with(AmbientOcclusion::default).max_distance = tokens.number(key)?,
+            _ => unknown_key(integrator, key)?,
+        }
+    }
+
+    Ok(occlusion)
+}
+
+fn parse_named_material(tokens: &mut Tokens) -> Result<(String, Arc<dyn Material>), String> {
+    let name = tokens.word("material name")?;
+    let kind = tokens.word("material type")?;
+
+    let material: Arc<dyn Material> = match kind {
+        "emissive" => Arc::new(parse_emissive(tokens)?),
+        "lambertian" => Arc::new(parse_lambertian(tokens)?),
+        "mirror" => Arc::new(parse_mirror(tokens)?),
+        "dielectric" => Arc::new(parse_dielectric(tokens)?),
+        "plastic" => Arc::new(parse_plastic(tokens)?),
+        "metal" => Arc::new(parse_metal(tokens)?),
+        "principled" => Arc::new(parse_principled(tokens)?),
+        "volume" => Arc::new(Volume { medium: parse_medium(tokens, kind)? }),
+        "subsurface" => Arc::new(parse_subsurface(tokens)?),
+        _ => return Err(format!("unknown material type {}", kind)),
+    };
+
+    Ok((name.to_string(), material))
+}
+
+fn parse_emissive(tokens: &mut Tokens) -> Result<Emissive, String> {
+    let mut material = Emissive { radiance: Vec3::new(1.0, 1.0, 1.0) };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "radiance" => material.radiance = tokens.vec3(key)?,
+            _ => unknown_key("emissive", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_lambertian(tokens: &mut Tokens) -> Result<Lambertian, String> {
+    let mut material = Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "albedo" => material.albedo = tokens.vec3(key)?,
+            _ => unknown_key("lambertian", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_mirror(tokens: &mut Tokens) -> Result<Mirror, String> {
+    let mut material = Mirror { reflectance: Vec3::new(1.0, 1.0, 1.0) };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "reflectance" => material.reflectance = tokens.vec3(key)?,
+            _ => unknown_key("mirror", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_dielectric(tokens: &mut Tokens) -> Result<Dielectric, String> {
+    let mut material = Dielectric::glass();
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "ior" => material.ior = tokens.number(key)?,
+            "absorption" => material.absorption = tokens.vec3(key)?,
+            _ => unknown_key("dielectric", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_plastic(tokens: &mut Tokens) -> Result<Plastic, String> {
+    let mut material = Plastic::new(Vec3::new(0.5, 0.5, 0.5), 0.0);
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "diffuse" => material.diffuse = tokens.vec3(key)?,
+            "roughness" => material.roughness = tokens.number(key)?,
+            "ior" => material.ior = tokens.number(key)?,
+            _ => unknown_key("plastic", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_metal(tokens: &mut Tokens) -> Result<Metal, String> {
+    let mut material = Metal::silver(0.0);
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "color" => material.color = tokens.vec3(key)?,
+            "roughness" => material.roughness = tokens.number(key)?,
+            _ => unknown_key("metal", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_principled(tokens: &mut Tokens) -> Result<Principled, String> {
+    let mut material = Principled::new(Vec3::new(0.8, 0.8, 0.8));
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "base_color" => material.base_color = tokens.vec3(key)?,
+            "subsurface" => material.subsurface = tokens.number(key)?,
+            "metallic" => material.metallic = tokens.number(key)?,
+            "specular" => material.specular = tokens.number(key)?,
+            "specular_tint" => material.specular_tint = tokens.number(key)?,
+            "roughness" => material.roughness = tokens.number(key)?,
+            "sheen" => material.sheen = tokens.number(key)?,
+            "sheen_tint" => material.sheen_tint = tokens.number(key)?,
+            "clearcoat" => material.clearcoat = tokens.number(key)?,
+            "clearcoat_gloss" => material.clearcoat_gloss = tokens.number(key)?,
+            "transmission" => material.transmission = tokens.number(key)?,
+            "ior" => material.ior = tokens.number(key)?,
+            _ => unknown_key("principled", key)?,
+        }
+    }
+
+    Ok(material)
+}
+
+fn parse_subsurface(tokens: &mut Tokens) -> Result<Subsurface, String> {
+    let mut material = Subsurface::new(Vec3::new(0.8, 0.8, 0.8), Vec3::new(0.1, 0.1, 0.1));
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "albedo" => material.albedo = tokens.vec3(key)?,
+            "mean_free_path" => material.mean_free_path = tokens.vec3(key)?,
+            "ior" => material.ior = tokens.number(key)?,
+            "anisotropy" => material.anisotropy = tokens.number(key)?,
+            _ => unknown_key("subsurface", key)?,
+        }
+    }
+
+    let albedo = &material.albedo;
+    if albedo.x.min(albedo.y).min(albedo.z) < 0.0 || albedo.max_component() > 1.0 {
+        return Err(String::from("albedo must be between 0 and 1"));
+    }
+    let path = &material.mean_free_path;
+    if path.x <= 0.0 || path.y <= 0.0 || path.z <= 0.0 {
+        return Err(String::from("mean_free_path must be positive"));
+    }
+    if material.anisotropy <= -1.0 || material.anisotropy >= 1.0 {
+        return Err(format!("anisotropy must be between -1 and 1, got {}", material.anisotropy));
+    }
+    Ok(material)
+}
+
+// For the scene's medium and volume materials
+fn parse_medium(tokens: &mut Tokens, statement: &str) -> Result<Medium, String> {
+    let mut medium = Medium {
+        absorption: Vec3::new(0.0, 0.0, 0.0),
+        scattering: Vec3::new(0.1, 0.1, 0.1),
+        anisotropy: 0.0,
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "absorption" => medium.absorption = tokens.vec3(key)?,
+            "scattering" => medium.scattering = tokens.vec3(key)?,
+            "anisotropy" => medium.anisotropy = tokens.number(key)?,
+            _ => unknown_key(statement, key)?,
+        }
+    }
+
+    // Negative coefficients would make light grow on its way through
+    for &(name, coefficients) in [("absorption", &medium.absorption), ("scattering", &medium.scattering)].iter() {
+        if coefficients.x.min(coefficients.y).min(coefficients.z) < 0.0 {
+            return Err(format!("{} can't be negative", name));
+        }
+    }
+    if medium.anisotropy <= -1.0 || medium.anisotropy >= 1.0 {
+        return Err(format!("anisotropy must be between -1 and 1, got {}", medium.anisotropy));
+    }
+    Ok(medium)
+}
+
+fn parse_grid_volume(tokens: &mut Tokens, base_dir: &Path) -> Result<GridVolume, String> {
+    let mut file = None;
+    let mut min = Vec3::new(-1.0, -1.0, -1.0);
+    let mut max = Vec3::new(1.0, 1.0, 1.0);
+    let mut density = 1.0;
+    let mut albedo = Vec3::new(0.8, 0.8, 0.8);
+    let mut anisotropy = 0.0;
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "file" => file = Some(tokens.word(key)?),
+            "min" => min = tokens.vec3(key)?,
+            "max" => max = tokens.vec3(key)?,
+            "density" => density = tokens.number(key)?,
+            "albedo" => albedo = tokens.vec3(key)?,
+            "anisotropy" => anisotropy = tokens.number(key)?,
+            _ => unknown_key("grid_volume", key)?,
+        }
+    }
+
+    if min.x >= max.x || min.y >= max.y || min.z >= max.z {
+        return Err(String::from("min must be below max on every axis"));
+    }
+    // Tracking takes density and albedo as chances, out of range they would add light
+    if density < 0.0 {
+        return Err(format!("density can't be negative, got {}", density));
+    }
+    if albedo.x.min(albedo.y).min(albedo.z) < 0.0 || albedo.max_component() > 1.0 {
+        return Err(String::from("albedo must be between 0 and 1"));
+    }
+    if anisotropy <= -1.0 || anisotropy >= 1.0 {
+        return Err(format!("anisotropy must be between -1 and 1, got {}", anisotropy));
+    }
+    let file = file.ok_or_else(|| String::from("missing file"))?;
+    let grid = load_density_grid(&base_dir.join(file)).map_err(|err| format!("can't load density grid {}: {}", file, err))?;
+
+    Ok(GridVolume { min, max, grid, density, albedo, anisotropy })
+}
+
+fn find_material(materials: &HashMap<String, Arc<dyn Material>>, name: &str) -> Result<Arc<dyn Material>, String> {
+    materials.get(name).cloned().ok_or_else(|| format!("undefined material {}", name))
+}
+
+// Objects without a material get a plain grey one
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) })
+}
+
+fn parse_sphere(tokens: &mut Tokens, materials: &HashMap<String, Arc<dyn Material>>) -> Result<Sphere, String> {
+    let mut sphere = Sphere {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: default_material(),
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "position" => sphere.position = tokens.vec3(key)?,
+            "radius" => sphere.radius = tokens.number(key)?,
+            "material" => sphere.material = find_material(materials, tokens.word(key)?)?,
+            _ => unknown_key("sphere", key)?,
+        }
+    }
+
+    if sphere.radius <= 0.0 {
+        return Err(format!("radius must be positive, got {}", sphere.radius));
+    }
+    Ok(sphere)
+}
+
+fn parse_plane(tokens: &mut Tokens, materials: &HashMap<String, Arc<dyn Material>>) -> Result<Plane, String> {
+    let mut plane = Plane {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        normal: Vec3::new(0.0, -1.0, 0.0),
+        material: default_material(),
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "position" => plane.position = tokens.vec3(key)?,
+            "normal" => plane.normal = tokens.vec3(key)?.unit(),
+            "material" => plane.material = find_material(materials, tokens.word(key)?)?,
+            _ => unknown_key("plane", key)?,
+        }
+    }
+
+    Ok(plane)
+}
+
+fn parse_quad(tokens: &mut Tokens, materials: &HashMap<String, Arc<dyn Material>>) -> Result<Quad, String> {
+    let mut quad = Quad {
+        corner: Vec3::new(0.0, 0.0, 0.0),
+        edge_u: Vec3::new(1.0, 0.0, 0.0),
+        edge_v: Vec3::new(0.0, 0.0, 1.0),
+        material: default_material(),
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "corner" => quad.corner = tokens.vec3(key)?,
+            "edge_u" => quad.edge_u = tokens.vec3(key)?,
+            "edge_v" => quad.edge_v = tokens.vec3(key)?,
+            "material" => quad.material = find_material(materials, tokens.word(key)?)?,
+            _ => unknown_key("quad", key)?,
+        }
+    }
+
+    if quad.edge_u.cross(&quad.edge_v).mag() <= 0.0 {
+        return Err(String::from("edge_u and edge_v must span an area"));
+    }
+    Ok(quad)
+}
+
+// Vertices are scaled first, then moved to `position`
+fn parse_mesh(tokens: &mut Tokens, materials: &HashMap<String, Arc<dyn Material>>, base_dir: &Path) -> Result<Mesh, String> {
+    let mut file = None;
+    let mut position = Vec3::new(0.0, 0.0, 0.0);
+    let mut scale = 1.0;
+    let mut material = default_material();
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "file" => file = Some(tokens.word(key)?),
+            "position" => position = tokens.vec3(key)?,
+            "scale" => scale = tokens.number(key)?,
+            "material" => material = find_material(materials, tokens.word(key)?)?,
+            _ => unknown_key("mesh", key)?,
+        }
+    }
+
+    let file = file.ok_or_else(|| String::from("missing file"))?;
+    let obj = load_obj(&base_dir.join(file)).map_err(|err| format!("can't load mesh {}: {}", file, err))?;
+    let vertices = obj.vertices.iter().map(|vertex| &(vertex * scale) + &position).collect();
+
+    Mesh::new(vertices, obj.triangles, material).map_err(|err| format!("can't use mesh {}: {}", file, err))
+}
+
+// Point light unless a shape is given, the shape's size keys can come in any order
+fn parse_light(tokens: &mut Tokens, base_dir: &Path) -> Result<Light, String> {
+    let mut position = Vec3::new(0.0, 0.0, 0.0);
+    let mut color = Vec3::new(1.0, 1.0, 1.0);
+    let mut force = 100.0;
+    let mut samples = 1;
+    let mut shape = "point";
+    let mut edge_u = Vec3::new(1.0, 0.0, 0.0);
+    let mut edge_v = Vec3::new(0.0, 0.0, 1.0);
+    let mut normal = Vec3::new(0.0, 1.0, 0.0);
+    let mut radius = 0.5;
+    let mut ies = None;
+    let mut nadir = Vec3::new(0.0, 1.0, 0.0);
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "position" => position = tokens.vec3(key)?,
+            "color" => color = tokens.vec3(key)?,
+            "force" => force = tokens.number(key)?,
+            "shape" => shape = tokens.word(key)?,
+            "edge_u" => edge_u = tokens.vec3(key)?,
+            "edge_v" => edge_v = tokens.vec3(key)?,
+            "normal" => normal = tokens.vec3(key)?,
+            "radius" => radius = tokens.number(key)?,
+            "samples" => samples = tokens.number(key)? as u32,
+            "ies" => ies = Some(tokens.word(key)?),
+            "nadir" => nadir = tokens.vec3(key)?,
+            _ => unknown_key("light", key)?,
+        }
+    }
+
+    let shape = match shape {
+        "point" => {
+            let profile = load_profile(ies, nadir, base_dir)?;
+            return Ok(Light::Point(PointLight { position, color, force, profile }));
+        }
+        "rectangle" => AreaShape::Rectangle { edge_u, edge_v },
+        "disk" => AreaShape::Disk { normal, radius },
+        "sphere" => AreaShape::Sphere { radius },
+        _ => return Err(format!("unknown light shape {}", shape)),
+    };
+    // Points get picked on the shape by area
+    match shape {
+        AreaShape::Rectangle { ref edge_u, ref edge_v } if edge_u.cross(edge_v).mag() <= 0.0 => {
+            return Err(String::from("edge_u and edge_v must span an area"));
+        }
+        AreaShape::Disk { radius, .. } | AreaShape::Sphere { radius } if radius <= 0.0 => {
+            return Err(format!("radius must be positive, got {}", radius));
+        }
+        _ => {}
+    }
+
+    if ies.is_some() {
+        return Err(String::from("ies profiles only work on point and spot lights"));
+    }
+    Ok(Light::Area(AreaLight { position, color, force, shape, samples }))
+}
+
+fn parse_directional_light(tokens: &mut Tokens) -> Result<Light, String> {
+    let mut light = DirectionalLight {
+        direction: Vec3::new(0.0, 1.0, 0.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        strength: 1.0,
+    };
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "direction" => light.direction = tokens.vec3(key)?.unit(),
+            "color" => light.color = tokens.vec3(key)?,
+            "strength" => light.strength = tokens.number(key)?,
+            _ => unknown_key("directional_light", key)?,
+        }
+    }
+
+    Ok(Light::Directional(light))
+}
+
+fn parse_spot_light(tokens: &mut Tokens, base_dir: &Path) -> Result<Light, String> {
+    let mut light = SpotLight {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.0, 1.0, 0.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        force: 100.0,
+        cone_angle: 30.0,
+        falloff: 1.0,
+        profile: None,
+    };
+    let mut ies = None;
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "position" => light.position = tokens.vec3(key)?,
+            "direction" => light.direction = tokens.vec3(key)?.unit(),
+            "color" => light.color = tokens.vec3(key)?,
+            "force" => light.force = tokens.number(key)?,
+            "cone_angle" => light.cone_angle = tokens.number(key)?,
+            "falloff" => light.falloff = tokens.number(key)?,
+            "ies" => ies = Some(tokens.word(key)?),
+            _ => unknown_key("spot_light", key)?,
+        }
+    }
+
+    light.profile = load_profile(ies, light.direction.clone(), base_dir)?;
+    Ok(Light::Spot(light))
+}
+
+fn parse_background(tokens: &mut Tokens) -> Result<Background, String> {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "color" => color = tokens.vec3(key)?,
+            _ => unknown_key("background", key)?,
+        }
+    }
+
+    Ok(Background::Color(color))
+}
+
+fn parse_environment(tokens: &mut Tokens, base_dir: &Path) -> Result<Background, String> {
+    let mut file = None;
+    let mut rotation = 0.0;
+    let mut strength = 1.0;
+    let mut samples = None;
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "file" => file = Some(tokens.word(key)?),
+            "rotation" => rotation = tokens.number(key)?,
+            "strength" => strength = tokens.number(key)?,
+            "samples" => samples = Some(tokens.number(key)? as u32),
+            _ => unknown_key("environment", key)?,
+        }
+    }
+
+    let file = file.ok_or_else(|| String::from("missing file"))?;
+    let mut environment = load_environment(&base_dir.join(file)).map_err(|err| format!("can't load environment {}: {}", file, err))?;
+    environment.rotation = rotation;
+    environment.strength = strength;
+    if let Some(samples) = samples {
+        environment.samples = samples;
+    }
+
+    Ok(Background::Environment(environment))
+}
+
+fn parse_sky(tokens: &mut Tokens) -> Result<(Background, Light), String> {
+    let mut sun_direction = Vec3::new(0.5, -1.0, -0.5);
+    let mut turbidity = 3.0;
+    let mut ground_albedo = Vec3::new(0.3, 0.3, 0.3);
+    let mut strength = None;
+    let mut sun_strength = None;
+    let mut samples = None;
+
+    while let Some(key) = tokens.next() {
+        match key {
+            "sun_direction" => sun_direction = tokens.vec3(key)?,
+            "turbidity" => turbidity = tokens.number(key)?,
+            "ground_albedo" => ground_albedo = tokens.vec3(key)?,
+            "strength" => strength = Some(tokens.number(key)?),
+            "sun_strength" => sun_strength = Some(tokens.number(key)?),
+            "samples" => samples = Some(tokens.number(key)? as u32),
+            _ => unknown_key("sky", key)?,
+        }
+    }
+
+    if turbidity < 1.0 {
+        return Err(format!("turbidity must be at least 1, got {}", turbidity));
+    }
+    let mut sky = Sky::new(sun_direction, turbidity, ground_albedo);
+    if let Some(strength) = strength {
+        sky.strength = strength;
+    }
+    if let Some(sun_strength) = sun_strength {
+        sky.sun_strength = sun_strength;
+    }
+    if let Some(samples) = samples {
+        sky.samples = samples;
+    }
+
+    let sun = Light::Directional(sky.sun_light());
+    Ok((Background::Sky(Box::new(sky)), sun))
+}
+
+fn load_profile(file: Option<&str>, nadir: Vec3, base_dir: &Path) -> Result<Option<LightProfile>, String> {
+    let file = match file {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+
+    let ies = load_ies(&base_dir.join(file)).map_err(|err| format!("can't load ies profile {}: {}", file, err))?;
+    Ok(Some(LightProfile { ies: Arc::new(ies), nadir: nadir.unit() }))
+}
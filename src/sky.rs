@@ -0,0 +1,178 @@
+use std::f64::consts::PI;
+
+use environment::EnvironmentMap;
+use light::DirectionalLight;
+use vec3::Vec3;
+
+// Radiance is in kcd/m² in the model, this brings a clear midday sky to about the
+// brightness of the scenes' other lights
+const LUMINANCE_SCALE: f64 = 0.04;
+// Resolution of the copy used to pick directions towards the bright parts
+const SAMPLING_WIDTH: usize = 128;
+const SAMPLING_HEIGHT: usize = 64;
+
+// Preetham, Shirley and Smits' analytic daylight model: clear sky from the sun's
+// position and the haziness of the air (`turbidity`, 2 very clear to 10 hazy). Below
+// the horizon is flat ground lit by the sky and the sun. The sun itself is not part
+// of it, sun_light() gives the matching directional light.
+#[derive(Debug)]
+pub struct Sky {
+    // Unit vector towards the sun, up is -y
+    pub sun_direction: Vec3,
+    pub turbidity: f64,
+    pub ground_albedo: Vec3,
+    pub strength: f64,
+    pub sun_strength: f64,
+    // Shadow rays towards it per shading point
+    pub samples: u32,
+    // Luminance and chromaticity at the zenith, and the Perez coefficients of each
+    zenith: [f64; 3],
+    perez: [[f64; 5]; 3],
+    // Irradiance on the ground from the sky at strength 1
+    sky_irradiance: Vec3,
+    sun_transmittance: Vec3,
+    sampling_map: EnvironmentMap,
+}
+
+impl Sky {
+    pub fn new(sun_direction: Vec3, turbidity: f64, ground_albedo: Vec3) -> Sky {
+        let sun_direction = sun_direction.unit();
+        let t = turbidity;
+        let theta_sun = (-sun_direction.y).clamp(-1.0, 1.0).acos().min(PI / 2.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |t2: [f64; 4], t1: [f64; 4], t0: [f64; 4]| {
+            let powers = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            (0..4).map(|i| (t * t * t2[i] + t * t1[i] + t0[i]) * powers[i]).sum::<f64>()
+        };
+        let zenith_x = chromaticity(
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        );
+        let zenith_y = chromaticity(
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        );
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        let mut sky = Sky {
+            sun_direction,
+            turbidity,
+            ground_albedo,
+            strength: 1.0,
+            sun_strength: 5.0,
+            samples: 8,
+            zenith: [zenith_luminance, zenith_x, zenith_y],
+            perez,
+            sky_irradiance: Vec3::new(0.0, 0.0, 0.0),
+            sun_transmittance: sun_transmittance(theta_sun, turbidity),
+            sampling_map: EnvironmentMap::new(1, 1, vec![Vec3::new(0.0, 0.0, 0.0)]).expect("one pixel for one by one"),
+        };
+
+        // Only the sky half of the sampling map is known yet, which is all the ground needs
+        let mut pixels = Vec::with_capacity(SAMPLING_WIDTH * SAMPLING_HEIGHT);
+        let mut irradiance = Vec3::new(0.0, 0.0, 0.0);
+        for row in 0..SAMPLING_HEIGHT {
+            let theta = PI * (row as f64 + 0.5) / SAMPLING_HEIGHT as f64;
+            for column in 0..SAMPLING_WIDTH {
+                let phi = 2.0 * PI * (column as f64 + 0.5) / SAMPLING_WIDTH as f64;
+                let direction = Vec3::new(theta.sin() * phi.sin(), -theta.cos(), -theta.sin() * phi.cos());
+                let radiance = sky.sky_radiance(&direction);
+                let solid_angle = theta.sin() * (PI / SAMPLING_HEIGHT as f64) * (2.0 * PI / SAMPLING_WIDTH as f64);
+                irradiance = irradiance + &radiance * (theta.cos().max(0.0) * solid_angle);
+                pixels.push(radiance);
+            }
+        }
+        sky.sky_irradiance = irradiance;
+
+        let ground = sky.ground_radiance();
+        for pixel in pixels.iter_mut().skip(SAMPLING_WIDTH * SAMPLING_HEIGHT / 2) {
+            *pixel = ground.clone();
+        }
+        sky.sampling_map = EnvironmentMap::new(SAMPLING_WIDTH, SAMPLING_HEIGHT, pixels).expect("a pixel for every direction");
+        sky
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let direction = direction.unit();
+        if direction.y >= 0.0 {
+            return self.ground_radiance() * self.strength;
+        }
+        self.sky_radiance(&direction) * self.strength
+    }
+
+    // The sun as a light, dimmed and reddened by the air it crosses
+    pub fn sun_light(&self) -> DirectionalLight {
+        DirectionalLight {
+            direction: &self.sun_direction * -1.0,
+            color: self.sun_transmittance.clone(),
+            strength: self.sun_strength,
+        }
+    }
+
+    // Sky brightness by direction, for picking where shadow rays go
+    pub fn sampling_map(&self) -> &EnvironmentMap {
+        &self.sampling_map
+    }
+
+    // At strength 1, above the horizon
+    fn sky_radiance(&self, direction: &Vec3) -> Vec3 {
+        // The model blows up at the horizon
+        let cos_theta = (-direction.y).max(0.01);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_sun = (-self.sun_direction.y).clamp(-1.0, 1.0).acos().min(PI / 2.0);
+
+        let mut values = [0.0; 3];
+        for (index, value) in values.iter_mut().enumerate() {
+            let coefficients = &self.perez[index];
+            *value = self.zenith[index] * perez(coefficients, cos_theta, gamma) / perez(coefficients, 1.0, theta_sun);
+        }
+        let [luminance, x, y] = values;
+
+        xyz_to_rgb(x / y * luminance, luminance, (1.0 - x - y) / y * luminance) * LUMINANCE_SCALE
+    }
+
+    // Lambertian ground under the sky and the sun
+    fn ground_radiance(&self) -> Vec3 {
+        let sun_height = (-self.sun_direction.y).max(0.0);
+        let irradiance = &self.sky_irradiance * self.strength + &self.sun_transmittance * (self.sun_strength * sun_height);
+        &self.ground_albedo * &irradiance * (1.0 / PI)
+    }
+}
+
+fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
+
+// Linear sRGB primaries
+fn xyz_to_rgb(x: f64, y: f64, z: f64) -> Vec3 {
+    Vec3::new(
+        (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+        (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+        (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+    )
+}
+
+// Rayleigh and aerosol scattering out of the sunlight along its path through the air,
+// at wavelengths standing for red, green and blue
+fn sun_transmittance(theta_sun: f64, turbidity: f64) -> Vec3 {
+    let degrees = theta_sun.to_degrees();
+    let air_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - degrees).max(0.001).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |micrometres: f64| {
+        let rayleigh = 0.008735 * micrometres.powf(-4.08);
+        let aerosol = beta * micrometres.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+
+    Vec3::new(transmittance(0.68), transmittance(0.55), transmittance(0.44))
+}
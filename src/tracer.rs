@@ -0,0 +1,201 @@
+use image::Color;
+use lighting::{direct_lighting, emitter_lighting, environment_lighting, offset_ray_origin, segment_medium, Mis};
+use grid_volume::GridVolume;
+use material::Material;
+use medium::{henyey_greenstein, Medium};
+use occlusion::AmbientOcclusion;
+use optics::beer_lambert;
+use ray::{trace, Ray, RayIntersection, INFINITY};
+use sampler::Sampler;
+use scene::{Background, Scene};
+use vec3::Vec3;
+
+const MAX_DEPTH: u32 = 6;
+const MIN_CONTRIBUTION: f64 = 0.01;
+// Points fog gets lit at along each ray, spread over this many mean free paths at most
+const FOG_STEPS: u32 = 16;
+const FOG_DEPTH: f64 = 4.0;
+
+// With `occlusion` the ambient term is darker where the surroundings are closed in
+pub fn trace_ray(initial_ray: Ray, scene: &Scene, occlusion: Option<&AmbientOcclusion>, sampler: &mut Sampler) -> Color {
+    let mut final_color = radiance(&initial_ray, scene, occlusion, sampler, 0, 1.0);
+
+    // A flat background lifts everything, an environment is only seen where rays miss
+    if let Background::Color(ref background) = scene.background {
+        final_color = final_color + background;
+    }
+
+    display_color(final_color)
+}
+
+// Radiance to what the film stores, with the tracer's usual bit of extra contrast
+pub fn display_color(radiance: Vec3) -> Color {
+    // add contrast
+    let mut final_color = (radiance - 0.5) * 1.10 + 0.5;
+
+    if final_color.x < 0.0 { final_color.x = 0.0; }
+    if final_color.y < 0.0 { final_color.y = 0.0; }
+    if final_color.z < 0.0 { final_color.z = 0.0; }
+
+    if final_color.x > 1.0 { final_color.x = 1.0; }
+    if final_color.y > 1.0 { final_color.y = 1.0; }
+    if final_color.z > 1.0 { final_color.z = 1.0; }
+
+
+    Color { r: final_color.x, g: final_color.y, b: final_color.z }
+}
+
+// `contribution` is how much this ray still counts towards the pixel,
+// branches that can't visibly change it anymore aren't followed
+fn radiance(ray: &Ray, scene: &Scene, occlusion: Option<&AmbientOcclusion>, sampler: &mut Sampler, depth: u32, contribution: f64) -> Vec3 {
+    let intersection = trace(ray, scene);
+    let distance = if intersection.t >= INFINITY { INFINITY } else { intersection.t * ray.direction.mag() };
+    let mut color = surface_radiance(ray, &intersection, scene, occlusion, sampler, depth, contribution);
+
+    if let Some(medium) = segment_medium(ray, &intersection, scene) {
+        color = fog(color, ray, distance, medium, scene, sampler);
+    }
+    for volume in &scene.volumes {
+        color = grid_fog(color, ray, distance, volume, scene, sampler);
+    }
+
+    color
+}
+
+fn surface_radiance(
+    ray: &Ray,
+    intersection: &RayIntersection,
+    scene: &Scene,
+    occlusion: Option<&AmbientOcclusion>,
+    sampler: &mut Sampler,
+    depth: u32,
+    contribution: f64,
+) -> Vec3 {
+    if intersection.t >= INFINITY {
+        return match scene.background {
+            // Already added to the whole image
+            Background::Color(_) => Vec3::new(0.0, 0.0, 0.0),
+            ref background => background.radiance(&ray.direction),
+        };
+    }
+
+    let ray_hit = &ray.origin + &(&ray.direction * intersection.t);
+    let material = intersection.material;
+    let normal = &intersection.normal;
+    let wo = (&ray.direction * -1.0).unit();
+
+    let mut color = material.emission(normal, &wo);
+    let mut ambient = ambient_lighting(&ray_hit, material, scene);
+    if let Some(occlusion) = occlusion {
+        if ambient.max_component() > 0.0 {
+            // Over the side the ray came from
+            let facing = if normal.dot(&wo) < 0.0 { normal * -1.0 } else { normal.clone() };
+            ambient = ambient * occlusion.visibility(&ray_hit, &facing, scene, sampler);
+        }
+    }
+    color = color + ambient;
+    color = color + direct_lighting(&wo, &ray_hit, normal, material, scene, sampler);
+    color = color + emitter_lighting(&wo, &ray_hit, normal, material, scene, sampler, Mis::Off);
+    color = color + environment_lighting(&wo, &ray_hit, normal, material, scene, sampler, Mis::Off);
+
+    // === Reflection / refraction
+    for lobe in material.specular_lobes(normal, &wo) {
+        let lobe_contribution = contribution * lobe.weight.max_component();
+        let next_ray = Ray {
+            origin: offset_ray_origin(&ray_hit, normal, &lobe.direction),
+            direction: lobe.direction,
+        };
+        color = color + bounce(&next_ray, scene, occlusion, sampler, depth, lobe_contribution) * &lobe.weight;
+    }
+
+    // === Absorption
+    // Hit from the inside, so the light travelled through the object to get here
+    if ray.direction.dot(normal) > 0.0 {
+        let distance = intersection.t * ray.direction.mag();
+        color = color * beer_lambert(&material.absorption(), distance);
+    }
+
+    color
+}
+
+fn bounce(ray: &Ray, scene: &Scene, occlusion: Option<&AmbientOcclusion>, sampler: &mut Sampler, depth: u32, contribution: f64) -> Vec3 {
+    if depth + 1 >= MAX_DEPTH || contribution < MIN_CONTRIBUTION {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    radiance(ray, scene, occlusion, sampler, depth + 1, contribution)
+}
+
+// Cheap stand-in for light in a medium: `color` fades with distance into the light
+// the medium scatters towards the viewer, taken from the lights at a few points along
+// the ray without shadows. Past a few mean free paths there is little left to see.
+fn fog(color: Vec3, ray: &Ray, distance: f64, medium: &Medium, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let direction = ray.direction.unit();
+    let wo = &direction * -1.0;
+    let extinction = medium.extinction();
+    let mean_free_path = 3.0 / (extinction.x + extinction.y + extinction.z).max(1.0 / INFINITY);
+    let step = distance.min(FOG_DEPTH * mean_free_path) / FOG_STEPS as f64;
+
+    // One random offset for all of them, so the steps don't show as bands
+    let offset = sampler.next_f64();
+    let mut in_scattered = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..FOG_STEPS {
+        let along = (i as f64 + offset) * step;
+        let point = &ray.origin + &(&direction * along);
+        let lighting = unshadowed_lighting(&point, &wo, medium.anisotropy, scene);
+        in_scattered = in_scattered + lighting * &medium.transmittance(along) * step;
+    }
+
+    color * &medium.transmittance(distance) + &(in_scattered * &medium.scattering)
+}
+
+// The same for a grid volume, its density read at each step
+fn grid_fog(color: Vec3, ray: &Ray, distance: f64, volume: &GridVolume, scene: &Scene, sampler: &mut Sampler) -> Vec3 {
+    let direction = ray.direction.unit();
+    let unit_ray = Ray { origin: ray.origin.clone(), direction: direction.clone() };
+    let (start, end) = match volume.overlap(&unit_ray, distance) {
+        Some(overlap) => overlap,
+        None => return color,
+    };
+    let wo = &direction * -1.0;
+    let step = (end - start) / FOG_STEPS as f64;
+
+    let offset = sampler.next_f64();
+    let mut transmittance = 1.0;
+    let mut in_scattered = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..FOG_STEPS {
+        let point = &unit_ray.origin + &(&direction * (start + (i as f64 + offset) * step));
+        let extinction = volume.extinction(&point);
+        if extinction <= 0.0 {
+            continue;
+        }
+        let lighting = unshadowed_lighting(&point, &wo, volume.anisotropy, scene);
+        in_scattered = in_scattered + lighting * &volume.albedo * (transmittance * extinction * step);
+        transmittance *= (-extinction * step).exp();
+    }
+
+    color * transmittance + &in_scattered
+}
+
+// Light from the scene's lights scattered towards `wo` at `point`, per unit of scattering
+fn unshadowed_lighting(point: &Vec3, wo: &Vec3, anisotropy: f64, scene: &Scene) -> Vec3 {
+    let mut lighting = Vec3::new(0.0, 0.0, 0.0);
+    for light in &scene.lights {
+        if let Some(sample) = light.sample(point, 0.5, 0.5) {
+            lighting = lighting + sample.irradiance * henyey_greenstein(-wo.dot(&sample.direction), anisotropy);
+        }
+    }
+    lighting
+}
+
+// Flat stand-in for indirect light, brighter close to lights
+fn ambient_lighting(ray_hit: &Vec3, material: &dyn Material, scene: &Scene) -> Vec3 {
+    let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+    for position in scene.lights.iter().filter_map(|light| light.position()) {
+        let ray_hit_to_light = position - ray_hit;
+        color = color + material.diffuse_albedo() * (0.2 / ray_hit_to_light.dot(&ray_hit_to_light));
+    }
+
+    color
+}
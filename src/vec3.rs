@@ -16,14 +16,16 @@ impl Vec3 {
         self - &(normal * 2.0 * self.dot(normal))
     }
 
-    pub fn refract(&self, normal: &Vec3, eta: f64) -> Vec3 {
+    // Expects unit vectors, with the normal facing against self.
+    // Returns None on total internal reflection.
+    pub fn refract(&self, normal: &Vec3, eta: f64) -> Option<Vec3> {
         let n_dot_i = normal.dot(self);
         let k = 1.0 - eta * eta * (1.0 - n_dot_i * n_dot_i);
 
         if k < 0.0 {
-            Vec3::new(0.0, 0.0, 0.0)
+            None
         } else {
-            self * eta - normal * (eta * n_dot_i + k.sqrt()) 
+            Some(self * eta - normal * (eta * n_dot_i + k.sqrt()))
         }
     }
 
@@ -39,12 +41,12 @@ impl Vec3 {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
 
-    pub fn dot(&self, other: &Vec3) -> f64 {
-        self.x * other.x + self.y * other.y + self.z * other.z
+    pub fn max_component(&self) -> f64 {
+        self.x.max(self.y).max(self.z)
     }
 
-    pub fn clone(&self) -> Vec3 {
-        Vec3 { x: self.x, y: self.y, z: self.z }
+    pub fn dot(&self, other: &Vec3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
     }
 
     pub fn cross(&self, other: &Vec3) -> Vec3 {
@@ -59,13 +61,13 @@ impl Vec3 {
         let mag = self.mag();
 
         if mag > 0.0 {
-            return Vec3 {
+            Vec3 {
                 x: self.x / mag,
                 y: self.y / mag,
                 z: self.z / mag,
             }
         } else {
-            return Vec3 { x: 0.0, y: 0.0, z: 0.0 }
+            Vec3 { x: 0.0, y: 0.0, z: 0.0 }
         }
     }
 }
@@ -82,7 +84,7 @@ impl ops::Add for Vec3 {
     }
 }
 
-impl<'a > ops::Add<&'a Vec3> for Vec3 {
+impl ops::Add<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn add(self, other: &Vec3) -> Vec3 {
@@ -94,10 +96,10 @@ impl<'a > ops::Add<&'a Vec3> for Vec3 {
     }
 }
 
-impl<'a, 'b> ops::Add<&'b Vec3> for &'a Vec3 {
+impl ops::Add<&Vec3> for &Vec3 {
     type Output = Vec3;
 
-    fn add(self, other: &'b Vec3) -> Vec3 {
+    fn add(self, other: &Vec3) -> Vec3 {
         Vec3 {
             x: self.x + other.x,
             y: self.y + other.y,
@@ -130,7 +132,7 @@ impl ops::Sub for Vec3 {
     }
 }
 
-impl<'a > ops::Sub<&'a Vec3> for Vec3 {
+impl ops::Sub<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn sub(self, other: &Vec3) -> Vec3 {
@@ -142,10 +144,10 @@ impl<'a > ops::Sub<&'a Vec3> for Vec3 {
     }
 }
 
-impl<'a, 'b> ops::Sub<&'b Vec3> for &'a Vec3 {
+impl ops::Sub<&Vec3> for &Vec3 {
     type Output = Vec3;
 
-    fn sub(self, other: &'b Vec3) -> Vec3 {
+    fn sub(self, other: &Vec3) -> Vec3 {
         Vec3 {
             x: self.x - other.x,
             y: self.y - other.y,
@@ -178,7 +180,7 @@ impl ops::Mul for Vec3 {
     }
 }
 
-impl<'a > ops::Mul<&'a Vec3> for Vec3 {
+impl ops::Mul<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn mul(self, other: &Vec3) -> Vec3 {
@@ -190,10 +192,10 @@ impl<'a > ops::Mul<&'a Vec3> for Vec3 {
     }
 }
 
-impl<'a, 'b> ops::Mul<&'b Vec3> for &'a Vec3 {
+impl ops::Mul<&Vec3> for &Vec3 {
     type Output = Vec3;
 
-    fn mul(self, other: &'b Vec3) -> Vec3 {
+    fn mul(self, other: &Vec3) -> Vec3 {
         Vec3 {
             x: self.x * other.x,
             y: self.y * other.y,
@@ -214,7 +216,7 @@ impl ops::Mul<f64> for Vec3 {
     }
 }
 
-impl<'a> ops::Mul<f64> for &'a Vec3 {
+impl ops::Mul<f64> for &Vec3 {
     type Output = Vec3;
 
     fn mul(self, other: f64) -> Vec3 {
@@ -238,7 +240,7 @@ impl ops::Div for Vec3 {
     }
 }
 
-impl<'a > ops::Div<&'a Vec3> for Vec3 {
+impl ops::Div<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn div(self, other: &Vec3) -> Vec3 {
@@ -250,10 +252,10 @@ impl<'a > ops::Div<&'a Vec3> for Vec3 {
     }
 }
 
-impl<'a, 'b> ops::Div<&'b Vec3> for &'a Vec3 {
+impl ops::Div<&Vec3> for &Vec3 {
     type Output = Vec3;
 
-    fn div(self, other: &'b Vec3) -> Vec3 {
+    fn div(self, other: &Vec3) -> Vec3 {
         Vec3 {
             x: self.x / other.x,
             y: self.y / other.y,
@@ -0,0 +1,79 @@
+use std::f64::consts::PI;
+
+use vec3::Vec3;
+
+// Orthonormal basis around a normal, to build directions in a local space where
+// the normal is +z and bring them back to world space
+#[derive(Debug)]
+pub struct Frame {
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub normal: Vec3,
+}
+
+impl Frame {
+    pub fn from_normal(normal: &Vec3) -> Frame {
+        // Any axis not parallel to the normal works as a starting point
+        let helper = if normal.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let tangent = helper.cross(normal).unit();
+        let bitangent = normal.cross(&tangent);
+
+        Frame {
+            tangent,
+            bitangent,
+            normal: normal.clone(),
+        }
+    }
+
+    pub fn to_world(&self, local: &Vec3) -> Vec3 {
+        &(&self.tangent * local.x) + &(&self.bitangent * local.y) + &self.normal * local.z
+    }
+
+    pub fn to_local(&self, world: &Vec3) -> Vec3 {
+        Vec3::new(world.dot(&self.tangent), world.dot(&self.bitangent), world.dot(&self.normal))
+    }
+}
+
+// Local space, z up. pdf is cos(theta) / PI
+pub fn cosine_hemisphere(u1: f64, u2: f64) -> Vec3 {
+    let radius = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(radius * phi.cos(), radius * phi.sin(), (1.0 - u1).max(0.0).sqrt())
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+    cos_theta.max(0.0) / PI
+}
+
+// Uniform over the unit sphere. pdf is 1 / (4 PI)
+pub fn uniform_sphere(u1: f64, u2: f64) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(radius * phi.cos(), radius * phi.sin(), z)
+}
+
+// Barycentric weights of the 2nd and 3rd vertices, uniform over the triangle's area
+pub fn uniform_triangle(u1: f64, u2: f64) -> (f64, f64) {
+    let root = u1.sqrt();
+    (root * (1.0 - u2), root * u2)
+}
+
+// Uniform over the directions within acos(cos_max) of +z. pdf is 1 / (2 PI (1 - cos_max))
+pub fn uniform_cone(cos_max: f64, u1: f64, u2: f64) -> Vec3 {
+    let cos_theta = 1.0 - u1 * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+// Uniform over the unit disk in the xy plane
+pub fn uniform_disk(u1: f64, u2: f64) -> (f64, f64) {
+    let radius = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    (radius * phi.cos(), radius * phi.sin())
+}
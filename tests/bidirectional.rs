@@ -0,0 +1,192 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::bidirectional::bidirectional_radiance;
+use rust_tracer::light::{AreaLight, AreaShape, DirectionalLight, Light, PointLight, SpotLight};
+use rust_tracer::lighting::Mis;
+use rust_tracer::material::{Dielectric, Emissive, Lambertian, Metal, Volume};
+use rust_tracer::medium::Medium;
+use rust_tracer::path_tracer::path_radiance;
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Background, Plane, Quad, Scene, Sphere};
+use rust_tracer::sky::Sky;
+use rust_tracer::vec3::Vec3;
+
+const SAMPLES: u64 = 20000;
+
+fn matte(albedo: f64) -> Arc<Lambertian> {
+    Arc::new(Lambertian { albedo: Vec3::new(albedo, albedo, albedo) })
+}
+
+// Inside of a closed box 2 wide, with the floor at y = 1
+fn closed_box(lights: Vec<Light>, quads: Vec<Quad>, spheres: Vec<Sphere>) -> Scene {
+    let wall = |x: f64, y: f64, z: f64| Plane {
+        position: Vec3::new(x, y, z),
+        normal: Vec3::new(-x, -y, -z),
+        material: matte(0.6),
+    };
+
+    Scene {
+        lights,
+        spheres,
+        planes: vec![
+            wall(1.0, 0.0, 0.0),
+            wall(-1.0, 0.0, 0.0),
+            wall(0.0, 1.0, 0.0),
+            wall(0.0, -1.0, 0.0),
+            wall(0.0, 0.0, 1.0),
+            wall(0.0, 0.0, -1.0),
+        ],
+        quads,
+        ..Scene::default()
+    }
+}
+
+// Mean green and its standard error
+fn estimate<F: FnMut(Ray, &mut Sampler) -> Vec3>(mut radiance: F) -> (f64, f64) {
+    let values: Vec<f64> = (0..SAMPLES)
+        .map(|index| {
+            let ray = Ray {
+                origin: Vec3::new(0.2, -0.3, 0.9),
+                direction: Vec3::new(-0.3, 0.6, -1.0),
+            };
+            radiance(ray, &mut Sampler::new(7, index, 0)).y
+        })
+        .collect();
+
+    let mean = values.iter().sum::<f64>() / SAMPLES as f64;
+    let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / SAMPLES as f64;
+    (mean, (variance / SAMPLES as f64).sqrt())
+}
+
+// Both integrators converge to the same image, each connection strategy is weighted
+// so they add up to one
+fn assert_agrees_with_path_tracer(scene: &Scene) {
+    let (path_mean, path_error) = estimate(|ray, sampler| path_radiance(ray, scene, Mis::PowerHeuristic, sampler));
+    let (mean, error) = estimate(|ray, sampler| bidirectional_radiance(ray, scene, sampler));
+
+    assert!(mean > 0.0);
+    let tolerance = 4.0 * (path_error * path_error + error * error).sqrt();
+    assert!((mean - path_mean).abs() < tolerance, "{} vs {} from the path tracer", mean, path_mean);
+}
+
+#[test]
+fn point_spot_and_area_lights_through_glass() {
+    let lights = vec![
+        Light::Point(PointLight {
+            position: Vec3::new(0.3, -0.7, 0.2),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 1.0,
+            profile: None,
+        }),
+        Light::Spot(SpotLight {
+            position: Vec3::new(-0.5, -0.8, 0.0),
+            direction: Vec3::new(0.0, 1.0, -0.3),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 2.0,
+            cone_angle: 40.0,
+            falloff: 2.0,
+            profile: None,
+        }),
+        Light::Area(AreaLight {
+            position: Vec3::new(0.3, -0.5, -0.2),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 2.0,
+            shape: AreaShape::Sphere { radius: 0.2 },
+            samples: 1,
+        }),
+    ];
+    let spheres = vec![Sphere {
+        position: Vec3::new(-0.2, 0.5, -0.3),
+        radius: 0.4,
+        material: Arc::new(Dielectric::glass()),
+    }];
+
+    assert_agrees_with_path_tracer(&closed_box(lights, Vec::new(), spheres));
+}
+
+#[test]
+fn emissive_geometry_and_glossy_metal() {
+    let quads = vec![Quad {
+        corner: Vec3::new(-0.3, -0.99, -0.3),
+        edge_u: Vec3::new(0.0, 0.0, 0.6),
+        edge_v: Vec3::new(0.6, 0.0, 0.0),
+        material: Arc::new(Emissive { radiance: Vec3::new(3.0, 3.0, 3.0) }),
+    }];
+    let spheres = vec![
+        Sphere {
+            position: Vec3::new(0.3, -0.5, -0.2),
+            radius: 0.2,
+            material: Arc::new(Emissive { radiance: Vec3::new(3.0, 3.0, 3.0) }),
+        },
+        Sphere {
+            position: Vec3::new(-0.4, 0.6, -0.5),
+            radius: 0.35,
+            material: Arc::new(Metal { color: Vec3::new(0.9, 0.9, 0.9), roughness: 0.3 }),
+        },
+    ];
+
+    assert_agrees_with_path_tracer(&closed_box(Vec::new(), quads, spheres));
+}
+
+// Lights without a position are only sampled from the camera side
+#[test]
+fn sun_and_sky() {
+    let scene = Scene {
+        lights: vec![Light::Directional(DirectionalLight {
+            direction: Vec3::new(0.3, 1.0, -0.2),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            strength: 1.0,
+        })],
+        planes: vec![Plane {
+            position: Vec3::new(0.0, 1.0, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: matte(0.6),
+        }],
+        background: Background::Sky(Box::new(Sky::new(Vec3::new(0.5, -1.0, 0.3), 3.0, Vec3::new(0.3, 0.3, 0.3)))),
+        ..Scene::default()
+    };
+
+    assert_agrees_with_path_tracer(&scene);
+}
+
+// The sun's shadow rays go through volumes instead of stopping at their boundary
+#[test]
+fn sun_through_a_volume() {
+    let sun_on_floor = |spheres: Vec<Sphere>| {
+        let scene = Scene {
+            lights: vec![Light::Directional(DirectionalLight {
+                direction: Vec3::new(0.0, 1.0, 0.0),
+                color: Vec3::new(1.0, 1.0, 1.0),
+                strength: 1.0,
+            })],
+            spheres,
+            planes: vec![Plane {
+                position: Vec3::new(0.0, 1.0, 0.0),
+                normal: Vec3::new(0.0, -1.0, 0.0),
+                material: matte(0.6),
+            }],
+            ..Scene::default()
+        };
+        estimate(|ray, sampler| bidirectional_radiance(ray, &scene, sampler)).0
+    };
+    // Right above where the camera ray meets the floor, out of the camera's way
+    let absorbing = Sphere {
+        position: Vec3::new(-0.45, -1.0, -1.2 - 0.2 / 3.0),
+        radius: 0.5,
+        material: Arc::new(Volume {
+            medium: Medium {
+                absorption: Vec3::new(1.0, 1.0, 1.0),
+                scattering: Vec3::new(0.0, 0.0, 0.0),
+                anisotropy: 0.0,
+            },
+        }),
+    };
+
+    let clear = sun_on_floor(Vec::new());
+    let shaded = sun_on_floor(vec![absorbing]);
+    assert!(clear > 0.0);
+    assert!((shaded / clear - (-1.0f64).exp()).abs() < 1e-3, "{} vs {}", shaded, clear);
+}
@@ -0,0 +1,103 @@
+extern crate rust_tracer;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rust_tracer::camera::{create_camera, Camera};
+use rust_tracer::checkpoint::{scene_hash, Checkpoint, CheckpointError};
+use rust_tracer::film::Film;
+use rust_tracer::image::Color;
+use rust_tracer::integrator::{Integrator, PathTracer, Whitted};
+use rust_tracer::render::Screen;
+use rust_tracer::scene::create_scene;
+use rust_tracer::vec3::Vec3;
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("rust-tracer-{}-{}.checkpoint", name, std::process::id()))
+}
+
+fn camera() -> Camera {
+    create_camera(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 45.0, 2.0, Vec3::new(0.0, -1.0, 0.0))
+}
+
+fn film() -> Film {
+    let mut film = Film::new(4, 2);
+    film.add_sample(3, 1, &Color { r: 0.5, g: 0.25, b: 1.0 });
+    film.add_sample(3, 1, &Color { r: 0.5, g: 0.25, b: 1.0 });
+    film.add_sample(0, 0, &Color { r: 0.1, g: 0.2, b: 0.3 });
+    film
+}
+
+#[test]
+fn saves_and_loads_the_film() {
+    let path = checkpoint_path("round-trip");
+    Checkpoint { scene_hash: 11, seed: 7, film: film() }.save(&path).expect("checkpoint written");
+
+    let loaded = Checkpoint::load(&path).expect("checkpoint read back");
+    assert_eq!((loaded.scene_hash, loaded.seed), (11, 7));
+    assert_eq!((loaded.film.width, loaded.film.height), (4, 2));
+    assert_eq!(loaded.film.samples, film().samples);
+    assert_eq!((loaded.film.sums[7].x, loaded.film.sums[7].y, loaded.film.sums[0].z), (1.0, 0.5, 0.3));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn resumes_only_the_same_scene() {
+    let scene = create_scene();
+    let screen = Screen { width: 4, height: 2 };
+    let whitted = Whitted::default();
+    let path = checkpoint_path("resume");
+    let hash = scene_hash(&scene, &camera(), &screen, &whitted);
+    Checkpoint { scene_hash: hash, seed: 7, film: film() }.save(&path).expect("checkpoint written");
+
+    let checkpoint = Checkpoint::resume(&path, &scene, &camera(), &screen, &whitted).expect("same scene resumes");
+    assert_eq!((checkpoint.seed, checkpoint.film.samples[7]), (7, 2));
+
+    let wider = Screen { width: 8, height: 1 };
+    let changes: [(&Screen, &dyn Integrator); 2] = [(&wider, &whitted), (&screen, &PathTracer)];
+    for &(screen, integrator) in changes.iter() {
+        match Checkpoint::resume(&path, &scene, &camera(), screen, integrator) {
+            Err(CheckpointError::SceneChanged) => {}
+            other => panic!("expected a changed scene, got {:?}", other),
+        }
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let path = checkpoint_path("truncated");
+    Checkpoint { scene_hash: 1, seed: 2, film: film() }.save(&path).expect("checkpoint written");
+
+    let bytes = fs::read(&path).unwrap();
+    fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+    match Checkpoint::load(&path) {
+        Err(CheckpointError::InvalidFormat) => {}
+        other => panic!("expected an invalid checkpoint, got {:?}", other),
+    }
+
+    fs::write(&path, b"RTCKPT02").unwrap();
+    match Checkpoint::load(&path) {
+        Err(CheckpointError::InvalidFormat) => {}
+        other => panic!("expected an invalid checkpoint, got {:?}", other),
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+// Sizes in a corrupt header have to match the file before a film gets allocated
+#[test]
+fn rejects_sizes_the_file_doesnt_hold() {
+    let path = checkpoint_path("corrupt");
+    Checkpoint { scene_hash: 1, seed: 2, film: Film::new(2, 2) }.save(&path).expect("checkpoint written");
+
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[24..32].copy_from_slice(&[0xff; 8]);
+    fs::write(&path, &bytes).unwrap();
+
+    match Checkpoint::load(&path) {
+        Err(CheckpointError::InvalidFormat) => {}
+        other => panic!("expected an invalid checkpoint, got {:?}", other),
+    }
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,26 @@
+// Fixtures shared by the integration tests
+
+use std::sync::Arc;
+
+use rust_tracer::light::{Light, PointLight};
+use rust_tracer::material::Lambertian;
+use rust_tracer::scene::{Plane, Scene};
+use rust_tracer::vec3::Vec3;
+
+// A white point light of force 1 at `light`, over a grey floor at height `floor`
+pub fn lit_floor(light: Vec3, floor: f64) -> Scene {
+    Scene {
+        lights: vec![Light::Point(PointLight {
+            position: light,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 1.0,
+            profile: None,
+        })],
+        planes: vec![Plane {
+            position: Vec3::new(0.0, floor, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) }),
+        }],
+        ..Scene::default()
+    }
+}
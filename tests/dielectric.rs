@@ -0,0 +1,97 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::material::{Dielectric, Material, IOR_AIR, IOR_GLASS};
+use rust_tracer::optics::fresnel_dielectric;
+use rust_tracer::ray::{intersect_sphere, Ray};
+use rust_tracer::scene::Sphere;
+use rust_tracer::vec3::Vec3;
+
+fn glass_sphere() -> Sphere {
+    Sphere {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        radius: 1.0,
+        material: Arc::new(Dielectric::glass()),
+    }
+}
+
+#[test]
+fn fresnel_at_normal_incidence() {
+    // ((n1 - n2) / (n1 + n2))^2
+    let expected = ((IOR_AIR - IOR_GLASS) / (IOR_AIR + IOR_GLASS)).powi(2);
+
+    assert!((fresnel_dielectric(1.0, IOR_AIR, IOR_GLASS) - expected).abs() < 1e-12);
+    // Same both ways at normal incidence
+    assert!((fresnel_dielectric(1.0, IOR_GLASS, IOR_AIR) - expected).abs() < 1e-12);
+}
+
+#[test]
+fn fresnel_at_grazing_angle_reflects_everything() {
+    assert!((fresnel_dielectric(0.0, IOR_AIR, IOR_GLASS) - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn fresnel_past_critical_angle_is_total_internal_reflection() {
+    let critical_cos = (1.0 - (IOR_AIR / IOR_GLASS).powi(2)).sqrt();
+
+    assert_eq!(fresnel_dielectric(critical_cos * 0.9, IOR_GLASS, IOR_AIR), 1.0);
+    assert!(fresnel_dielectric(critical_cos * 1.01, IOR_GLASS, IOR_AIR) < 1.0);
+}
+
+#[test]
+fn ray_inside_sphere_hits_far_side() {
+    let ray = Ray {
+        origin: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.0, 0.0, -1.0),
+    };
+
+    let sphere = glass_sphere();
+    let intersection = intersect_sphere(&ray, &sphere).ok().expect("ray from the centre must leave the sphere");
+    assert!((intersection.t - 1.0).abs() < 1e-12);
+    // Outward normal, same way as the ray is going
+    assert!(intersection.normal.dot(&ray.direction) > 0.0);
+}
+
+#[test]
+fn ray_outside_sphere_hits_near_side() {
+    let ray = Ray {
+        origin: Vec3::new(0.0, 0.0, 3.0),
+        direction: Vec3::new(0.0, 0.0, -1.0),
+    };
+
+    let sphere = glass_sphere();
+    let intersection = intersect_sphere(&ray, &sphere).ok().expect("ray aimed at the sphere must hit it");
+    assert!((intersection.t - 2.0).abs() < 1e-12);
+    assert!(intersection.normal.dot(&ray.direction) < 0.0);
+}
+
+#[test]
+fn specular_lobes_split_all_the_light() {
+    let glass = Dielectric::glass();
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let wo = Vec3::new(0.6, 0.0, 0.8);
+
+    let lobes = glass.specular_lobes(&normal, &wo);
+    assert_eq!(lobes.len(), 2);
+
+    let total: f64 = lobes.iter().map(|lobe| lobe.weight.x).sum();
+    assert!((total - 1.0).abs() < 1e-12);
+    // Reflected stays outside, refracted goes in
+    assert!(lobes[0].direction.dot(&normal) > 0.0);
+    assert!(lobes[1].direction.dot(&normal) < 0.0);
+}
+
+#[test]
+fn specular_lobes_inside_past_critical_angle_only_reflect() {
+    let glass = Dielectric::glass();
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    // Seen from inside the glass, 60 degrees off the normal
+    let angle: f64 = 60.0_f64.to_radians();
+    let wo = Vec3::new(angle.sin(), 0.0, -angle.cos());
+
+    let lobes = glass.specular_lobes(&normal, &wo);
+    assert_eq!(lobes.len(), 1);
+    assert_eq!(lobes[0].weight.x, 1.0);
+    assert!(lobes[0].direction.dot(&normal) < 0.0);
+}
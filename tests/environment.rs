@@ -0,0 +1,153 @@
+extern crate rust_tracer;
+
+use std::f64::consts::PI;
+use std::path::Path;
+
+use rust_tracer::environment::{load_environment, parse_pfm, parse_rgbe, EnvironmentError, EnvironmentMap};
+use rust_tracer::sampler::Sampler;
+use rust_tracer::vec3::Vec3;
+
+fn sky() -> EnvironmentMap {
+    load_environment(Path::new("tests/fixtures/sky.hdr")).expect("valid environment")
+}
+
+// Same angles as the map: theta from straight up (-y), phi from -z towards +x
+fn direction(theta_degrees: f64, phi_degrees: f64) -> Vec3 {
+    let (theta, phi) = (theta_degrees.to_radians(), phi_degrees.to_radians());
+    Vec3::new(theta.sin() * phi.sin(), -theta.cos(), -theta.sin() * phi.cos())
+}
+
+fn close(a: &Vec3, b: &Vec3, tolerance: f64) -> bool {
+    (a.x - b.x).abs() <= tolerance && (a.y - b.y).abs() <= tolerance && (a.z - b.z).abs() <= tolerance
+}
+
+#[test]
+fn loads_run_length_encoded_hdr() {
+    let sky = sky();
+
+    assert_eq!((sky.width, sky.height), (64, 32));
+    // RGBE keeps about 1% of precision
+    assert!(close(&sky.pixels[31 * 64], &Vec3::new(0.3, 0.24, 0.17), 0.005));
+    assert!(close(&sky.radiance(&direction(40.0, 135.0)), &Vec3::new(60.0, 55.0, 45.0), 0.5));
+    assert!(close(&sky.radiance(&Vec3::new(0.0, 1.0, 0.0)), &Vec3::new(0.3, 0.24, 0.17), 0.005));
+}
+
+#[test]
+fn loads_pfm_bottom_row_last() {
+    let pfm = load_environment(Path::new("tests/fixtures/tiny.pfm")).expect("valid environment");
+
+    assert_eq!((pfm.width, pfm.height), (3, 2));
+    assert!(close(&pfm.pixels[0], &Vec3::new(4.0, 4.0, 4.0), 0.0));
+    assert!(close(&pfm.pixels[2], &Vec3::new(10.0, 0.0, 0.0), 0.0));
+    assert!(close(&pfm.pixels[4], &Vec3::new(1.0, 2.0, 3.0), 0.0));
+}
+
+#[test]
+fn rejects_malformed_images() {
+    let cases: Vec<(Result<_, _>, &str)> = vec![
+        (parse_rgbe(b"P6\n"), "not a Radiance HDR file"),
+        (parse_rgbe(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"), "unsupported FORMAT=32-bit_rle_xyze"),
+        (parse_rgbe(b"#?RADIANCE\n\n+Y 1 +X 1\n"), "unsupported resolution line +Y 1 +X 1"),
+        (parse_rgbe(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x80"), "truncated pixel data"),
+        (parse_rgbe(b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x02\x02\x86\xa0"), "truncated pixel data"),
+        (parse_rgbe(b"#?RADIANCE\n\n-Y 4294967296 +X 4294967296\n"), "image too large"),
+        (parse_pfm(b"P6 1 1 255\n"), "not a PFM file"),
+        (parse_pfm(b"PF\n2 2\n-1.0\n\0\0\0\0"), "truncated pixel data"),
+        (parse_pfm(b"PF\n4294967296 4294967296\n-1.0\n"), "image too large"),
+    ];
+
+    for (result, expected) in cases {
+        match result {
+            Err(EnvironmentError::Format(message)) => assert_eq!(message, expected),
+            other => panic!("expected {:?}, got {:?}", expected, other.map(|(width, height, _)| (width, height))),
+        }
+    }
+
+    match EnvironmentMap::new(2, 2, vec![Vec3::new(1.0, 1.0, 1.0)]) {
+        Err(EnvironmentError::Format(message)) => assert_eq!(message, "environment needs width * height pixels"),
+        other => panic!("expected a missing pixels error, got {:?}", other),
+    }
+}
+
+#[test]
+fn rotation_turns_the_map_around_the_vertical() {
+    let mut rotated = sky();
+    rotated.rotation = 90.0;
+    let sky = sky();
+
+    for &(theta, phi) in &[(40.0, 135.0), (20.0, 10.0), (120.0, 300.0)] {
+        assert!(close(&rotated.radiance(&direction(theta, phi + 90.0)), &sky.radiance(&direction(theta, phi)), 1e-9));
+    }
+}
+
+#[test]
+fn pdf_integrates_to_one() {
+    let sky = sky();
+    let steps = 400;
+    let mut total = 0.0;
+
+    for i in 0..steps {
+        let theta = PI * (i as f64 + 0.5) / steps as f64;
+        for j in 0..2 * steps {
+            let phi = PI * (j as f64 + 0.5) / steps as f64;
+            let area = theta.sin() * (PI / steps as f64) * (PI / steps as f64);
+            total += sky.pdf(&direction(theta.to_degrees(), phi.to_degrees())) * area;
+        }
+    }
+
+    assert!((total - 1.0).abs() < 0.01, "pdf integrates to {}", total);
+}
+
+#[test]
+fn importance_sampling_favours_the_sun_without_bias() {
+    let sky = sky();
+    let mut sampler = Sampler::new(1, 2, 3);
+    let sun = direction(40.0, 135.0);
+    let count = 20000;
+    let mut towards_sun = 0;
+    let mut estimate = Vec3::new(0.0, 0.0, 0.0);
+
+    for _ in 0..count {
+        let sample = sky.sample(sampler.next_f64(), sampler.next_f64()).expect("sky has light");
+        assert!((sample.pdf - sky.pdf(&sample.direction)).abs() <= 1e-9 * sample.pdf);
+        if sample.direction.dot(&sun) > 10.0_f64.to_radians().cos() {
+            towards_sun += 1;
+        }
+        estimate = estimate + sample.radiance * (1.0 / (sample.pdf * count as f64));
+    }
+
+    // Reference: the same integral over a fine grid of directions
+    let steps = 400;
+    let mut reference = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..steps {
+        let theta = PI * (i as f64 + 0.5) / steps as f64;
+        for j in 0..2 * steps {
+            let phi = PI * (j as f64 + 0.5) / steps as f64;
+            let area = theta.sin() * (PI / steps as f64) * (PI / steps as f64);
+            reference = reference + sky.radiance(&direction(theta.to_degrees(), phi.to_degrees())) * area;
+        }
+    }
+
+    // The sun covers under 1% of the sphere
+    assert!(towards_sun as f64 / count as f64 > 0.3, "{} samples towards the sun", towards_sun);
+    assert!(close(&estimate, &reference, 0.03 * reference.max_component()), "{:?} vs {:?}", estimate, reference);
+}
+
+// NaN and infinite pixels go black instead of breaking importance sampling
+#[test]
+fn broken_pixels_go_black() {
+    let pixels = vec![
+        Vec3::new(f64::NAN, 1.0, 1.0),
+        Vec3::new(1.0, f64::INFINITY, 1.0),
+        Vec3::new(1.0, 1.0, 1.0),
+        Vec3::new(0.5, 0.5, 0.5),
+    ];
+    let map = EnvironmentMap::new(2, 2, pixels).expect("valid map");
+    assert_eq!((map.pixels[0].x, map.pixels[1].y, map.pixels[2].z), (0.0, 0.0, 1.0));
+
+    let mut sampler = Sampler::new(0, 0, 0);
+    for _ in 0..100 {
+        let sample = map.sample(sampler.next_f64(), sampler.next_f64()).expect("map has light");
+        assert!(sample.pdf.is_finite() && sample.radiance.max_component().is_finite());
+    }
+}
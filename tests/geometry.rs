@@ -0,0 +1,158 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::material::Lambertian;
+use rust_tracer::obj::{parse_obj, ObjError};
+use rust_tracer::ray::{intersect_mesh, intersect_quad, intersect_triangle, Ray, INFINITY};
+use rust_tracer::scene::{Mesh, Quad, Sphere};
+use rust_tracer::vec3::Vec3;
+
+fn grey() -> Arc<Lambertian> {
+    Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) })
+}
+
+fn ray_down_z(x: f64, y: f64) -> Ray {
+    Ray {
+        origin: Vec3::new(x, y, 5.0),
+        direction: Vec3::new(0.0, 0.0, -1.0),
+    }
+}
+
+fn unit_quad() -> Quad {
+    Quad {
+        corner: Vec3::new(0.0, 0.0, 0.0),
+        edge_u: Vec3::new(1.0, 0.0, 0.0),
+        edge_v: Vec3::new(0.0, 2.0, 0.0),
+        material: grey(),
+    }
+}
+
+#[test]
+fn quad_is_hit_only_inside_its_edges() {
+    let quad = unit_quad();
+
+    let intersection = intersect_quad(&ray_down_z(0.5, 1.5), &quad).ok().expect("inside the quad");
+    assert!((intersection.t - 5.0).abs() < 1e-12);
+    assert!((intersection.normal.z - 1.0).abs() < 1e-12);
+
+    assert!(intersect_quad(&ray_down_z(1.5, 1.0), &quad).is_err());
+    assert!(intersect_quad(&ray_down_z(0.5, -0.1), &quad).is_err());
+}
+
+#[test]
+fn triangle_is_hit_from_both_sides() {
+    let a = Vec3::new(0.0, 0.0, 0.0);
+    let b = Vec3::new(1.0, 0.0, 0.0);
+    let c = Vec3::new(0.0, 1.0, 0.0);
+
+    assert_eq!(intersect_triangle(&ray_down_z(0.2, 0.2), &a, &b, &c), Some(5.0));
+    assert_eq!(intersect_triangle(&ray_down_z(0.6, 0.6), &a, &b, &c), None);
+
+    let from_below = Ray {
+        origin: Vec3::new(0.2, 0.2, -1.0),
+        direction: Vec3::new(0.0, 0.0, 1.0),
+    };
+    assert_eq!(intersect_triangle(&from_below, &a, &b, &c), Some(1.0));
+}
+
+#[test]
+fn mesh_reports_the_closest_triangle() {
+    // Two parallel triangles, the ray has to pick the nearer one
+    let vertices = vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(0.0, 1.0, 1.0),
+    ];
+    let mesh = Mesh::new(vertices, vec![[0, 1, 2], [3, 4, 5]], grey()).expect("valid mesh");
+
+    let intersection = intersect_mesh(&ray_down_z(0.2, 0.2), &mesh, INFINITY).ok().expect("ray through both triangles");
+    assert!((intersection.t - 4.0).abs() < 1e-12);
+    assert!(intersect_mesh(&ray_down_z(0.2, 0.2), &mesh, 3.0).is_err());
+    assert!(intersect_mesh(&ray_down_z(2.0, 2.0), &mesh, INFINITY).is_err());
+}
+
+#[test]
+fn surface_samples_lie_on_the_shape_with_area_pdf() {
+    let sphere = Sphere {
+        position: Vec3::new(1.0, 2.0, 3.0),
+        radius: 0.5,
+        material: grey(),
+    };
+    let quad = unit_quad();
+    let mesh = Mesh::new(
+        vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 1.0)],
+        vec![[0, 1, 2], [0, 1, 3]],
+        grey(),
+    )
+    .expect("valid mesh");
+    assert!((mesh.area() - 3.0).abs() < 1e-12);
+
+    for i in 0..100 {
+        let u1 = (i as f64 + 0.5) / 100.0;
+        let u2 = ((i * 37) % 100) as f64 / 100.0;
+
+        let sample = sphere.sample_surface(u1, u2);
+        assert!(((&sample.point - &sphere.position).mag() - 0.5).abs() < 1e-9);
+        assert!((sample.pdf - 1.0 / std::f64::consts::PI).abs() < 1e-9);
+
+        let sample = quad.sample_surface(u1, u2);
+        assert!(sample.point.z.abs() < 1e-12 && sample.point.x <= 1.0 && sample.point.y <= 2.0);
+        assert!((sample.pdf - 0.5).abs() < 1e-12);
+
+        // Each point must be in the plane of one of the two triangles
+        let sample = mesh.sample_surface(u1, u2);
+        assert!(sample.point.z.abs() < 1e-12 || sample.point.y.abs() < 1e-12);
+        assert!((sample.pdf - 1.0 / 3.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn obj_faces_become_triangle_fans() {
+    let obj = parse_obj("
+        # a quad and a triangle
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vn 0 0 1
+        f 1//1 2//1 3//1 4//1
+        f -1 -2 -3
+    ").expect("valid obj");
+
+    assert_eq!(obj.vertices.len(), 4);
+    assert_eq!(obj.triangles, vec![[0, 1, 2], [0, 2, 3], [3, 2, 1]]);
+
+    assert!(parse_obj("v 0 0 0\nf 1 2 3").is_err());
+    assert!(parse_obj("v 0 0 inf\nv 1 0 0\nv 0 1 0\nf 1 2 3").is_err());
+}
+
+// Emissive meshes get points picked on their faces, there have to be some
+#[test]
+fn obj_without_faces_is_an_error() {
+    match parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0") {
+        Err(ObjError::NoFaces) => {}
+        other => panic!("expected no faces, got {:?}", other),
+    }
+}
+
+// Nothing a glowing mesh could pick points on
+#[test]
+fn meshes_need_triangles_with_area() {
+    let vertices = || vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)];
+    let cases = [
+        (Mesh::new(vertices(), Vec::new(), grey()), "no triangles"),
+        (Mesh::new(vertices(), vec![[0, 1, 3]], grey()), "triangle uses missing vertex 3"),
+        (Mesh::new(vertices(), vec![[0, 1, 2]], grey()), "no area"),
+    ];
+
+    for &(ref mesh, expected) in cases.iter() {
+        match *mesh {
+            Err(ref err) => assert_eq!(err.to_string(), expected),
+            Ok(_) => panic!("expected {}, got a mesh", expected),
+        }
+    }
+}
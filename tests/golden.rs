@@ -0,0 +1,670 @@
+// Golden-image regression tests.
+//
+// Each scene is rendered at a low resolution and compared against the reference in
+// tests/golden/. On failure the rendered image and a diff heat map are written next
+// to the test binary's temp dir. After an intentional change to the renderer, run
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden
+//
+// to re-bless the references.
+extern crate rust_tracer;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rust_tracer::camera::{create_camera, Camera};
+use rust_tracer::environment::load_environment;
+use rust_tracer::film::Film;
+use rust_tracer::image::{parse_ppm, Color, Image};
+use rust_tracer::integrator::{Bidirectional, Integrator, PathTracer, PhotonMapper, Whitted};
+use rust_tracer::render::{render, CancelToken, RenderSettings, Screen};
+use rust_tracer::occlusion::AmbientOcclusion;
+use rust_tracer::material::{Dielectric, Emissive, Lambertian, Metal, Mirror, Plastic};
+use rust_tracer::light::{AreaLight, AreaShape, DirectionalLight, Light, PointLight, SpotLight};
+use rust_tracer::scene::{create_scene, Background, Mesh, Plane, Quad, Scene, Sphere};
+use rust_tracer::scene_file::load_scene;
+use rust_tracer::sky::Sky;
+use rust_tracer::vec3::Vec3;
+
+const WIDTH: u32 = 80;
+const HEIGHT: u32 = 60;
+const SAMPLES_PER_PIXEL: u32 = 4;
+// Path tracing is too noisy at 4 to show anything
+const PATH_TRACED_SAMPLES_PER_PIXEL: u32 = 16;
+
+// Per-pixel perceptual distance above which a pixel counts as different
+const PIXEL_THRESHOLD: f64 = 0.1;
+// Fraction of pixels allowed over the threshold (float noise on silhouettes)
+const MAX_DIFFERENT_FRACTION: f64 = 0.01;
+// Mean distance over the whole image, catches subtle global shifts
+const MAX_MEAN_DISTANCE: f64 = 0.01;
+
+fn screen() -> Screen {
+    Screen { width: WIDTH, height: HEIGHT }
+}
+
+fn demo_camera(screen: &Screen) -> Camera {
+    let aspect_ratio = screen.width as f64 / screen.height as f64;
+    create_camera(
+        Vec3::new(0.0, -0.6, 2.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        45.0,
+        aspect_ratio,
+        Vec3::new(0.0, 1.0, 0.0),
+    )
+}
+
+fn matte(color: Vec3) -> Arc<Lambertian> {
+    Arc::new(Lambertian { albedo: color })
+}
+
+fn spheres_on_floor_scene() -> Scene {
+    Scene {
+        lights: vec![Light::Point(PointLight {
+            position: Vec3::new(1.5, -4.0, 1.0),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 75.0,
+            profile: None,
+        })],
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.6, 1.0, -1.5),
+                radius: 0.5,
+                material: matte(Vec3::new(0.2, 0.3, 0.9)),
+            },
+            Sphere {
+                position: Vec3::new(0.7, 1.1, -1.8),
+                radius: 0.4,
+                material: Arc::new(Mirror { reflectance: Vec3::new(0.8, 0.8, 0.8) }),
+            },
+        ],
+        planes: vec![Plane {
+            position: Vec3::new(0.0, 1.5, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: matte(Vec3::new(0.4, 0.4, 0.4)),
+        }],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// Thick tinted glass next to the clear presets, against a bright wall so the tint shows
+fn absorbing_glass_scene() -> Scene {
+    Scene {
+        lights: vec![Light::Point(PointLight {
+            position: Vec3::new(0.0, -3.0, 2.0),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 100.0,
+            profile: None,
+        })],
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.7, 0.9, -1.6),
+                radius: 0.6,
+                material: Arc::new(Dielectric { absorption: Vec3::new(0.2, 1.5, 2.5), ..Dielectric::glass() }),
+            },
+            Sphere {
+                position: Vec3::new(0.6, 1.1, -1.4),
+                radius: 0.4,
+                material: Arc::new(Dielectric::diamond()),
+            },
+            Sphere {
+                position: Vec3::new(0.2, 1.25, -0.6),
+                radius: 0.25,
+                material: Arc::new(Dielectric { absorption: Vec3::new(3.0, 0.8, 0.3), ..Dielectric::water() }),
+            },
+        ],
+        planes: vec![
+            Plane {
+                position: Vec3::new(0.0, 1.5, 0.0),
+                normal: Vec3::new(0.0, -1.0, 0.0),
+                material: matte(Vec3::new(0.5, 0.5, 0.5)),
+            },
+            Plane {
+                position: Vec3::new(0.0, 0.0, -3.0),
+                normal: Vec3::new(0.0, 0.0, 1.0),
+                material: matte(Vec3::new(0.9, 0.9, 0.9)),
+            },
+        ],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// Roughness increasing left to right, plastic on the top row and gold below
+fn microfacet_scene() -> Scene {
+    let mut spheres = Vec::new();
+
+    for i in 0..5 {
+        let roughness = 0.1 + 0.2 * i as f64;
+        let x = -1.0 + 0.5 * i as f64;
+
+        spheres.push(Sphere {
+            position: Vec3::new(x, 0.35, -1.5),
+            radius: 0.22,
+            material: Arc::new(Plastic::new(Vec3::new(0.8, 0.1, 0.1), roughness)),
+        });
+        spheres.push(Sphere {
+            position: Vec3::new(x, 0.95, -1.5),
+            radius: 0.22,
+            material: Arc::new(Metal::gold(roughness)),
+        });
+    }
+
+    Scene {
+        lights: vec![
+            Light::Point(PointLight {
+                position: Vec3::new(-2.0, -3.0, 2.0),
+                color: Vec3::new(1.0, 1.0, 1.0),
+                force: 70.0,
+                profile: None,
+            }),
+            Light::Point(PointLight {
+                position: Vec3::new(2.0, -1.0, 1.0),
+                color: Vec3::new(0.6, 0.7, 1.0),
+                force: 40.0,
+                profile: None,
+            }),
+        ],
+        spheres,
+        planes: vec![Plane {
+            position: Vec3::new(0.0, 1.3, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: matte(Vec3::new(0.3, 0.3, 0.3)),
+        }],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// Lit only by glowing geometry: a ceiling panel, a small sphere and a triangle
+// mesh, next to a matte pyramid mesh
+fn emissive_geometry_scene() -> Scene {
+    let pyramid = vec![
+        Vec3::new(-0.9, 1.5, -1.2),
+        Vec3::new(-0.1, 1.5, -1.2),
+        Vec3::new(-0.1, 1.5, -2.0),
+        Vec3::new(-0.9, 1.5, -2.0),
+        Vec3::new(-0.5, 0.7, -1.6),
+    ];
+    let pyramid_faces = vec![[0, 1, 4], [1, 2, 4], [2, 3, 4], [3, 0, 4]];
+
+    let panel = vec![
+        Vec3::new(0.9, 0.3, -2.4),
+        Vec3::new(1.5, 0.3, -2.4),
+        Vec3::new(1.2, -0.4, -2.4),
+    ];
+
+    Scene {
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(0.6, 1.2, -1.4),
+                radius: 0.3,
+                material: matte(Vec3::new(0.8, 0.8, 0.8)),
+            },
+            Sphere {
+                position: Vec3::new(0.0, 1.35, -0.9),
+                radius: 0.15,
+                material: Arc::new(Emissive { radiance: Vec3::new(4.0, 1.5, 0.5) }),
+            },
+        ],
+        planes: vec![
+            Plane {
+                position: Vec3::new(0.0, 1.5, 0.0),
+                normal: Vec3::new(0.0, -1.0, 0.0),
+                material: matte(Vec3::new(0.6, 0.6, 0.6)),
+            },
+            Plane {
+                position: Vec3::new(0.0, 0.0, -2.5),
+                normal: Vec3::new(0.0, 0.0, 1.0),
+                material: matte(Vec3::new(0.6, 0.6, 0.6)),
+            },
+        ],
+        quads: vec![Quad {
+            corner: Vec3::new(-0.6, -0.6, -1.8),
+            edge_u: Vec3::new(0.0, 0.0, 0.8),
+            edge_v: Vec3::new(1.2, 0.0, 0.0),
+            material: Arc::new(Emissive { radiance: Vec3::new(3.0, 3.0, 3.0) }),
+        }],
+        meshes: vec![
+            Mesh::new(pyramid, pyramid_faces, matte(Vec3::new(0.2, 0.6, 0.3))).expect("valid pyramid"),
+            Mesh::new(panel, vec![[0, 2, 1]], Arc::new(Emissive { radiance: Vec3::new(0.5, 1.0, 4.0) })).expect("valid panel"),
+        ],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// One light of each area shape over a row of spheres, each casting a penumbra
+fn soft_shadows_scene() -> Scene {
+    let red = Arc::new(Plastic::new(Vec3::new(0.8, 0.2, 0.2), 0.3));
+    let spheres = [-0.8, 0.0, 0.8]
+        .iter()
+        .map(|&x| Sphere {
+            position: Vec3::new(x, 1.1, -1.5),
+            radius: 0.4,
+            material: red.clone(),
+        })
+        .collect();
+
+    Scene {
+        lights: vec![
+            Light::Area(AreaLight {
+                position: Vec3::new(-1.2, -1.5, -1.0),
+                color: Vec3::new(1.0, 0.8, 0.6),
+                force: 12.0,
+                shape: AreaShape::Rectangle { edge_u: Vec3::new(1.0, 0.0, 0.0), edge_v: Vec3::new(0.0, 0.0, -1.0) },
+                samples: 16,
+            }),
+            Light::Area(AreaLight {
+                position: Vec3::new(0.6, -1.8, -0.5),
+                color: Vec3::new(0.6, 0.8, 1.0),
+                force: 12.0,
+                shape: AreaShape::Sphere { radius: 0.4 },
+                samples: 16,
+            }),
+            Light::Area(AreaLight {
+                position: Vec3::new(1.5, -1.2, -1.0),
+                color: Vec3::new(0.8, 1.0, 0.8),
+                force: 10.0,
+                shape: AreaShape::Disk { normal: Vec3::new(-0.5, 1.0, 0.0), radius: 0.5 },
+                samples: 16,
+            }),
+        ],
+        spheres,
+        planes: vec![
+            Plane {
+                position: Vec3::new(0.0, 1.5, 0.0),
+                normal: Vec3::new(0.0, -1.0, 0.0),
+                material: matte(Vec3::new(0.6, 0.6, 0.6)),
+            },
+            Plane {
+                position: Vec3::new(0.0, 0.0, -2.5),
+                normal: Vec3::new(0.0, 0.0, 1.0),
+                material: matte(Vec3::new(0.6, 0.6, 0.6)),
+            },
+        ],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// A low sun casting long parallel shadows and a narrow spot on the back wall
+fn sun_and_spot_scene() -> Scene {
+    Scene {
+        lights: vec![
+            Light::Directional(DirectionalLight {
+                direction: Vec3::new(1.0, 0.6, -0.4).unit(),
+                color: Vec3::new(1.0, 0.9, 0.75),
+                strength: 4.0,
+            }),
+            Light::Spot(SpotLight {
+                position: Vec3::new(-0.5, -1.0, 1.0),
+                direction: Vec3::new(0.3, 0.45, -1.0).unit(),
+                color: Vec3::new(0.5, 0.7, 1.0),
+                force: 20.0,
+                cone_angle: 12.0,
+                falloff: 8.0,
+                profile: None,
+            }),
+        ],
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.7, 1.1, -1.5),
+                radius: 0.4,
+                material: matte(Vec3::new(0.8, 0.8, 0.8)),
+            },
+            Sphere {
+                position: Vec3::new(0.4, 1.25, -1.2),
+                radius: 0.25,
+                material: Arc::new(Plastic::new(Vec3::new(0.2, 0.3, 0.8), 0.2)),
+            },
+        ],
+        planes: vec![
+            Plane {
+                position: Vec3::new(0.0, 1.5, 0.0),
+                normal: Vec3::new(0.0, -1.0, 0.0),
+                material: matte(Vec3::new(0.5, 0.5, 0.5)),
+            },
+            Plane {
+                position: Vec3::new(0.0, 0.0, -2.5),
+                normal: Vec3::new(0.0, 0.0, 1.0),
+                material: matte(Vec3::new(0.5, 0.5, 0.5)),
+            },
+        ],
+        background: Background::default(),
+        ..Scene::default()
+    }
+}
+
+// No lights, only the sky map with its sun behind the spheres, seen above the horizon and in the mirror
+fn environment_lighting_scene() -> Scene {
+    let mut sky = load_environment(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sky.hdr"))
+        .expect("couldn't load the sky fixture");
+    sky.rotation = 180.0;
+    sky.samples = 8;
+
+    Scene {
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.6, 0.6, -1.0),
+                radius: 0.4,
+                material: matte(Vec3::new(0.8, 0.8, 0.8)),
+            },
+            Sphere {
+                position: Vec3::new(0.5, 0.6, -1.3),
+                radius: 0.4,
+                material: Arc::new(Mirror { reflectance: Vec3::new(0.9, 0.9, 0.9) }),
+            },
+        ],
+        planes: vec![Plane {
+            position: Vec3::new(0.0, 1.0, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: matte(Vec3::new(0.5, 0.5, 0.5)),
+        }],
+        background: Background::Environment(sky),
+        ..Scene::default()
+    }
+}
+
+// Midday sky with its sun to the back left, a clear day's shadows and blue fill
+fn sky_and_sun_scene() -> Scene {
+    let sky = Sky::new(Vec3::new(-1.0, -2.0, -1.5), 3.0, Vec3::new(0.3, 0.3, 0.3));
+
+    Scene {
+        lights: vec![Light::Directional(sky.sun_light())],
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.6, 0.6, -1.0),
+                radius: 0.4,
+                material: matte(Vec3::new(0.8, 0.8, 0.8)),
+            },
+            Sphere {
+                position: Vec3::new(0.5, 0.6, -1.3),
+                radius: 0.4,
+                material: Arc::new(Mirror { reflectance: Vec3::new(0.9, 0.9, 0.9) }),
+            },
+        ],
+        planes: vec![Plane {
+            position: Vec3::new(0.0, 1.0, 0.0),
+            normal: Vec3::new(0.0, -1.0, 0.0),
+            material: matte(Vec3::new(0.5, 0.5, 0.5)),
+        }],
+        background: Background::Sky(Box::new(sky)),
+        ..Scene::default()
+    }
+}
+
+// Closed box lit only by a panel in the ceiling, the walls bleed their colour onto
+// the spheres and the ceiling is lit by the floor
+fn cornell_box_scene() -> Scene {
+    let white = matte(Vec3::new(0.7, 0.7, 0.7));
+    let wall = |position: Vec3, normal: Vec3, material: Arc<Lambertian>| Plane { position, normal, material };
+
+    Scene {
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(-0.5, 0.55, -1.4),
+                radius: 0.45,
+                material: white.clone(),
+            },
+            Sphere {
+                position: Vec3::new(0.55, 0.6, -0.8),
+                radius: 0.4,
+                material: Arc::new(Dielectric::glass()),
+            },
+        ],
+        planes: vec![
+            wall(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), white.clone()),
+            wall(Vec3::new(0.0, -1.4, 0.0), Vec3::new(0.0, 1.0, 0.0), white.clone()),
+            wall(Vec3::new(0.0, 0.0, -2.2), Vec3::new(0.0, 0.0, 1.0), white.clone()),
+            wall(Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0), white),
+            wall(Vec3::new(-1.2, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), matte(Vec3::new(0.7, 0.15, 0.1))),
+            wall(Vec3::new(1.2, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), matte(Vec3::new(0.15, 0.6, 0.15))),
+        ],
+        quads: vec![Quad {
+            corner: Vec3::new(-0.4, -1.39, -1.4),
+            edge_u: Vec3::new(0.0, 0.0, 0.8),
+            edge_v: Vec3::new(0.8, 0.0, 0.0),
+            material: Arc::new(Emissive { radiance: Vec3::new(15.0, 14.0, 12.0) }),
+        }],
+        ..Scene::default()
+    }
+}
+
+fn render_scene(scene: &Scene) -> Image {
+    let screen = screen();
+    render_scene_from(scene, &demo_camera(&screen))
+}
+
+fn render_scene_from(scene: &Scene, camera: &Camera) -> Image {
+    render_scene_with(scene, camera, Box::new(Whitted::default()), SAMPLES_PER_PIXEL)
+}
+
+fn render_scene_with(scene: &Scene, camera: &Camera, integrator: Box<dyn Integrator>, samples_per_pixel: u32) -> Image {
+    let screen = screen();
+    let mut settings = RenderSettings {
+        integrator,
+        samples_per_pixel,
+        seed: 0,
+        checkpoint: None,
+    };
+    let mut film = Film::new(screen.width, screen.height);
+
+    render(scene, camera, &screen, &mut settings, &mut film, &CancelToken::new(), |_| {})
+        .expect("render without checkpoint can't fail");
+
+    // Round-trip through the 8 bit file format so both sides are quantized the same way
+    parse_ppm(&film.to_image().to_ppm()).expect("renderer wrote an unreadable ppm")
+}
+
+// One of the example scenes in scenes/, with the camera it sets framing the test screen
+fn load_scene_file(name: &str) -> (Scene, Camera) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenes").join(format!("{}.scene", name));
+    let scene_file = load_scene(&path).unwrap_or_else(|error| panic!("couldn't load {}: {}", path.display(), error));
+    let view = scene_file.view.unwrap_or_else(|| panic!("{} doesn't set its camera", path.display()));
+
+    let screen = screen();
+    let aspect_ratio = screen.width as f64 / screen.height as f64;
+    let camera = create_camera(view.origin, view.target, view.fov, aspect_ratio, view.up);
+    (scene_file.scene, camera)
+}
+
+// "Redmean" weighted RGB distance, a cheap approximation of perceived colour
+// difference. Normalized so black vs white is 1.0.
+fn perceptual_distance(a: &Color, b: &Color) -> f64 {
+    let mean_r = (a.r + b.r) * 0.5;
+    let dr = a.r - b.r;
+    let dg = a.g - b.g;
+    let db = a.b - b.b;
+    let distance_sq = (2.0 + mean_r) * dr * dr + 4.0 * dg * dg + (3.0 - mean_r) * db * db;
+    (distance_sq / 9.0).sqrt()
+}
+
+fn diff_image(actual: &Image, expected: &Image) -> Image {
+    let mut diff = Image::new(actual.width, actual.height);
+
+    for (pixel, (a, e)) in diff.pixels.iter_mut().zip(actual.pixels.iter().zip(&expected.pixels)) {
+        // Amplified so small differences are still visible, red where over the threshold
+        let distance = (perceptual_distance(a, e) * 10.0).min(1.0);
+        *pixel = if perceptual_distance(a, e) > PIXEL_THRESHOLD {
+            Color { r: 1.0, g: 0.0, b: 0.0 }
+        } else {
+            Color { r: distance, g: distance, b: distance }
+        };
+    }
+
+    diff
+}
+
+fn assert_matches_golden(name: &str, actual: &Image) {
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    let golden_path = golden_dir.join(format!("{}.ppm", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, actual.to_ppm()).expect("couldn't write golden image");
+        return;
+    }
+
+    let expected_ppm = fs::read_to_string(&golden_path)
+        .unwrap_or_else(|_| panic!("missing golden image {}, run with UPDATE_GOLDEN=1", golden_path.display()));
+    let expected = parse_ppm(&expected_ppm).expect("golden image is not a valid ppm");
+
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "{}: resolution differs from golden image",
+        name
+    );
+
+    let distances: Vec<f64> = actual.pixels.iter().zip(&expected.pixels)
+        .map(|(a, e)| perceptual_distance(a, e))
+        .collect();
+    let different = distances.iter().filter(|&&d| d > PIXEL_THRESHOLD).count();
+    let different_fraction = different as f64 / distances.len() as f64;
+    let mean_distance = distances.iter().sum::<f64>() / distances.len() as f64;
+
+    if different_fraction > MAX_DIFFERENT_FRACTION || mean_distance > MAX_MEAN_DISTANCE {
+        let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&out_dir).expect("couldn't create diff output dir");
+        let actual_path = out_dir.join(format!("{}.actual.ppm", name));
+        let diff_path = out_dir.join(format!("{}.diff.ppm", name));
+        fs::write(&actual_path, actual.to_ppm()).expect("couldn't write actual image");
+        fs::write(&diff_path, diff_image(actual, &expected).to_ppm()).expect("couldn't write diff image");
+
+        panic!(
+            "{}: {} pixels ({:.2}%) differ, mean distance {:.4}\n  actual: {}\n  diff:   {}",
+            name,
+            different,
+            different_fraction * 100.0,
+            mean_distance,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn demo_scene() {
+    assert_matches_golden("demo", &render_scene(&create_scene()));
+}
+
+#[test]
+fn ambient_occlusion() {
+    let image = render_scene_with(&create_scene(), &demo_camera(&screen()), Box::new(AmbientOcclusion::default()), SAMPLES_PER_PIXEL);
+    assert_matches_golden("ambient_occlusion", &image);
+}
+
+#[test]
+fn spheres_on_floor() {
+    assert_matches_golden("spheres_on_floor", &render_scene(&spheres_on_floor_scene()));
+}
+
+#[test]
+fn absorbing_glass() {
+    assert_matches_golden("absorbing_glass", &render_scene(&absorbing_glass_scene()));
+}
+
+#[test]
+fn microfacet_roughness_and_metalness() {
+    assert_matches_golden("microfacet", &render_scene(&microfacet_scene()));
+}
+
+#[test]
+fn emissive_geometry() {
+    assert_matches_golden("emissive_geometry", &render_scene(&emissive_geometry_scene()));
+}
+
+#[test]
+fn soft_shadows() {
+    assert_matches_golden("soft_shadows", &render_scene(&soft_shadows_scene()));
+}
+
+#[test]
+fn sun_and_spot_lights() {
+    assert_matches_golden("sun_and_spot", &render_scene(&sun_and_spot_scene()));
+}
+
+#[test]
+fn environment_lighting() {
+    assert_matches_golden("environment_lighting", &render_scene(&environment_lighting_scene()));
+}
+
+#[test]
+fn sky_and_sun() {
+    assert_matches_golden("sky_and_sun", &render_scene(&sky_and_sun_scene()));
+}
+
+#[test]
+fn path_traced_cornell_box() {
+    let image = render_scene_with(&cornell_box_scene(), &demo_camera(&screen()), Box::new(PathTracer), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("path_traced_cornell_box", &image);
+}
+
+#[test]
+fn bidirectional_cornell_box() {
+    let image = render_scene_with(&cornell_box_scene(), &demo_camera(&screen()), Box::new(Bidirectional), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("bidirectional_cornell_box", &image);
+}
+
+#[test]
+fn principled_material_grid() {
+    let (scene, camera) = load_scene_file("material_grid");
+    assert_matches_golden("material_grid", &render_scene_from(&scene, &camera));
+}
+
+#[test]
+fn veach_plates() {
+    let (scene, camera) = load_scene_file("veach_plates");
+
+    let image = render_scene_with(&scene, &camera, Box::new(PathTracer), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("veach_plates", &image);
+}
+
+#[test]
+fn photon_mapped_caustics() {
+    let (scene, camera) = load_scene_file("caustics");
+
+    // Fewer photons than by default, the image is small
+    let integrator = Box::new(PhotonMapper::new(20_000, 50_000, 0.2, 0.05));
+    let image = render_scene_with(&scene, &camera, integrator, PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("photon_mapped_caustics", &image);
+}
+
+#[test]
+fn light_shafts() {
+    let (scene, camera) = load_scene_file("light_shafts");
+
+    let image = render_scene_with(&scene, &camera, Box::new(PathTracer), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("light_shafts", &image);
+
+    // The same fog, the cheap way
+    assert_matches_golden("whitted_fog", &render_scene_from(&scene, &camera));
+}
+
+#[test]
+fn smoke_grid() {
+    let (scene, camera) = load_scene_file("smoke");
+
+    let image = render_scene_with(&scene, &camera, Box::new(PathTracer), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("smoke_grid", &image);
+}
+
+#[test]
+fn subsurface_spheres() {
+    let (scene, camera) = load_scene_file("subsurface");
+
+    let image = render_scene_with(&scene, &camera, Box::new(PathTracer), PATH_TRACED_SAMPLES_PER_PIXEL);
+    assert_matches_golden("subsurface_spheres", &image);
+}
+
+#[test]
+fn identical_images_have_no_distance() {
+    let white = Color { r: 1.0, g: 1.0, b: 1.0 };
+    let black = Color { r: 0.0, g: 0.0, b: 0.0 };
+
+    assert_eq!(perceptual_distance(&white, &white), 0.0);
+    assert!((perceptual_distance(&white, &black) - 1.0).abs() < 1e-9);
+}
@@ -0,0 +1,178 @@
+extern crate rust_tracer;
+
+mod common;
+
+use rust_tracer::grid_volume::{grid_transmittance, parse_density_grid, DensityGrid, GridError, GridVolume};
+use rust_tracer::lighting::{shadow_transmittance, Mis};
+use rust_tracer::medium::Medium;
+use rust_tracer::path_tracer::path_radiance;
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::Scene;
+use rust_tracer::vec3::Vec3;
+
+use common::lit_floor;
+
+fn grid_bytes(width: u32, height: u32, depth: u32, densities: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for &size in [width, height, depth].iter() {
+        bytes.extend_from_slice(&size.to_le_bytes());
+    }
+    for density in densities {
+        bytes.extend_from_slice(&density.to_le_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn parses_raw_grids() {
+    let grid = parse_density_grid(&grid_bytes(2, 1, 3, &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0])).expect("valid grid");
+    assert_eq!((grid.width, grid.height, grid.depth), (2, 1, 3));
+    assert_eq!(grid.densities[3], 3.0);
+    assert_eq!(grid.max_density(), 5.0);
+
+    let cases: [(Vec<u8>, &str); 6] = [
+        (vec![2, 0, 0], "truncated grid header"),
+        (grid_bytes(0, 1, 1, &[]), "empty grid"),
+        (grid_bytes(2, 2, 2, &[1.0; 7]), "truncated voxel data"),
+        (grid_bytes(u32::MAX, u32::MAX, u32::MAX, &[]), "grid too large"),
+        (grid_bytes(1, 1, 2, &[1.0, -1.0]), "negative or invalid density"),
+        (grid_bytes(1, 1, 2, &[1.0, f32::INFINITY]), "negative or invalid density"),
+    ];
+    for &(ref bytes, expected) in cases.iter() {
+        match parse_density_grid(bytes) {
+            Err(GridError::Format(message)) => assert_eq!(message, expected),
+            other => panic!("expected {}, got {:?}", expected, other),
+        }
+    }
+
+    let grids = [
+        (DensityGrid::new(0, 0, 0, Vec::new()), "empty grid"),
+        (DensityGrid::new(2, 1, 1, vec![1.0]), "grid needs width * height * depth densities"),
+    ];
+    for &(ref grid, expected) in grids.iter() {
+        match *grid {
+            Err(GridError::Format(ref message)) => assert_eq!(message, expected),
+            ref other => panic!("expected {}, got {:?}", expected, other),
+        }
+    }
+}
+
+#[test]
+fn interpolates_between_voxel_centres() {
+    let grid = DensityGrid::new(2, 1, 1, vec![0.0, 1.0]).expect("valid grid");
+    assert_eq!(grid.density(0.1, 0.5, 0.5), 0.0);
+    assert_eq!(grid.density(0.25, 0.5, 0.5), 0.0);
+    assert_eq!(grid.density(0.5, 0.5, 0.5), 0.5);
+    assert_eq!(grid.density(0.75, 0.5, 0.5), 1.0);
+    assert_eq!(grid.density(1.0, 0.0, 1.0), 1.0);
+}
+
+// Density ramping up from 0 to 1 in the middle half of the unit box, the optical
+// depth straight across is 0.5 of `density`
+fn ramp(density: f64, albedo: f64) -> GridVolume {
+    GridVolume {
+        min: Vec3::new(0.0, 0.0, 0.0),
+        max: Vec3::new(1.0, 1.0, 1.0),
+        grid: DensityGrid::new(2, 1, 1, vec![0.0, 1.0]).expect("valid grid"),
+        density,
+        albedo: Vec3::new(albedo, albedo, albedo),
+        anisotropy: 0.0,
+    }
+}
+
+fn across() -> Ray {
+    Ray { origin: Vec3::new(-1.0, 0.5, 0.5), direction: Vec3::new(1.0, 0.0, 0.0) }
+}
+
+#[test]
+fn tracking_matches_the_optical_depth() {
+    let volume = ramp(2.0, 0.5);
+    let expected = (-1.0f64).exp();
+    let mut sampler = Sampler::new(0, 0, 0);
+    let samples = 50_000;
+
+    let mut transmittance = 0.0;
+    let mut passed = 0;
+    for _ in 0..samples {
+        transmittance += volume.transmittance(&across(), 3.0, &mut sampler);
+        if volume.sample_collision(&across(), 3.0, &mut sampler).is_none() {
+            passed += 1;
+        }
+    }
+    transmittance /= samples as f64;
+    let passed = passed as f64 / samples as f64;
+
+    assert!((transmittance - expected).abs() < 0.01, "{} vs {}", transmittance, expected);
+    assert!((passed - expected).abs() < 0.01, "{} vs {}", passed, expected);
+
+    // Stopping short of the box, or missing it
+    assert_eq!(volume.transmittance(&across(), 0.5, &mut sampler), 1.0);
+    let above = Ray { origin: Vec3::new(-1.0, -0.5, 0.5), direction: Vec3::new(1.0, 0.0, 0.0) };
+    assert!(volume.sample_collision(&above, 3.0, &mut sampler).is_none());
+}
+
+#[test]
+fn grid_volumes_cast_shadows() {
+    let scene = Scene { volumes: vec![ramp(2.0, 0.5)], ..lit_floor(Vec3::new(0.5, -2.0, 0.5), 2.0) };
+    let mut sampler = Sampler::new(1, 0, 0);
+    let samples = 20_000;
+
+    // From the floor below the box up to the light, straight through it
+    let up = Ray { origin: Vec3::new(0.5, 2.0, 0.5), direction: Vec3::new(0.0, -1.0, 0.0) };
+    let mut through_grid = 0.0;
+    let mut through_scene = 0.0;
+    for _ in 0..samples {
+        through_grid += grid_transmittance(&up, 4.0, &scene, &mut sampler);
+        through_scene += shadow_transmittance(&up, 4.0, &scene, &mut sampler).x;
+    }
+    // The ramp goes along x, straight up the density doesn't change
+    let expected = (-2.0f64 * 0.5).exp();
+    for &estimate in [through_grid, through_scene].iter() {
+        let estimate = estimate / samples as f64;
+        assert!((estimate - expected).abs() < 0.01, "{} vs {}", estimate, expected);
+    }
+}
+
+// A grid of even density reaching past the light and the floor absorbs and scatters
+// like fog of the same density, which also has to light the floor up above the
+// absorbing one
+#[test]
+fn even_grids_light_the_floor_like_fog() {
+    let ray = || Ray { origin: Vec3::new(0.5, -1.0, 0.5), direction: Vec3::new(0.0, 1.0, 0.0) };
+    let density = 0.25;
+    let samples = 50_000;
+
+    let mut brightness = Vec::new();
+    for &albedo in [0.0, 0.8].iter() {
+        let grid = GridVolume {
+            min: Vec3::new(-50.0, -50.0, -50.0),
+            max: Vec3::new(50.0, 50.0, 50.0),
+            grid: DensityGrid::new(1, 1, 1, vec![1.0]).expect("valid grid"),
+            density,
+            albedo: Vec3::new(albedo, albedo, albedo),
+            anisotropy: 0.0,
+        };
+        let fog = Medium {
+            absorption: Vec3::new(1.0, 1.0, 1.0) * ((1.0 - albedo) * density),
+            scattering: Vec3::new(1.0, 1.0, 1.0) * (albedo * density),
+            anisotropy: 0.0,
+        };
+        let scenes = [
+            Scene { volumes: vec![grid], ..lit_floor(Vec3::new(0.5, -2.0, 0.5), 2.0) },
+            Scene { medium: Some(fog), ..lit_floor(Vec3::new(0.5, -2.0, 0.5), 2.0) },
+        ];
+
+        let mut estimates = [0.0; 2];
+        for (scene, estimate) in scenes.iter().zip(estimates.iter_mut()) {
+            let mut sampler = Sampler::new(2, 0, 0);
+            for _ in 0..samples {
+                *estimate += path_radiance(ray(), scene, Mis::PowerHeuristic, &mut sampler).x;
+            }
+            *estimate /= samples as f64;
+        }
+        assert!((estimates[0] - estimates[1]).abs() < 0.05 * estimates[1], "albedo {}: {:?}", albedo, estimates);
+        brightness.push(estimates[0]);
+    }
+    assert!(brightness[1] > 2.0 * brightness[0], "{:?}", brightness);
+}
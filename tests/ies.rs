@@ -0,0 +1,94 @@
+extern crate rust_tracer;
+
+use std::path::Path;
+
+use rust_tracer::ies::{load_ies, parse_ies, IesError};
+use rust_tracer::light::Light;
+use rust_tracer::scene_file::parse_scene;
+use rust_tracer::vec3::Vec3;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn parses_the_fixture_profile() {
+    let profile = load_ies(Path::new("tests/fixtures/downlight.ies")).expect("valid profile");
+
+    assert_eq!(profile.vertical_angles.len(), 10);
+    assert_eq!(profile.horizontal_angles, vec![0.0, 90.0, 180.0]);
+    assert_eq!(profile.candela.len(), 3);
+    assert_eq!(profile.candela[1][3], 900.0);
+
+    // Normalized to the peak straight down
+    assert!(close(profile.intensity(0.0, 0.0), 1.0));
+    assert!(close(profile.intensity(30.0, 0.0), 0.85));
+    // Between two vertical and two horizontal angles
+    assert!(close(profile.intensity(35.0, 0.0), 0.775));
+    assert!(close(profile.intensity(30.0, 45.0), 0.875));
+    // Bilateral symmetry fills in 180-360
+    assert!(close(profile.intensity(30.0, 270.0), profile.intensity(30.0, 90.0)));
+    assert!(close(profile.intensity(30.0, -90.0), profile.intensity(30.0, 90.0)));
+    // Nothing above the horizon
+    assert_eq!(profile.intensity(120.0, 0.0), 0.0);
+}
+
+#[test]
+fn skips_included_tilt_data_and_applies_the_multiplier() {
+    let contents = "IESNA91
+TILT=INCLUDE
+1
+3
+0 45 90
+1 0.9 0.8
+1 -1 2.0 3 1 1 1 0 0 0
+1 1 10
+0, 45, 90
+0
+50, 40, 10
+";
+    let profile = parse_ies(contents).expect("valid profile");
+
+    assert_eq!(profile.horizontal_angles, vec![0.0]);
+    assert_eq!(profile.candela[0], vec![100.0, 80.0, 20.0]);
+    // Rotationally symmetric
+    assert!(close(profile.intensity(45.0, 123.0), 0.8));
+}
+
+#[test]
+fn rejects_malformed_files() {
+    let cases = [
+        ("IESNA:LM-63-2002\n1 1000 1 2 1 1 1 0 0 0\n", "missing TILT line"),
+        ("TILT=lamp.tlt\n", "unsupported TILT=lamp.tlt"),
+        ("TILT=NONE\n1 1000 1 2 1 2 1 0 0 0 1 1 10 0 90 0 1 1\n", "unsupported photometric type 2"),
+        ("TILT=NONE\n1 1000 1 2 1 1 1 0 0 0 1 1 10 0 90 0 1\n", "missing candela value"),
+        ("TILT=NONE\n1 1000 1 2 1 1 1 0 0 0 1 1 10 90 0 0 1 1\n", "angles must be increasing"),
+        ("TILT=NONE\n1 1000 x 2 1 1 1 0 0 0\n", "invalid number for candela multiplier: x"),
+    ];
+
+    for &(contents, expected) in cases.iter() {
+        match parse_ies(contents) {
+            Err(IesError::Syntax(message)) => assert_eq!(message, expected),
+            other => panic!("expected a syntax error for {:?}, got {:?}", contents, other),
+        }
+    }
+}
+
+#[test]
+fn profile_shapes_a_point_light() {
+    let contents = "light position 0 0 0 force 4 ies downlight.ies nadir 0 1 0";
+    let scene_file = parse_scene(contents, Path::new("tests/fixtures")).expect("valid scene");
+    let light = &scene_file.scene.lights[0];
+    match *light {
+        Light::Point(ref point) => assert!(point.profile.is_some()),
+        ref other => panic!("expected a point light, got {:?}", other),
+    }
+
+    let at_angle = |degrees: f64| {
+        let radians = degrees.to_radians();
+        light.sample(&Vec3::new(radians.sin() * 2.0, radians.cos() * 2.0, 0.0), 0.5, 0.5).unwrap().irradiance.x
+    };
+    assert!(close(at_angle(0.0), 1.0));
+    assert!(at_angle(60.0) < at_angle(30.0));
+    assert_eq!(at_angle(180.0), 0.0);
+}
@@ -0,0 +1,124 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::integrator::{integrator_by_name, Integrator, Normals, Whitted};
+use rust_tracer::light::{Light, PointLight};
+use rust_tracer::material::Lambertian;
+use rust_tracer::occlusion::AmbientOcclusion;
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Background, Plane, Scene, Sphere};
+use rust_tracer::vec3::Vec3;
+
+fn unit_sphere_scene() -> Scene {
+    Scene {
+        spheres: vec![Sphere {
+            position: Vec3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) }),
+        }],
+        background: Background::Color(Vec3::new(0.3, 0.3, 0.3)),
+        ..Scene::default()
+    }
+}
+
+#[test]
+fn normals_map_to_colours() {
+    let scene = unit_sphere_scene();
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    let facing_camera = Normals.trace(
+        Ray { origin: Vec3::new(0.0, 0.0, 3.0), direction: Vec3::new(0.0, 0.0, -1.0) },
+        &scene,
+        &mut sampler,
+    );
+    assert_eq!((facing_camera.r, facing_camera.g, facing_camera.b), (0.5, 0.5, 1.0));
+
+    let top = Normals.trace(
+        Ray { origin: Vec3::new(0.0, -3.0, 0.0), direction: Vec3::new(0.0, 1.0, 0.0) },
+        &scene,
+        &mut sampler,
+    );
+    assert_eq!((top.r, top.g, top.b), (0.5, 0.0, 0.5));
+
+    let miss = Normals.trace(
+        Ray { origin: Vec3::new(0.0, 0.0, 3.0), direction: Vec3::new(0.0, 0.0, 1.0) },
+        &scene,
+        &mut sampler,
+    );
+    assert_eq!((miss.r, miss.g, miss.b), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn integrators_by_name() {
+    let expected_names = [
+        ("whitted", "Whitted { ambient_occlusion: None }"),
+        ("path", "PathTracer"),
+        ("bdpt", "Bidirectional"),
+        ("ao", "AmbientOcclusion { samples: 16, max_distance: 1.0 }"),
+        ("normals", "Normals"),
+    ];
+    for &(name, expected) in expected_names.iter() {
+        let integrator = integrator_by_name(name).expect(name);
+        assert_eq!(format!("{:?}", integrator), expected);
+    }
+    assert!(format!("{:?}", integrator_by_name("photons").expect("photons")).starts_with("PhotonMapper {"));
+    assert!(integrator_by_name("Path").is_none());
+}
+
+// Floor at y = 1 under a ceiling at y = 0
+fn floor_and_ceiling(ceiling: bool) -> Scene {
+    let plane = |y: f64, normal: f64| Plane {
+        position: Vec3::new(0.0, y, 0.0),
+        normal: Vec3::new(0.0, normal, 0.0),
+        material: Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) }),
+    };
+
+    Scene {
+        lights: vec![Light::Point(PointLight {
+            position: Vec3::new(0.0, 0.5, 0.0),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            force: 1.0,
+            profile: None,
+        })],
+        planes: if ceiling { vec![plane(1.0, -1.0), plane(0.0, 1.0)] } else { vec![plane(1.0, -1.0)] },
+        ..Scene::default()
+    }
+}
+
+fn floor_ray() -> Ray {
+    Ray { origin: Vec3::new(0.0, 0.5, 0.5), direction: Vec3::new(0.0, 1.0, -1.0) }
+}
+
+// Rays leaving at angle theta hit the ceiling 1 / cos(theta) away, so those with
+// cos(theta) below 1 / max_distance get out, which is that squared of the cosine
+// weighted hemisphere
+#[test]
+fn ambient_occlusion_under_a_ceiling() {
+    let occlusion = AmbientOcclusion { samples: 20000, max_distance: 2.0 };
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    let open = occlusion.trace(floor_ray(), &floor_and_ceiling(false), &mut sampler);
+    assert_eq!(open.r, 1.0);
+
+    let covered = occlusion.trace(floor_ray(), &floor_and_ceiling(true), &mut sampler);
+    assert!((covered.r - 0.25).abs() < 0.01, "{}", covered.r);
+
+    let sky = occlusion.trace(Ray { origin: Vec3::new(0.0, 0.5, 0.0), direction: Vec3::new(0.0, 0.0, 1.0) }, &floor_and_ceiling(false), &mut sampler);
+    assert_eq!((sky.r, sky.g, sky.b), (1.0, 1.0, 1.0));
+}
+
+#[test]
+fn ambient_occlusion_darkens_the_ambient_term() {
+    let scene = floor_and_ceiling(true);
+    let flat = Whitted::default().trace(floor_ray(), &scene, &mut Sampler::new(0, 0, 0));
+    let occluded = Whitted { ambient_occlusion: Some(AmbientOcclusion { samples: 64, max_distance: 10.0 }) }
+        .trace(floor_ray(), &scene, &mut Sampler::new(0, 0, 0));
+    assert!(occluded.r < flat.r, "{} vs {}", occluded.r, flat.r);
+
+    // Nothing within reach
+    let short = Whitted { ambient_occlusion: Some(AmbientOcclusion { samples: 64, max_distance: 0.5 }) }
+        .trace(floor_ray(), &scene, &mut Sampler::new(0, 0, 0));
+    assert_eq!(short.r, flat.r);
+}
@@ -0,0 +1,137 @@
+extern crate rust_tracer;
+
+use rust_tracer::light::{AreaLight, AreaShape, DirectionalLight, Light, SpotLight};
+use rust_tracer::ray::INFINITY;
+use rust_tracer::vec3::Vec3;
+
+const SAMPLE_GRID: u32 = 32;
+
+fn area_light(shape: AreaShape) -> AreaLight {
+    AreaLight {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        force: 1.0,
+        shape,
+        samples: 1,
+    }
+}
+
+fn area_lights() -> Vec<Light> {
+    vec![
+        Light::Area(area_light(AreaShape::Rectangle { edge_u: Vec3::new(0.5, 0.0, 0.0), edge_v: Vec3::new(0.0, 0.0, -0.5) })),
+        Light::Area(area_light(AreaShape::Disk { normal: Vec3::new(0.0, 1.0, 0.0), radius: 0.3 })),
+        Light::Area(area_light(AreaShape::Sphere { radius: 0.3 })),
+    ]
+}
+
+fn grid() -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    for i in 0..SAMPLE_GRID {
+        for j in 0..SAMPLE_GRID {
+            points.push(((i as f64 + 0.5) / SAMPLE_GRID as f64, (j as f64 + 0.5) / SAMPLE_GRID as f64));
+        }
+    }
+    points
+}
+
+// Mean irradiance compared to a point light of force 1.0 at the origin
+fn relative_strength(light: &Light, from: &Vec3) -> f64 {
+    let point_irradiance = 1.0 / from.dot(from);
+    let total: f64 = grid()
+        .iter()
+        .filter_map(|&(u1, u2)| light.sample(from, u1, u2))
+        .map(|sample| sample.irradiance.x)
+        .sum();
+
+    total / (SAMPLE_GRID * SAMPLE_GRID) as f64 / point_irradiance
+}
+
+#[test]
+fn area_lights_match_a_point_light_from_far_away() {
+    let below = Vec3::new(0.0, 50.0, 0.0);
+
+    for light in area_lights() {
+        let strength = relative_strength(&light, &below);
+        assert!((strength - 1.0).abs() < 0.01, "{:?}: {}", light, strength);
+    }
+}
+
+#[test]
+fn flat_lights_are_dark_behind() {
+    let above = Vec3::new(0.0, -3.0, 0.0);
+
+    for light in area_lights() {
+        let strength = relative_strength(&light, &above);
+        match light {
+            Light::Area(AreaLight { shape: AreaShape::Sphere { .. }, .. }) => assert!(strength > 0.9, "{}", strength),
+            _ => assert_eq!(strength, 0.0),
+        }
+    }
+}
+
+#[test]
+fn samples_stay_on_the_light() {
+    let from = Vec3::new(0.4, 2.0, 0.3);
+
+    for (u1, u2) in grid() {
+        for light in area_lights() {
+            let sample = light.sample(&from, u1, u2).expect("light faces the point");
+            let position = &from + &(&sample.direction * sample.distance);
+
+            match light {
+                Light::Area(AreaLight { shape: AreaShape::Rectangle { .. }, .. }) => {
+                    assert!(position.x.abs() <= 0.25 + 1e-9 && position.z.abs() <= 0.25 + 1e-9 && position.y.abs() < 1e-9)
+                }
+                Light::Area(AreaLight { shape: AreaShape::Disk { radius, .. }, .. }) => {
+                    assert!(position.mag() <= radius + 1e-9 && position.y.abs() < 1e-9)
+                }
+                Light::Area(AreaLight { shape: AreaShape::Sphere { radius }, .. }) => {
+                    // On the half facing the shading point
+                    assert!((position.mag() - radius).abs() < 1e-9);
+                    assert!(position.dot(&from) > 0.0);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[test]
+fn directional_light_has_no_falloff() {
+    let sun = Light::Directional(DirectionalLight {
+        direction: Vec3::new(0.0, 1.0, 0.0),
+        color: Vec3::new(1.0, 0.9, 0.8),
+        strength: 2.0,
+    });
+
+    for &height in [0.0, -100.0, 1e6].iter() {
+        let sample = sun.sample(&Vec3::new(3.0, height, 1.0), 0.5, 0.5).expect("the sun reaches everywhere");
+        assert_eq!(sample.distance, INFINITY);
+        assert_eq!(sample.direction.y, -1.0);
+        assert_eq!(sample.irradiance.z, 1.6);
+    }
+    assert!(sun.position().is_none());
+}
+
+#[test]
+fn spot_light_dims_towards_the_edge_of_its_cone() {
+    let spot = Light::Spot(SpotLight {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.0, 1.0, 0.0),
+        color: Vec3::new(1.0, 1.0, 1.0),
+        force: 4.0,
+        cone_angle: 30.0,
+        falloff: 2.0,
+        profile: None,
+    });
+    let at_angle = |degrees: f64| {
+        let radians = degrees.to_radians();
+        spot.sample(&Vec3::new(radians.sin() * 2.0, radians.cos() * 2.0, 0.0), 0.5, 0.5)
+    };
+
+    assert!((at_angle(0.0).unwrap().irradiance.x - 1.0).abs() < 1e-12);
+    let edge = at_angle(25.0).unwrap().irradiance.x;
+    assert!((edge - 25.0_f64.to_radians().cos().powi(2)).abs() < 1e-12);
+    assert!(at_angle(35.0).is_none());
+    assert!(at_angle(180.0).is_none());
+}
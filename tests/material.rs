@@ -0,0 +1,168 @@
+extern crate rust_tracer;
+
+use std::f64::consts::PI;
+
+use rust_tracer::brdf::{ggx_distribution, roughness_to_alpha};
+use rust_tracer::material::{Lambertian, Material, Metal, Mirror, Plastic, Principled};
+use rust_tracer::sampler::Sampler;
+use rust_tracer::vec3::Vec3;
+
+const SAMPLE_COUNT: u64 = 20000;
+
+fn materials() -> Vec<(&'static str, Box<dyn Material>)> {
+    let white = Vec3::new(1.0, 1.0, 1.0);
+
+    vec![
+        ("lambertian", Box::new(Lambertian { albedo: white.clone() }) as Box<dyn Material>),
+        ("mirror", Box::new(Mirror { reflectance: white.clone() })),
+        ("smooth plastic", Box::new(Plastic::new(white.clone(), 0.0))),
+        ("plastic 0.3", Box::new(Plastic::new(white.clone(), 0.3))),
+        ("plastic 0.8", Box::new(Plastic::new(white.clone(), 0.8))),
+        ("polished metal", Box::new(Metal { color: white.clone(), roughness: 0.0 })),
+        ("metal 0.3", Box::new(Metal { color: white.clone(), roughness: 0.3 })),
+        ("metal 0.8", Box::new(Metal { color: white, roughness: 0.8 })),
+    ]
+}
+
+// Disney's model isn't energy conserving (the diffuse lobe ignores what the specular
+// took and sheen is added on top), so it is only checked for consistent sampling
+fn principled_materials() -> Vec<(&'static str, Box<dyn Material>)> {
+    let white = Vec3::new(1.0, 1.0, 1.0);
+
+    vec![
+        ("principled", Box::new(Principled::new(white.clone())) as Box<dyn Material>),
+        ("principled metallic", Box::new(Principled { metallic: 1.0, roughness: 0.3, ..Principled::new(white.clone()) })),
+        ("principled glossy", Box::new(Principled { roughness: 0.0, clearcoat: 1.0, ..Principled::new(white.clone()) })),
+        ("principled satin", Box::new(Principled { subsurface: 1.0, sheen: 1.0, clearcoat: 1.0, clearcoat_gloss: 0.2, ..Principled::new(white.clone()) })),
+        ("principled glass", Box::new(Principled { transmission: 1.0, roughness: 0.0, ..Principled::new(white) })),
+    ]
+}
+
+fn view_directions() -> Vec<Vec3> {
+    [0.0_f64, 45.0, 80.0]
+        .iter()
+        .map(|angle| {
+            let angle = angle.to_radians();
+            Vec3::new(angle.sin(), 0.0, angle.cos())
+        })
+        .collect()
+}
+
+// Riemann sum of f * cos over the hemisphere plus the specular lobes: the fraction of light reflected towards `wo`
+fn directional_albedo(material: &dyn Material, normal: &Vec3, wo: &Vec3) -> Vec3 {
+    let theta_steps = 256;
+    let phi_steps = 256;
+    let d_theta = 0.5 * PI / theta_steps as f64;
+    let d_phi = 2.0 * PI / phi_steps as f64;
+    let mut total = Vec3::new(0.0, 0.0, 0.0);
+
+    for i in 0..theta_steps {
+        let theta = (i as f64 + 0.5) * d_theta;
+        for j in 0..phi_steps {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let wi = Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+            let weight = theta.cos() * theta.sin() * d_theta * d_phi;
+            total = total + material.evaluate(normal, wo, &wi) * weight;
+        }
+    }
+
+    for lobe in material.specular_lobes(normal, wo) {
+        total = total + lobe.weight;
+    }
+
+    total
+}
+
+// Same quantity estimated by importance sampling
+fn sampled_albedo(material: &dyn Material, normal: &Vec3, wo: &Vec3) -> Vec3 {
+    let mut total = Vec3::new(0.0, 0.0, 0.0);
+
+    for i in 0..SAMPLE_COUNT {
+        let mut sampler = Sampler::new(7, 0, i);
+        if let Some(sample) = material.sample(normal, wo, &mut sampler) {
+            total = total + sample.weight;
+        }
+    }
+
+    total / SAMPLE_COUNT as f64
+}
+
+#[test]
+fn ggx_distribution_is_normalized() {
+    // The projected microfacet area must add up to the macro surface: integral of D * cos = 1
+    for &roughness in [0.3, 0.6, 1.0].iter() {
+        let alpha = roughness_to_alpha(roughness);
+        let steps = 20000;
+        let d_theta = 0.5 * PI / steps as f64;
+        let integral: f64 = (0..steps)
+            .map(|i| {
+                let theta = (i as f64 + 0.5) * d_theta;
+                ggx_distribution(theta.cos(), alpha) * theta.cos() * theta.sin() * d_theta * 2.0 * PI
+            })
+            .sum();
+
+        assert!((integral - 1.0).abs() < 0.01, "roughness {}: {}", roughness, integral);
+    }
+}
+
+#[test]
+fn materials_conserve_energy() {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    for (name, material) in materials() {
+        for wo in view_directions() {
+            let albedo = directional_albedo(&*material, &normal, &wo);
+            assert!(albedo.max_component() <= 1.01, "{} seen from {:?} reflects {:?}", name, wo, albedo);
+        }
+    }
+}
+
+#[test]
+fn sampling_matches_evaluation() {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    for (name, material) in materials().into_iter().chain(principled_materials()) {
+        for wo in view_directions() {
+            let expected = directional_albedo(&*material, &normal, &wo);
+            let sampled = sampled_albedo(&*material, &normal, &wo);
+            assert!(
+                (&expected - &sampled).mag() < 0.03 * expected.max_component().max(1.0),
+                "{} seen from {:?}: integrated {:?}, sampled {:?}",
+                name, wo, expected, sampled
+            );
+        }
+    }
+}
+
+#[test]
+fn sample_pdf_matches_pdf() {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let wo = Vec3::new(0.5, 0.0, 0.866);
+
+    for (name, material) in materials().into_iter().chain(principled_materials()) {
+        for i in 0..100 {
+            let mut sampler = Sampler::new(3, 0, i);
+            if let Some(sample) = material.sample(&normal, &wo, &mut sampler) {
+                if sample.specular {
+                    continue;
+                }
+                let pdf = material.pdf(&normal, &wo, &sample.direction);
+                assert!((pdf - sample.pdf).abs() <= 1e-9 * pdf.max(1.0), "{}: {} != {}", name, sample.pdf, pdf);
+            }
+        }
+    }
+}
+
+#[test]
+fn opaque_materials_are_two_sided() {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let flipped = Vec3::new(0.0, 0.0, -1.0);
+    let wo = Vec3::new(0.0, 0.6, 0.8);
+    let wi = Vec3::new(0.6, 0.0, 0.8);
+
+    for (name, material) in materials().into_iter().chain(principled_materials()) {
+        let front = material.evaluate(&normal, &wo, &wi);
+        let back = material.evaluate(&flipped, &wo, &wi);
+        assert!((&front - &back).mag() < 1e-12, "{}", name);
+    }
+}
@@ -0,0 +1,158 @@
+extern crate rust_tracer;
+
+mod common;
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rust_tracer::lighting::{shadow_transmittance, Mis};
+use rust_tracer::material::{Lambertian, Volume};
+use rust_tracer::medium::{henyey_greenstein, Medium};
+use rust_tracer::path_tracer::path_radiance;
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Scene, Sphere};
+use rust_tracer::vec3::Vec3;
+
+use common::lit_floor;
+
+fn medium(absorption: f64, scattering: f64, anisotropy: f64) -> Medium {
+    Medium {
+        absorption: Vec3::new(absorption, absorption, absorption),
+        scattering: Vec3::new(scattering, scattering, scattering),
+        anisotropy,
+    }
+}
+
+#[test]
+fn henyey_greenstein_integrates_to_one() {
+    for &g in [-0.6, 0.0, 0.3, 0.9].iter() {
+        let steps = 100_000;
+        let integral: f64 = (0..steps)
+            .map(|i| {
+                let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                2.0 * PI * henyey_greenstein(cos_theta, g) * 2.0 / steps as f64
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-3, "g = {}: {}", g, integral);
+    }
+}
+
+// The mean cosine of Henyey-Greenstein scattering is g
+#[test]
+fn phase_samples_follow_the_anisotropy() {
+    let mut sampler = Sampler::new(0, 0, 0);
+    let wo = Vec3::new(0.0, 0.6, 0.8);
+
+    for &g in [-0.5, 0.0, 0.7].iter() {
+        let medium = medium(0.0, 1.0, g);
+        let samples = 50_000;
+        let mut mean_cos = 0.0;
+        for _ in 0..samples {
+            let wi = medium.sample_phase(&wo, sampler.next_f64(), sampler.next_f64());
+            assert!((wi.mag() - 1.0).abs() < 1e-9);
+            // Between the way light went on and wo's opposite, the way it came
+            mean_cos += -wi.dot(&wo);
+        }
+        mean_cos /= samples as f64;
+        assert!((mean_cos - g).abs() < 0.01, "g = {}: {}", g, mean_cos);
+    }
+}
+
+// Light passing unscattered carries the transmittance on average, per channel
+#[test]
+fn distance_samples_average_to_the_transmittance() {
+    let medium = Medium {
+        absorption: Vec3::new(0.1, 0.2, 0.4),
+        scattering: Vec3::new(0.3, 0.5, 0.2),
+        anisotropy: 0.0,
+    };
+    let distance = 2.0;
+    let mut sampler = Sampler::new(1, 0, 0);
+    let samples = 100_000;
+
+    let mut passed = Vec3::new(0.0, 0.0, 0.0);
+    let mut scattered = Vec3::new(0.0, 0.0, 0.0);
+    for _ in 0..samples {
+        match medium.sample_distance(distance, &mut sampler) {
+            (None, weight) => passed = passed + weight,
+            (Some(at), weight) => {
+                assert!(at < distance);
+                scattered = scattered + weight;
+            }
+        }
+    }
+
+    let expected = medium.transmittance(distance);
+    let passed = passed * (1.0 / samples as f64);
+    for &(actual, expected) in [(passed.x, expected.x), (passed.y, expected.y), (passed.z, expected.z)].iter() {
+        assert!((actual - expected).abs() < 0.01, "{} vs {}", actual, expected);
+    }
+
+    // Scattered somewhere on the way, integral of scattering * transmittance
+    let scattered = scattered * (1.0 / samples as f64);
+    let extinction = medium.extinction();
+    let expected_scattered = 0.5 / 0.7 * (1.0 - (-0.7 * distance).exp());
+    assert!((scattered.y - expected_scattered).abs() < 0.01, "{} vs {}", scattered.y, expected_scattered);
+    assert_eq!(extinction.y, 0.7);
+}
+
+fn smoke_ball_scene(global: Option<Medium>) -> Scene {
+    Scene {
+        spheres: vec![
+            Sphere {
+                position: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+                material: Arc::new(Volume { medium: medium(0.5, 0.5, 0.0) }),
+            },
+            Sphere {
+                position: Vec3::new(0.0, 0.0, -5.0),
+                radius: 1.0,
+                material: Arc::new(Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) }),
+            },
+        ],
+        medium: global,
+        ..Scene::default()
+    }
+}
+
+#[test]
+fn shadow_rays_pass_through_volumes() {
+    let ray = Ray { origin: Vec3::new(0.0, 0.0, 3.0), direction: Vec3::new(0.0, 0.0, -1.0) };
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    // Two units of the ball's inside, nothing else
+    let scene = smoke_ball_scene(None);
+    let through_ball = shadow_transmittance(&ray, 4.5, &scene, &mut sampler);
+    assert!((through_ball.x - (-2.0f64).exp()).abs() < 1e-3, "{}", through_ball.x);
+    let into_ball = shadow_transmittance(&ray, 3.5, &scene, &mut sampler);
+    assert!((into_ball.x - (-1.5f64).exp()).abs() < 1e-3, "{}", into_ball.x);
+    assert_eq!(shadow_transmittance(&ray, 1.5, &scene, &mut sampler).x, 1.0);
+    assert_eq!(shadow_transmittance(&ray, 8.0, &scene, &mut sampler).x, 0.0);
+
+    // Fog all the way, but not inside the ball
+    let scene = smoke_ball_scene(Some(medium(0.1, 0.0, 0.0)));
+    let foggy = shadow_transmittance(&ray, 4.5, &scene, &mut sampler);
+    let expected = (-2.0 - 0.1 * 2.5f64).exp();
+    assert!((foggy.x - expected).abs() < 1e-3, "{} vs {}", foggy.x, expected);
+}
+
+// Floor at y = 1 lit from straight above, seen from above at 45 degrees
+// Without scattering fog only dims the light on its way to the floor and back
+#[test]
+fn absorbing_fog_dims_the_path_traced_floor() {
+    let ray = || Ray { origin: Vec3::new(0.0, 0.0, -1.0), direction: Vec3::new(0.0, 1.0, 1.0).unit() };
+    let clear = path_radiance(ray(), &lit_floor(Vec3::new(0.0, 0.0, 0.0), 1.0), Mis::PowerHeuristic, &mut Sampler::new(0, 0, 0));
+
+    let scene = Scene { medium: Some(medium(0.2, 0.0, 0.0)), ..lit_floor(Vec3::new(0.0, 0.0, 0.0), 1.0) };
+    let samples = 20_000;
+    let mut sampler = Sampler::new(2, 0, 0);
+    let mut foggy = 0.0;
+    for _ in 0..samples {
+        foggy += path_radiance(ray(), &scene, Mis::PowerHeuristic, &mut sampler).x;
+    }
+    foggy /= samples as f64;
+
+    let expected = clear.x * (-0.2 * (2.0f64.sqrt() + 1.0)).exp();
+    assert!((foggy - expected).abs() < 0.02 * expected, "{} vs {}", foggy, expected);
+}
@@ -0,0 +1,103 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::lighting::Mis;
+use rust_tracer::material::{Emissive, Lambertian, Metal};
+use rust_tracer::path_tracer::path_radiance;
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Background, Quad, Scene, Sphere};
+use rust_tracer::vec3::Vec3;
+
+// A diffuse sphere under a uniform sky reflects albedo times the sky, whatever
+// direction it is seen from
+#[test]
+fn white_furnace() {
+    let scene = Scene {
+        spheres: vec![Sphere {
+            position: Vec3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian { albedo: Vec3::new(0.8, 0.5, 0.2) }),
+        }],
+        background: Background::Color(Vec3::new(0.5, 0.5, 0.5)),
+        ..Scene::default()
+    };
+
+    let count = 20000;
+    let mut total = Vec3::new(0.0, 0.0, 0.0);
+    for index in 0..count {
+        let mut sampler = Sampler::new(0, index, 0);
+        let ray = Ray {
+            origin: Vec3::new(0.3, -0.2, 3.0),
+            direction: Vec3::new(-0.1, 0.05, -1.0),
+        };
+        total = total + path_radiance(ray, &scene, Mis::PowerHeuristic, &mut sampler);
+    }
+    let average = total * (1.0 / count as f64);
+
+    assert!((average.x - 0.4).abs() < 0.01, "{:?}", average);
+    assert!((average.y - 0.25).abs() < 0.01, "{:?}", average);
+    assert!((average.z - 0.1).abs() < 0.01, "{:?}", average);
+}
+
+// Missing everything, the camera sees the background itself
+#[test]
+fn background_seen_directly() {
+    let scene = Scene {
+        background: Background::Color(Vec3::new(0.1, 0.2, 0.3)),
+        ..Scene::default()
+    };
+    let ray = Ray {
+        origin: Vec3::new(0.0, 0.0, 0.0),
+        direction: Vec3::new(0.0, 0.0, -1.0),
+    };
+
+    let color = path_radiance(ray, &scene, Mis::PowerHeuristic, &mut Sampler::new(0, 0, 0));
+    assert_eq!((color.x, color.y, color.z), (0.1, 0.2, 0.3));
+}
+
+// A large light in a polished plate: sampling only the light is unbiased but noisy,
+// weighing in the BSDF samples with MIS must give the same mean with less noise
+#[test]
+fn mis_keeps_the_mean_and_lowers_the_noise() {
+    let scene = Scene {
+        spheres: vec![Sphere {
+            position: Vec3::new(0.0, -2.0, -2.0),
+            radius: 1.0,
+            material: Arc::new(Emissive { radiance: Vec3::new(1.0, 1.0, 1.0) }),
+        }],
+        quads: vec![Quad {
+            corner: Vec3::new(-2.0, 0.0, -2.0),
+            edge_u: Vec3::new(4.0, 0.0, 0.0),
+            edge_v: Vec3::new(0.0, 0.0, 4.0),
+            material: Arc::new(Metal { color: Vec3::new(0.8, 0.8, 0.8), roughness: 0.2 }),
+        }],
+        ..Scene::default()
+    };
+
+    let count = 20000;
+    let estimate = |mis: Mis| {
+        let mut values = Vec::with_capacity(count);
+        for index in 0..count {
+            let mut sampler = Sampler::new(0, index as u64, 0);
+            let ray = Ray {
+                origin: Vec3::new(0.0, -2.0, 2.0),
+                direction: Vec3::new(0.0, 1.0, -1.0),
+            };
+            values.push(path_radiance(ray, &scene, mis, &mut sampler).y);
+        }
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / count as f64;
+        (mean, variance)
+    };
+
+    let (light_only_mean, light_only_variance) = estimate(Mis::Off);
+    let (mis_mean, mis_variance) = estimate(Mis::PowerHeuristic);
+
+    assert!(mis_mean > 0.1, "{}", mis_mean);
+    // Within four standard errors of each other
+    let tolerance = 4.0 * ((light_only_variance + mis_variance) / count as f64).sqrt();
+    assert!((mis_mean - light_only_mean).abs() < tolerance, "{} vs {}", mis_mean, light_only_mean);
+    assert!(mis_variance < 0.1 * light_only_variance, "{} vs {}", mis_variance, light_only_variance);
+}
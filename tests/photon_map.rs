@@ -0,0 +1,90 @@
+extern crate rust_tracer;
+
+mod common;
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rust_tracer::material::{Dielectric, Lambertian};
+use rust_tracer::photon_map::{progressive_radius, shoot_photons, Photon, PhotonMap};
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Scene, Sphere};
+use rust_tracer::vec3::Vec3;
+
+use common::lit_floor;
+
+fn random_photon(sampler: &mut Sampler) -> Photon {
+    Photon {
+        position: Vec3::new(sampler.next_f64() * 4.0 - 2.0, sampler.next_f64(), sampler.next_f64() * 2.0),
+        normal: Vec3::new(0.0, -1.0, 0.0),
+        direction: Vec3::new(0.0, 1.0, 0.0),
+        power: Vec3::new(1.0, 1.0, 1.0),
+    }
+}
+
+// Lit from straight above by a point light 1 up, on the floor plane at y = 1
+#[test]
+fn finds_the_same_photons_as_a_linear_search() {
+    let mut sampler = Sampler::new(3, 0, 0);
+    let photons: Vec<Photon> = (0..2000).map(|_| random_photon(&mut sampler)).collect();
+    let map = PhotonMap::new(photons.clone());
+    assert_eq!(map.len(), photons.len());
+
+    for _ in 0..50 {
+        let point = random_photon(&mut sampler).position;
+        let radius = sampler.next_f64() * 0.3;
+
+        let mut found = Vec::new();
+        map.for_each_within(&point, radius, |photon| found.push(photon.position.clone()));
+        let expected: Vec<&Photon> = photons.iter().filter(|photon| (&photon.position - &point).mag() <= radius).collect();
+
+        assert_eq!(found.len(), expected.len());
+        for photon in expected {
+            assert!(found.iter().any(|position| (position - &photon.position).mag() == 0.0));
+        }
+    }
+}
+
+// Radiance off a diffuse floor right under a point light: albedo / PI times the
+// irradiance, force / distance^2
+#[test]
+fn density_estimate_matches_direct_light() {
+    let scene = lit_floor(Vec3::new(0.0, 0.0, 0.0), 1.0);
+    let photons = shoot_photons(&scene, 200_000, false, &mut Sampler::new(0, 0, 0));
+    let map = PhotonMap::new(photons);
+
+    let material = Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) };
+    let normal = Vec3::new(0.0, -1.0, 0.0);
+    let radiance = map.radiance(&Vec3::new(0.0, 1.0, 0.0), &normal, &normal, &material, 0.1);
+
+    let expected = 0.5 / PI;
+    assert!((radiance.y - expected).abs() < 0.1 * expected, "{} vs {}", radiance.y, expected);
+}
+
+#[test]
+fn caustic_photons_come_through_glass() {
+    let matte_only = lit_floor(Vec3::new(0.0, 0.0, 0.0), 1.0);
+    assert!(shoot_photons(&matte_only, 10_000, true, &mut Sampler::new(0, 0, 0)).is_empty());
+
+    let glass = Sphere {
+        position: Vec3::new(0.0, 0.5, 0.0),
+        radius: 0.3,
+        material: Arc::new(Dielectric::glass()),
+    };
+    let scene = Scene { spheres: vec![glass], ..lit_floor(Vec3::new(0.0, 0.0, 0.0), 1.0) };
+    let photons = shoot_photons(&scene, 10_000, true, &mut Sampler::new(0, 0, 0));
+    let on_floor: Vec<&Photon> = photons.iter().filter(|photon| photon.position.y > 0.999).collect();
+
+    // Most are focused under the sphere, into less than its shadow, the rest reflected off it
+    let focused = on_floor.iter().filter(|photon| photon.position.x.hypot(photon.position.z) < 0.2).count();
+    assert!(focused as f64 > 0.8 * on_floor.len() as f64, "{} of {}", focused, on_floor.len());
+}
+
+#[test]
+fn radius_shrinks_every_pass() {
+    assert_eq!(progressive_radius(0.1, 0), 0.1);
+
+    let radii: Vec<f64> = (0..100).map(|pass| progressive_radius(0.1, pass)).collect();
+    assert!(radii.windows(2).all(|pair| pair[1] < pair[0]));
+    assert!(radii[99] < 0.05);
+}
@@ -0,0 +1,68 @@
+extern crate rust_tracer;
+
+use std::time::Duration;
+
+use rust_tracer::camera::{create_camera, Camera};
+use rust_tracer::film::Film;
+use rust_tracer::integrator::Whitted;
+use rust_tracer::render::{render, CancelToken, Progress, RenderSettings, RenderStatus, Screen};
+use rust_tracer::scene::create_scene;
+use rust_tracer::vec3::Vec3;
+
+const SCREEN: Screen = Screen { width: 4, height: 3 };
+
+fn camera() -> Camera {
+    create_camera(Vec3::new(0.0, -0.6, 2.0), Vec3::new(0.0, 0.0, 0.0), 45.0, 4.0 / 3.0, Vec3::new(0.0, 1.0, 0.0))
+}
+
+fn settings(samples_per_pixel: u32) -> RenderSettings {
+    RenderSettings {
+        integrator: Box::new(Whitted::default()),
+        samples_per_pixel,
+        seed: 0,
+        checkpoint: None,
+    }
+}
+
+#[test]
+fn cancelling_stops_between_pixels() {
+    let mut film = Film::new(SCREEN.width, SCREEN.height);
+    let cancel = CancelToken::new();
+    let status = render(&create_scene(), &camera(), &SCREEN, &mut settings(2), &mut film, &cancel, |progress| {
+        if progress.rows_done == 2 {
+            cancel.cancel();
+        }
+    });
+
+    assert_eq!(status.expect("render without checkpoint can't fail"), RenderStatus::Cancelled);
+    // The first two rows of the first pass, nothing after them
+    let rendered = (SCREEN.width * 2) as usize;
+    assert!(film.samples[..rendered].iter().all(|&samples| samples == 1));
+    assert!(film.samples[rendered..].iter().all(|&samples| samples == 0));
+}
+
+#[test]
+fn progress_rises_to_one() {
+    let mut film = Film::new(SCREEN.width, SCREEN.height);
+    let mut fractions = Vec::new();
+    let status = render(&create_scene(), &camera(), &SCREEN, &mut settings(2), &mut film, &CancelToken::new(), |progress| {
+        fractions.push(progress.fraction());
+    });
+
+    assert_eq!(status.expect("render without checkpoint can't fail"), RenderStatus::Complete);
+    assert_eq!(fractions.len(), 2 * SCREEN.height as usize);
+    assert!(fractions.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", fractions);
+    assert_eq!(fractions.last(), Some(&1.0));
+    assert_eq!(film.min_samples(), 2);
+}
+
+#[test]
+fn eta_is_unknown_before_the_first_row() {
+    let progress = |rows_done, rows_total| Progress { rows_done, rows_total, elapsed: Duration::from_secs(6) };
+
+    assert_eq!(progress(0, 10).eta(), None);
+    assert_eq!(progress(5, 10).eta(), Some(Duration::from_secs(6)));
+    assert_eq!(progress(10, 10).eta(), Some(Duration::from_secs(0)));
+    // Nothing left to render, e.g. resuming a finished checkpoint
+    assert_eq!((progress(0, 0).fraction(), progress(0, 0).eta()), (1.0, Some(Duration::from_secs(0))));
+}
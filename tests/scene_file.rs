@@ -0,0 +1,146 @@
+extern crate rust_tracer;
+
+use std::path::Path;
+
+use rust_tracer::light::Light;
+use rust_tracer::scene_file::{parse_scene, SceneFileError};
+
+#[test]
+fn parses_objects_lights_and_camera() {
+    let contents = "
+        # two spheres on a floor
+        camera origin 0 -1 3 target 0 0 0 fov 60
+        integrator path
+
+        material red principled base_color 1 0.2 0.2 roughness 0.3 clearcoat 1
+        material floor lambertian albedo 0.4 0.4 0.4
+
+        sphere position 0 0 -2 radius 0.5 material red
+        sphere position 1 0 -2 material red   # default radius
+        plane position 0 1 0 normal 0 -2 0 material floor
+        light position 0 -5 0 force 80
+        light position 0 -5 0 shape rectangle edge_u 1 0 0 edge_v 0 0 1 samples 8
+        directional_light direction 0 2 0 strength 3
+        spot_light position 0 -5 0 cone_angle 20 falloff 2
+    ";
+
+    let scene_file = parse_scene(contents, Path::new("")).expect("valid scene");
+    let scene = &scene_file.scene;
+
+    assert_eq!(scene.spheres.len(), 2);
+    assert_eq!(scene.spheres[0].radius, 0.5);
+    assert_eq!(scene.spheres[1].radius, 1.0);
+    assert_eq!(scene.planes.len(), 1);
+    assert_eq!(scene.planes[0].normal.y, -1.0);
+    assert_eq!(scene.lights.len(), 4);
+    match scene.lights[0] {
+        Light::Point(ref light) => assert_eq!(light.force, 80.0),
+        ref other => panic!("expected a point light, got {:?}", other),
+    }
+    assert_eq!(scene.lights[1].samples(), 8);
+    match scene.lights[2] {
+        Light::Directional(ref light) => assert_eq!((light.direction.y, light.strength), (1.0, 3.0)),
+        ref other => panic!("expected a directional light, got {:?}", other),
+    }
+    match scene.lights[3] {
+        Light::Spot(ref light) => assert_eq!((light.cone_angle, light.falloff), (20.0, 2.0)),
+        ref other => panic!("expected a spot light, got {:?}", other),
+    }
+
+    let view = scene_file.view.expect("camera statement");
+    assert_eq!(view.fov, 60.0);
+    assert_eq!(view.origin.y, -1.0);
+
+    let integrator = scene_file.integrator.expect("integrator statement");
+    assert_eq!(format!("{:?}", integrator), "PathTracer");
+}
+
+#[test]
+fn ambient_occlusion_settings() {
+    let whitted = parse_scene("integrator whitted ao_distance 0.5", Path::new("")).expect("valid scene");
+    assert_eq!(
+        format!("{:?}", whitted.integrator.expect("integrator statement")),
+        "Whitted { ambient_occlusion: Some(AmbientOcclusion { samples: 16, max_distance: 0.5 }) }"
+    );
+
+    let ao = parse_scene("integrator ao ao_samples 64", Path::new("")).expect("valid scene");
+    assert_eq!(
+        format!("{:?}", ao.integrator.expect("integrator statement")),
+        "AmbientOcclusion { samples: 64, max_distance: 1.0 }"
+    );
+}
+
+#[test]
+fn media_and_volumes() {
+    let contents = "
+        medium absorption 0.01 0.02 0.03 anisotropy 0.5
+        material smoke volume scattering 2 2 2
+        sphere position 0 0 0 material smoke
+    ";
+    let scene = parse_scene(contents, Path::new("")).expect("valid scene").scene;
+
+    let medium = scene.medium.expect("medium statement");
+    assert_eq!((medium.absorption.z, medium.scattering.x, medium.anisotropy), (0.03, 0.1, 0.5));
+    let smoke = scene.spheres[0].material.medium().expect("volume material");
+    assert_eq!((smoke.absorption.x, smoke.scattering.y), (0.0, 2.0));
+
+    let scenes = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+    let scene = parse_scene("grid_volume file smoke.vol max 2 1 1 density 3", &scenes).expect("valid scene").scene;
+    let volume = &scene.volumes[0];
+    assert_eq!((volume.grid.width, volume.grid.height, volume.grid.depth), (32, 32, 32));
+    assert_eq!((volume.min.x, volume.max.x, volume.density), (-1.0, 2.0, 3.0));
+}
+
+#[test]
+fn subsurface_materials() {
+    let contents = "
+        material skin subsurface albedo 0.9 0.6 0.5 mean_free_path 0.3 0.1 0.05 ior 1.3
+        sphere position 0 0 0 material skin
+    ";
+    let scene = parse_scene(contents, Path::new("")).expect("valid scene").scene;
+
+    let skin = scene.spheres[0].material.subsurface().expect("subsurface material");
+    assert_eq!((skin.albedo.y, skin.mean_free_path.z, skin.ior, skin.anisotropy), (0.6, 0.05, 1.3, 0.0));
+}
+
+#[test]
+fn errors_name_the_line() {
+    let cases = [
+        ("sphere position 0 0 0 material missing", 1, "undefined material missing"),
+        ("\n\nsphere position 0 0", 3, "missing position"),
+        ("sphere radius 0", 1, "radius must be positive, got 0"),
+        ("quad edge_u 1 0 0 edge_v 2 0 0", 1, "edge_u and edge_v must span an area"),
+        ("light shape disk radius -1", 1, "radius must be positive, got -1"),
+        ("light shape rectangle edge_v 0 0 0", 1, "edge_u and edge_v must span an area"),
+        ("material m metal roughness rough", 1, "invalid number for roughness: rough"),
+        ("material m principled glow 1", 1, "unknown key glow for principled"),
+        ("# fine\ncube size 1", 2, "unknown statement cube"),
+        ("sky turbidity 0.5", 1, "turbidity must be at least 1, got 0.5"),
+        ("sky\nsphere position 0 0 0\nsky turbidity 4", 3, "sky already set on line 1"),
+        ("sky\nenvironment file sky.hdr", 2, "sky already set on line 1"),
+        ("sky\nbackground color 0 0 0", 2, "sky already set on line 1"),
+        ("environment file tests/fixtures/sky.hdr\n\nsky", 3, "environment already set on line 1"),
+        ("integrator radiosity", 1, "unknown integrator radiosity"),
+        ("integrator whitted ao_radius 2", 1, "unknown key ao_radius for whitted"),
+        ("medium anisotropy 1", 1, "anisotropy must be between -1 and 1, got 1"),
+        ("medium absorption 0.1 -0.1 0.1", 1, "absorption can't be negative"),
+        ("material fog volume scattering -1 0 0", 1, "scattering can't be negative"),
+        ("material fog volume density 1", 1, "unknown key density for volume"),
+        ("grid_volume density 2", 1, "missing file"),
+        ("grid_volume file smoke.vol min 0 0 0 max 1 -1 1", 1, "min must be below max on every axis"),
+        ("grid_volume file smoke.vol density -2", 1, "density can't be negative, got -2"),
+        ("grid_volume file smoke.vol albedo 0.5 1.5 0.5", 1, "albedo must be between 0 and 1"),
+        ("material wax subsurface mean_free_path 0.1 0 0.1", 1, "mean_free_path must be positive"),
+        ("material wax subsurface albedo 1.2 1 1", 1, "albedo must be between 0 and 1"),
+    ];
+
+    for &(contents, expected_line, expected_message) in cases.iter() {
+        match parse_scene(contents, Path::new("")) {
+            Err(SceneFileError::Syntax { line, message }) => {
+                assert_eq!(line, expected_line, "{}", contents);
+                assert_eq!(message, expected_message);
+            }
+            other => panic!("{}: expected a syntax error, got {:?}", contents, other),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+extern crate rust_tracer;
+
+use rust_tracer::sky::Sky;
+use rust_tracer::vec3::Vec3;
+
+fn luminance(color: &Vec3) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+#[test]
+fn midday_sky_is_blue_and_brightest_around_the_sun() {
+    let sky = Sky::new(Vec3::new(0.0, -2.0, -1.0), 3.0, Vec3::new(0.3, 0.3, 0.3));
+
+    let zenith = sky.radiance(&Vec3::new(0.0, -1.0, 0.0));
+    assert!(zenith.z > zenith.x, "zenith isn't blue: {:?}", zenith);
+
+    let near_sun = sky.radiance(&Vec3::new(0.0, -2.0, -1.1));
+    let away_from_sun = sky.radiance(&Vec3::new(0.0, -1.0, 1.5));
+    assert!(luminance(&near_sun) > 2.0 * luminance(&away_from_sun));
+}
+
+#[test]
+fn hazier_air_and_lower_sun_redden_the_sunlight() {
+    let high = Sky::new(Vec3::new(0.0, -4.0, -1.0), 2.5, Vec3::new(0.3, 0.3, 0.3)).sun_light();
+    let low = Sky::new(Vec3::new(0.0, -0.1, -1.0), 2.5, Vec3::new(0.3, 0.3, 0.3)).sun_light();
+    let hazy = Sky::new(Vec3::new(0.0, -4.0, -1.0), 8.0, Vec3::new(0.3, 0.3, 0.3)).sun_light();
+
+    // The light travels away from the sun
+    assert!(high.direction.y > 0.0);
+    assert!(high.color.x < 1.0 && high.color.z < high.color.x);
+    assert!(low.color.z / low.color.x < high.color.z / high.color.x);
+    assert!(hazy.color.y < high.color.y);
+}
+
+#[test]
+fn ground_reflects_its_albedo_of_the_light() {
+    let dark = Sky::new(Vec3::new(1.0, -2.0, 0.0), 3.0, Vec3::new(0.1, 0.1, 0.1));
+    let mut bright = Sky::new(Vec3::new(1.0, -2.0, 0.0), 3.0, Vec3::new(0.2, 0.2, 0.2));
+    let down = Vec3::new(0.3, 1.0, 0.2);
+
+    let ratio = bright.radiance(&down).y / dark.radiance(&down).y;
+    assert!((ratio - 2.0).abs() < 1e-9);
+
+    // Same everywhere below the horizon, and brighter with a brighter sun
+    assert_eq!(bright.radiance(&down).x, bright.radiance(&Vec3::new(-1.0, 0.1, 0.0)).x);
+    let before = bright.radiance(&down).x;
+    bright.sun_strength *= 2.0;
+    assert!(bright.radiance(&down).x > before);
+}
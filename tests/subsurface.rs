@@ -0,0 +1,141 @@
+extern crate rust_tracer;
+
+use std::sync::Arc;
+
+use rust_tracer::light::{Light, PointLight};
+use rust_tracer::lighting::Mis;
+use rust_tracer::material::{Lambertian, Material, Subsurface};
+use rust_tracer::path_tracer::path_radiance;
+use rust_tracer::random_walk::{random_walk, WalkExit};
+use rust_tracer::ray::Ray;
+use rust_tracer::sampler::Sampler;
+use rust_tracer::scene::{Scene, Sphere};
+use rust_tracer::vec3::Vec3;
+
+fn grey(value: f64) -> Vec3 {
+    Vec3::new(value, value, value)
+}
+
+// A ball big enough to look flat, its top at the origin, lit from straight above
+fn big_ball(material: Arc<dyn Material + Send + Sync>) -> Scene {
+    Scene {
+        lights: vec![Light::Point(PointLight {
+            position: Vec3::new(0.0, -3.0, 0.0),
+            color: grey(1.0),
+            force: 10.0,
+            profile: None,
+        })],
+        spheres: vec![Sphere { position: Vec3::new(0.0, 100.0, 0.0), radius: 100.0, material }],
+        ..Scene::default()
+    }
+}
+
+// The normal given to a walk points out of the ball, up at the light
+fn walk_in_at_the_top(material: &Subsurface, scene: &Scene, sampler: &mut Sampler) -> Option<WalkExit> {
+    random_walk(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, -1.0, 0.0), material, scene, sampler)
+}
+
+#[test]
+fn medium_follows_the_parameters() {
+    let material = Subsurface::new(Vec3::new(0.0, 0.5, 1.0), Vec3::new(0.5, 0.1, 2.0));
+    let medium = material.medium();
+
+    let extinction = medium.extinction();
+    assert!((extinction.x - 2.0).abs() < 1e-9 && (extinction.y - 10.0).abs() < 1e-9 && (extinction.z - 0.5).abs() < 1e-9);
+    // Light needs many bounces to get out, each has to lose less than the albedo says
+    assert!(medium.scattering.x.abs() < 1e-3 && medium.scattering.z > 0.5 * 0.999);
+    assert!(medium.scattering.y / extinction.y > 0.5 && medium.scattering.y / extinction.y < 1.0);
+}
+
+#[test]
+fn walks_come_out_of_the_surface() {
+    let material = Subsurface::new(grey(0.9), grey(0.05));
+    let scene = big_ball(Arc::new(material.clone()));
+    let centre = &scene.spheres[0].position;
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    let mut exits = 0;
+    for _ in 0..1000 {
+        if let Some(exit) = walk_in_at_the_top(&material, &scene, &mut sampler) {
+            let outward = (&exit.point - centre).unit();
+            assert!(((&exit.point - centre).mag() - 100.0).abs() < 1e-6);
+            assert!(exit.normal.dot(&outward) > 0.999);
+            assert!(exit.weight.max_component() <= 1.0 + 1e-9);
+            exits += 1;
+        }
+    }
+    assert!(exits > 500, "{} walks out of 1000", exits);
+}
+
+// Given the normal on the inside, the walk heads off up into the empty space above
+#[test]
+fn walks_start_against_the_normal() {
+    let material = Subsurface::new(grey(0.9), grey(0.05));
+    let scene = big_ball(Arc::new(material.clone()));
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    for _ in 0..1000 {
+        assert!(random_walk(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0), &material, &scene, &mut sampler).is_none());
+    }
+}
+
+#[test]
+fn black_objects_let_nothing_out() {
+    let material = Subsurface::new(grey(0.0), grey(0.05));
+    let scene = big_ball(Arc::new(material.clone()));
+    let mut sampler = Sampler::new(0, 0, 0);
+
+    for _ in 0..1000 {
+        if let Some(exit) = walk_in_at_the_top(&material, &scene, &mut sampler) {
+            assert!(exit.weight.max_component() < 1e-9);
+        }
+    }
+}
+
+// Over all the walks into a thick object, about the albedo comes out, also when the
+// channels get different mean free paths
+#[test]
+fn walks_give_back_about_the_albedo() {
+    let albedo = Vec3::new(0.3, 0.6, 0.85);
+    for mean_free_path in [grey(0.05), Vec3::new(0.08, 0.04, 0.02)].iter() {
+        let material = Subsurface::new(albedo.clone(), mean_free_path.clone());
+        let scene = big_ball(Arc::new(material.clone()));
+        let mut sampler = Sampler::new(0, 0, 0);
+
+        let samples = 20_000;
+        let mut total = grey(0.0);
+        for _ in 0..samples {
+            if let Some(exit) = walk_in_at_the_top(&material, &scene, &mut sampler) {
+                total = total + exit.weight;
+            }
+        }
+        let mean = total * (1.0 / samples as f64);
+        for &(got, expected) in [(mean.x, albedo.x), (mean.y, albedo.y), (mean.z, albedo.z)].iter() {
+            assert!((got - expected).abs() < 0.06, "{:?}: {:?}", mean_free_path, mean);
+        }
+    }
+}
+
+// Light hardly spreads in a dense object, which ends up about as bright as a diffuse one
+#[test]
+fn dense_objects_look_diffuse() {
+    let albedo = Vec3::new(0.8, 0.5, 0.2);
+    let ray = || Ray { origin: Vec3::new(0.0, -1.0, -1.0), direction: Vec3::new(0.0, 1.0, 1.0).unit() };
+    let translucent = big_ball(Arc::new(Subsurface::new(albedo.clone(), grey(0.005))));
+    let diffuse = big_ball(Arc::new(Lambertian { albedo }));
+
+    let samples = 5000;
+    let mut translucent_total = grey(0.0);
+    let mut diffuse_total = grey(0.0);
+    for sample in 0..samples {
+        translucent_total = translucent_total + path_radiance(ray(), &translucent, Mis::PowerHeuristic, &mut Sampler::new(0, 0, sample));
+        diffuse_total = diffuse_total + path_radiance(ray(), &diffuse, Mis::PowerHeuristic, &mut Sampler::new(0, 0, sample));
+    }
+
+    let translucent = [translucent_total.x, translucent_total.y, translucent_total.z];
+    let diffuse = [diffuse_total.x, diffuse_total.y, diffuse_total.z];
+    for channel in 0..3 {
+        let ratio = translucent[channel] / diffuse[channel];
+        assert!(ratio > 0.85 && ratio < 1.1, "{:?} against {:?}", translucent_total, diffuse_total);
+    }
+}
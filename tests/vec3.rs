@@ -0,0 +1,162 @@
+extern crate proptest;
+extern crate rust_tracer;
+
+use proptest::prelude::*;
+use rust_tracer::vec3::Vec3;
+
+const EPSILON: f64 = 1e-9;
+
+fn assert_close(a: &Vec3, b: &Vec3, tolerance: f64) {
+    let distance = (a - b).mag();
+    assert!(distance <= tolerance, "{:?} != {:?} (distance {})", a, b, distance);
+}
+
+fn component() -> impl Strategy<Value = f64> {
+    -100.0..100.0f64
+}
+
+fn any_vec3() -> impl Strategy<Value = Vec3> {
+    (component(), component(), component()).prop_map(|(x, y, z)| Vec3::new(x, y, z))
+}
+
+fn unit_vec3() -> impl Strategy<Value = Vec3> {
+    any_vec3().prop_filter("needs a direction", |v| v.mag() > 1e-3).prop_map(|v| v.unit())
+}
+
+// Incident direction and the normal of the surface it hits, facing each other
+fn incident_and_normal() -> impl Strategy<Value = (Vec3, Vec3)> {
+    (unit_vec3(), unit_vec3())
+        .prop_filter("must not graze the surface", |(i, n)| i.dot(n).abs() > 1e-3)
+        .prop_map(|(i, n)| if i.dot(&n) > 0.0 { (i, &n * -1.0) } else { (i, n) })
+}
+
+fn sin_angle(a: &Vec3, b: &Vec3) -> f64 {
+    a.cross(b).mag()
+}
+
+#[test]
+fn operators() {
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(4.0, -5.0, 6.0);
+
+    assert_close(&(&a + &b), &Vec3::new(5.0, -3.0, 9.0), EPSILON);
+    assert_close(&(&a - &b), &Vec3::new(-3.0, 7.0, -3.0), EPSILON);
+    assert_close(&(&a * &b), &Vec3::new(4.0, -10.0, 18.0), EPSILON);
+    assert_close(&(&a / &b), &Vec3::new(0.25, -0.4, 0.5), EPSILON);
+    assert_close(&(a.clone() + 1.0), &Vec3::new(2.0, 3.0, 4.0), EPSILON);
+    assert_close(&(a.clone() - 1.0), &Vec3::new(0.0, 1.0, 2.0), EPSILON);
+    assert_close(&(&a * 2.0), &Vec3::new(2.0, 4.0, 6.0), EPSILON);
+    assert_close(&(a.clone() / 2.0), &Vec3::new(0.5, 1.0, 1.5), EPSILON);
+    assert_eq!(a.dot(&b), 12.0);
+}
+
+#[test]
+fn cross_of_axes() {
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+
+    assert_close(&x.cross(&y), &Vec3::new(0.0, 0.0, 1.0), EPSILON);
+    assert_close(&y.cross(&x), &Vec3::new(0.0, 0.0, -1.0), EPSILON);
+}
+
+#[test]
+fn unit_of_zero_is_zero() {
+    assert_close(&Vec3::new(0.0, 0.0, 0.0).unit(), &Vec3::new(0.0, 0.0, 0.0), 0.0);
+}
+
+#[test]
+fn mix_endpoints() {
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(-1.0, 0.0, 5.0);
+
+    assert_close(&a.mix(&b, 0.0), &a, EPSILON);
+    assert_close(&a.mix(&b, 1.0), &b, EPSILON);
+    assert_close(&a.mix(&b, 0.5), &Vec3::new(0.0, 1.0, 4.0), EPSILON);
+}
+
+#[test]
+fn refract_head_on_keeps_direction() {
+    let incident = Vec3::new(0.0, 0.0, -1.0);
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    let refracted = incident.refract(&normal, 1.0 / 1.52).expect("no TIR head on");
+    assert_close(&refracted, &incident, EPSILON);
+}
+
+#[test]
+fn refract_past_critical_angle_is_total_internal_reflection() {
+    // Leaving glass into air at 60 degrees, the critical angle is ~41 degrees
+    let angle: f64 = 60.0_f64.to_radians();
+    let incident = Vec3::new(angle.sin(), 0.0, -angle.cos());
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    assert!(incident.refract(&normal, 1.52).is_none());
+}
+
+proptest! {
+    #[test]
+    fn cross_is_orthogonal(a in any_vec3(), b in any_vec3()) {
+        let c = a.cross(&b);
+        let scale = a.mag() * b.mag() * (a.mag() + b.mag()) + 1.0;
+
+        prop_assert!(c.dot(&a).abs() <= 1e-9 * scale);
+        prop_assert!(c.dot(&b).abs() <= 1e-9 * scale);
+    }
+
+    #[test]
+    fn cross_is_anticommutative(a in any_vec3(), b in any_vec3()) {
+        assert_close(&a.cross(&b), &(&b.cross(&a) * -1.0), 1e-9 * (a.mag() * b.mag() + 1.0));
+    }
+
+    #[test]
+    fn unit_has_length_one(v in any_vec3()) {
+        prop_assume!(v.mag() > 1e-6);
+        prop_assert!((v.unit().mag() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unit_keeps_direction(v in any_vec3()) {
+        prop_assume!(v.mag() > 1e-6);
+        assert_close(&(&v.unit() * v.mag()), &v, 1e-9 * v.mag());
+    }
+
+    #[test]
+    fn reflect_is_an_involution(v in any_vec3(), n in unit_vec3()) {
+        assert_close(&v.reflect(&n).reflect(&n), &v, 1e-9 * (v.mag() + 1.0));
+    }
+
+    #[test]
+    fn reflect_preserves_length_and_mirrors_normal_component(v in any_vec3(), n in unit_vec3()) {
+        let r = v.reflect(&n);
+        let tolerance = 1e-9 * (v.mag() + 1.0);
+
+        prop_assert!((r.mag() - v.mag()).abs() <= tolerance);
+        prop_assert!((r.dot(&n) + v.dot(&n)).abs() <= tolerance);
+    }
+
+    #[test]
+    fn refract_obeys_snells_law((incident, normal) in incident_and_normal(), eta in 0.3..3.0f64) {
+        match incident.refract(&normal, eta) {
+            Some(refracted) => {
+                prop_assert!((refracted.mag() - 1.0).abs() < 1e-9);
+                // Crosses to the other side of the surface
+                prop_assert!(refracted.dot(&normal) <= 1e-9);
+                // eta is n1 / n2, so sin(t) = eta * sin(i)
+                let expected_sin = eta * sin_angle(&incident, &normal);
+                prop_assert!((sin_angle(&refracted, &normal) - expected_sin).abs() < 1e-9);
+                // Stays in the plane of incidence
+                prop_assert!(refracted.dot(&incident.cross(&normal)).abs() < 1e-9);
+            }
+            None => {
+                // Only past the critical angle
+                prop_assert!(eta * sin_angle(&incident, &normal) > 1.0 - 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn mix_is_linear(a in any_vec3(), b in any_vec3(), ratio in 0.0..1.0f64) {
+        let expected = &(&a * (1.0 - ratio)) + &(&b * ratio);
+        assert_close(&a.mix(&b, ratio), &expected, 1e-9 * (a.mag() + b.mag() + 1.0));
+    }
+}